@@ -304,7 +304,7 @@ impl ClientEntity {
                         },
                     );
 
-                let mut migrator = builder.build().expect("builder failed");
+                let migrator = builder.build().expect("builder failed");
                 match params.operation {
                     launchdarkly_server_sdk::Operation::Read => {
                         let result = migrator
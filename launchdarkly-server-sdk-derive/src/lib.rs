@@ -0,0 +1,342 @@
+//! Derive macros for the [LaunchDarkly Server-Side SDK](https://docs.rs/launchdarkly-server-sdk).
+//!
+//! These macros are re-exported by the SDK when its `derive` feature is enabled, and should be
+//! used through that re-export rather than by depending on this crate directly.
+
+#![deny(rustdoc::missing_crate_level_docs)]
+#![deny(missing_docs)]
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::spanned::Spanned;
+use syn::{
+    parse_macro_input, Attribute, Data, DeriveInput, Error, Field, Fields, LitStr, Path, Result,
+    Type,
+};
+
+/// Derives `TryFrom<&T> for Context`, mapping the fields of a struct onto the attributes of a
+/// LaunchDarkly context.
+///
+/// Every field which is not otherwise marked becomes a custom attribute named after the field,
+/// and must be convertible into an `AttributeValue` once cloned. Fields of type `Option<_>` are
+/// only set when they hold a value.
+///
+/// The kind of the context may be given on the struct with `#[context(kind = "org")]`; if it is
+/// not, and no field is marked as the kind, the context is of the default `user` kind.
+///
+/// The generated code refers to the SDK as `::launchdarkly_server_sdk`. If the SDK is depended on
+/// under another name, or re-exported by another crate, give its path on the struct with
+/// `#[context(crate = "path::to::sdk")]`.
+///
+/// Fields may be marked with the following attributes:
+///
+/// - `#[context(key)]`: the context key. Exactly one field must be the key, and it is converted
+///   with `ToString`.
+/// - `#[context(kind)]`: the context kind, converted with `ToString`.
+/// - `#[context(name)]`: the context name, converted with `ToString`.
+/// - `#[context(anonymous)]`: a `bool` which marks the context as anonymous.
+/// - `#[context(private)]`: marks the attribute as private. May be combined with `name` or
+///   `rename`.
+/// - `#[context(rename = "...")]`: names the attribute differently from the field.
+/// - `#[context(skip)]`: leaves the field out of the context.
+/// - `#[context(nested)]`: a value which itself converts into a context, such as another struct
+///   deriving `IntoContext`. The resulting context is a multi-context of this struct's context
+///   along with every nested context.
+///
+/// ```ignore
+/// use launchdarkly_server_sdk::{Context, IntoContext};
+///
+/// #[derive(IntoContext)]
+/// #[context(kind = "org")]
+/// struct Organization {
+///     #[context(key)]
+///     id: u64,
+///     #[context(name)]
+///     name: String,
+/// }
+///
+/// #[derive(IntoContext)]
+/// struct User {
+///     #[context(key)]
+///     id: String,
+///     #[context(private)]
+///     email: String,
+///     #[context(rename = "plan")]
+///     tier: Option<String>,
+///     #[context(nested)]
+///     organization: Organization,
+///     #[context(skip)]
+///     session_token: String,
+/// }
+///
+/// let context = Context::try_from(&user)?;
+/// ```
+#[proc_macro_derive(IntoContext, attributes(context))]
+pub fn derive_into_context(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+#[derive(Default)]
+struct FieldOptions {
+    key: bool,
+    kind: bool,
+    name: bool,
+    anonymous: bool,
+    private: bool,
+    skip: bool,
+    nested: bool,
+    rename: Option<LitStr>,
+}
+
+impl FieldOptions {
+    fn parse(field: &Field) -> Result<Self> {
+        let mut options = FieldOptions::default();
+        for attr in context_attributes(&field.attrs) {
+            attr.parse_nested_meta(|meta| {
+                let flag = if meta.path.is_ident("key") {
+                    &mut options.key
+                } else if meta.path.is_ident("kind") {
+                    &mut options.kind
+                } else if meta.path.is_ident("name") {
+                    &mut options.name
+                } else if meta.path.is_ident("anonymous") {
+                    &mut options.anonymous
+                } else if meta.path.is_ident("private") {
+                    &mut options.private
+                } else if meta.path.is_ident("skip") {
+                    &mut options.skip
+                } else if meta.path.is_ident("nested") {
+                    &mut options.nested
+                } else if meta.path.is_ident("rename") {
+                    options.rename = Some(meta.value()?.parse()?);
+                    return Ok(());
+                } else {
+                    return Err(meta.error("unsupported context attribute"));
+                };
+                *flag = true;
+                Ok(())
+            })?;
+        }
+
+        let roles = [
+            options.key,
+            options.kind,
+            options.name,
+            options.anonymous,
+            options.skip,
+            options.nested,
+        ];
+        if roles.iter().filter(|role| **role).count() > 1 {
+            return Err(Error::new(
+                field.span(),
+                "a field may only be one of key, kind, name, anonymous, skip or nested",
+            ));
+        }
+        if options.private && !(options.name || options.is_attribute()) {
+            return Err(Error::new(
+                field.span(),
+                "only the name and custom attributes may be private",
+            ));
+        }
+        if options.rename.is_some() && !options.is_attribute() {
+            return Err(Error::new(
+                field.span(),
+                "only custom attributes may be renamed",
+            ));
+        }
+
+        Ok(options)
+    }
+
+    fn is_attribute(&self) -> bool {
+        !(self.key || self.kind || self.name || self.anonymous || self.skip || self.nested)
+    }
+}
+
+fn context_attributes(attrs: &[Attribute]) -> impl Iterator<Item = &Attribute> {
+    attrs.iter().filter(|attr| attr.path().is_ident("context"))
+}
+
+#[derive(Default)]
+struct StructOptions {
+    kind: Option<LitStr>,
+    sdk: Option<Path>,
+}
+
+impl StructOptions {
+    fn parse(input: &DeriveInput) -> Result<Self> {
+        let mut options = StructOptions::default();
+        for attr in context_attributes(&input.attrs) {
+            attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident("kind") {
+                    options.kind = Some(meta.value()?.parse()?);
+                    Ok(())
+                } else if meta.path.is_ident("crate") {
+                    let path: LitStr = meta.value()?.parse()?;
+                    options.sdk = Some(path.parse()?);
+                    Ok(())
+                } else {
+                    Err(meta.error("unsupported context attribute"))
+                }
+            })?;
+        }
+        Ok(options)
+    }
+}
+
+fn is_option(ty: &Type) -> bool {
+    match ty {
+        Type::Path(path) => path
+            .path
+            .segments
+            .last()
+            .is_some_and(|segment| segment.ident == "Option"),
+        _ => false,
+    }
+}
+
+// Applies `apply` to the field's value, skipping fields of type `Option` which hold no value.
+fn with_value(field: &Field, apply: impl Fn(TokenStream2) -> TokenStream2) -> TokenStream2 {
+    let ident = &field.ident;
+    if is_option(&field.ty) {
+        let body = apply(quote!(field));
+        quote! {
+            if let ::core::option::Option::Some(field) = &value.#ident {
+                #body
+            }
+        }
+    } else {
+        apply(quote!((&value.#ident)))
+    }
+}
+
+fn expand(input: DeriveInput) -> Result<TokenStream2> {
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => {
+                return Err(Error::new(
+                    input.span(),
+                    "IntoContext can only be derived for structs with named fields",
+                ))
+            }
+        },
+        _ => {
+            return Err(Error::new(
+                input.span(),
+                "IntoContext can only be derived for structs",
+            ))
+        }
+    };
+
+    let options = StructOptions::parse(&input)?;
+    let sdk = match options.sdk {
+        Some(path) => quote!(#path),
+        None => quote!(::launchdarkly_server_sdk),
+    };
+    let mut key = None;
+    let mut kind = options.kind.map(|kind| quote!(builder.kind(#kind);));
+    let mut setters = Vec::new();
+    let mut nested = Vec::new();
+
+    for field in fields {
+        let options = FieldOptions::parse(field)?;
+        let ident = field.ident.as_ref().expect("named fields have identifiers");
+
+        if options.skip {
+            continue;
+        } else if options.key {
+            if key.is_some() {
+                return Err(Error::new(field.span(), "only one field may be the key"));
+            }
+            key = Some(quote!(::std::string::ToString::to_string(&value.#ident)));
+        } else if options.kind {
+            if kind.is_some() {
+                return Err(Error::new(
+                    field.span(),
+                    "the kind may only be given once, on either the struct or a field",
+                ));
+            }
+            kind = Some(with_value(
+                field,
+                |value| quote!(builder.kind(::std::string::ToString::to_string(#value));),
+            ));
+        } else if options.name {
+            setters.push(with_value(
+                field,
+                |value| quote!(builder.name(::std::string::ToString::to_string(#value));),
+            ));
+            if options.private {
+                setters.push(quote!(builder.add_private_attribute("name");));
+            }
+        } else if options.anonymous {
+            setters.push(with_value(
+                field,
+                |value| quote!(builder.anonymous(*#value);),
+            ));
+        } else if options.nested {
+            nested.push(with_value(field, |value| {
+                quote! {
+                    multi.add_context(<#sdk::Context as ::core::convert::TryFrom<_>>::try_from(
+                        #value,
+                    )?);
+                }
+            }));
+        } else {
+            let attribute = options
+                .rename
+                .unwrap_or_else(|| LitStr::new(&ident.to_string(), ident.span()));
+            setters.push(with_value(field, |value| {
+                quote! {
+                    builder.set_value(
+                        #attribute,
+                        #sdk::AttributeValue::from(::core::clone::Clone::clone(#value)),
+                    );
+                }
+            }));
+            if options.private {
+                setters.push(quote!(builder.add_private_attribute(#attribute);));
+            }
+        }
+    }
+
+    let key = key.ok_or_else(|| {
+        Error::new(
+            input.span(),
+            "IntoContext requires a field marked with #[context(key)]",
+        )
+    })?;
+
+    let build = if nested.is_empty() {
+        quote!(builder.build())
+    } else {
+        quote! {
+            let mut multi = #sdk::MultiContextBuilder::new();
+            multi.add_context(builder.build()?);
+            #(#nested)*
+            multi.build()
+        }
+    };
+
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    Ok(quote! {
+        impl #impl_generics ::core::convert::TryFrom<&#name #ty_generics> for #sdk::Context
+        #where_clause
+        {
+            type Error = ::std::string::String;
+
+            fn try_from(value: &#name #ty_generics) -> ::core::result::Result<Self, Self::Error> {
+                let mut builder = #sdk::ContextBuilder::new(#key);
+                #kind
+                #(#setters)*
+                #build
+            }
+        }
+    })
+}
@@ -0,0 +1,45 @@
+//! Generates a Rust module with a typed accessor for every flag in a LaunchDarkly environment
+//! snapshot.
+//!
+//! Usage: `ld-flag-codegen <snapshot.json> [output.rs]`
+//!
+//! The module is written to standard output if no output path is given.
+
+use std::env;
+use std::fs;
+use std::process::exit;
+
+use launchdarkly_server_sdk::generate_flag_module;
+
+fn main() {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let (snapshot_path, module_path) = match args.as_slice() {
+        [snapshot] => (snapshot, None),
+        [snapshot, module] => (snapshot, Some(module)),
+        _ => {
+            eprintln!("Usage: ld-flag-codegen <snapshot.json> [output.rs]");
+            exit(2);
+        }
+    };
+
+    let module = fs::read_to_string(snapshot_path)
+        .map_err(Into::into)
+        .and_then(|snapshot| generate_flag_module(&snapshot));
+    let module = match module {
+        Ok(module) => module,
+        Err(e) => {
+            eprintln!("Failed to generate flags from {snapshot_path}: {e}");
+            exit(1);
+        }
+    };
+
+    match module_path {
+        Some(path) => {
+            if let Err(e) = fs::write(path, module) {
+                eprintln!("Failed to write {path}: {e}");
+                exit(1);
+            }
+        }
+        None => print!("{module}"),
+    }
+}
@@ -0,0 +1,178 @@
+//! Evaluates LaunchDarkly flags for a context without starting a client, to show what the
+//! context would receive and why.
+//!
+//! Usage: `ld-flag-eval [options] --context <json> [flag-key...]`
+//!
+//! Flag data is loaded from `--snapshot <file>`, or fetched once with `--sdk-key <key>` (or the
+//! `LAUNCHDARKLY_SDK_KEY` environment variable). Every flag is evaluated if no keys are given.
+//! Warnings and errors logged by the SDK, such as why flag data could not be fetched, are written
+//! to stderr.
+
+use std::env;
+use std::fs;
+use std::process::exit;
+
+use hyper_rustls::HttpsConnectorBuilder;
+use launchdarkly_server_sdk::{Context, FlagDetailConfig, FlagInspector};
+use log::{Level, LevelFilter, Log, Metadata, Record};
+
+const USAGE: &str = "Usage: ld-flag-eval [options] --context <json> [flag-key...]
+
+Options:
+    --snapshot <file>     Load flag data from a snapshot file
+    --sdk-key <key>       Fetch flag data with an SDK key (default: $LAUNCHDARKLY_SDK_KEY)
+    --base-uri <url>      Polling base URL (default: https://sdk.launchdarkly.com)
+    --context <json>      The context as JSON, or @<file> to read it from a file
+    --json                Print each evaluation as a line of JSON
+    --full                Print the FlagDetail for every flag, as used to bootstrap client SDKs
+    --with-reasons        Include reasons in --full output
+    --client-side-only    Only include client-side flags in --full output";
+
+#[derive(Default)]
+struct Options {
+    snapshot: Option<String>,
+    sdk_key: Option<String>,
+    base_uri: Option<String>,
+    context: Option<String>,
+    json: bool,
+    full: bool,
+    flag_detail_config: FlagDetailConfig,
+    flag_keys: Vec<String>,
+}
+
+// The SDK only describes why flag data could not be fetched in its logs.
+struct StderrLogger;
+
+impl Log for StderrLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= Level::Warn
+    }
+
+    fn log(&self, record: &Record) {
+        if self.enabled(record.metadata()) {
+            eprintln!("{}: {}", record.level(), record.args());
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+static LOGGER: StderrLogger = StderrLogger;
+
+fn usage_error(message: &str) -> ! {
+    eprintln!("{message}\n\n{USAGE}");
+    exit(2);
+}
+
+fn parse_options() -> Options {
+    let mut options = Options::default();
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = |name: &str| {
+            args.next()
+                .unwrap_or_else(|| usage_error(&format!("{name} requires a value")))
+        };
+        match arg.as_str() {
+            "--snapshot" => options.snapshot = Some(value(&arg)),
+            "--sdk-key" => options.sdk_key = Some(value(&arg)),
+            "--base-uri" => options.base_uri = Some(value(&arg)),
+            "--context" => options.context = Some(value(&arg)),
+            "--json" => options.json = true,
+            "--full" => options.full = true,
+            "--with-reasons" => {
+                options.flag_detail_config.with_reasons();
+            }
+            "--client-side-only" => {
+                options.flag_detail_config.client_side_only();
+            }
+            "-h" | "--help" => {
+                println!("{USAGE}");
+                exit(0);
+            }
+            flag if flag.starts_with("--") => usage_error(&format!("Unknown option {flag}")),
+            key => options.flag_keys.push(key.to_string()),
+        }
+    }
+    options
+}
+
+fn fail(message: String) -> ! {
+    eprintln!("{message}");
+    exit(1);
+}
+
+fn read_context(context: &str) -> Context {
+    let json = match context.strip_prefix('@') {
+        Some(path) => fs::read_to_string(path)
+            .unwrap_or_else(|e| fail(format!("Failed to read context from {path}: {e}"))),
+        None => context.to_string(),
+    };
+    serde_json::from_str(&json).unwrap_or_else(|e| fail(format!("Invalid context: {e}")))
+}
+
+fn load_inspector(options: &Options) -> FlagInspector {
+    if let Some(path) = &options.snapshot {
+        return FlagInspector::from_snapshot_file(path)
+            .unwrap_or_else(|e| fail(format!("Failed to load {path}: {e}")));
+    }
+
+    let sdk_key = options
+        .sdk_key
+        .clone()
+        .or_else(|| env::var("LAUNCHDARKLY_SDK_KEY").ok())
+        .unwrap_or_else(|| usage_error("Please provide --snapshot or --sdk-key"));
+    let base_uri = options
+        .base_uri
+        .as_deref()
+        .unwrap_or("https://sdk.launchdarkly.com");
+    let connector = HttpsConnectorBuilder::new()
+        .with_native_roots()
+        .https_or_http()
+        .enable_http1()
+        .enable_http2()
+        .build();
+
+    let runtime = tokio::runtime::Runtime::new()
+        .unwrap_or_else(|e| fail(format!("Failed to start runtime: {e}")));
+    runtime
+        .block_on(FlagInspector::fetch(base_uri, &sdk_key, connector))
+        .unwrap_or_else(|e| fail(format!("Failed to fetch flag data from {base_uri}: {e}")))
+}
+
+fn main() {
+    if log::set_logger(&LOGGER).is_ok() {
+        log::set_max_level(LevelFilter::Warn);
+    }
+
+    let options = parse_options();
+    let context = options
+        .context
+        .as_deref()
+        .map(read_context)
+        .unwrap_or_else(|| usage_error("Please provide --context"));
+    let inspector = load_inspector(&options);
+
+    if options.full {
+        let detail = inspector.all_flags_detail(&context, options.flag_detail_config);
+        let json = serde_json::to_string_pretty(&detail)
+            .unwrap_or_else(|e| fail(format!("Failed to serialize flag detail: {e}")));
+        println!("{json}");
+        return;
+    }
+
+    let flag_keys = if options.flag_keys.is_empty() {
+        inspector.flag_keys()
+    } else {
+        options.flag_keys.clone()
+    };
+    for flag_key in flag_keys {
+        let evaluation = inspector.evaluate(&context, &flag_key);
+        if options.json {
+            let json = serde_json::to_string(&evaluation)
+                .unwrap_or_else(|e| fail(format!("Failed to serialize evaluation: {e}")));
+            println!("{json}");
+        } else {
+            println!("{evaluation}");
+        }
+    }
+}
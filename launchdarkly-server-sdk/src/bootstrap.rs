@@ -0,0 +1,584 @@
+use std::convert::Infallible;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::task::{Context as TaskContext, Poll};
+use std::time::Duration;
+
+use futures::future::BoxFuture;
+use futures::FutureExt;
+use hyper::body::{Bytes, HttpBody};
+use hyper::header::{ACCEPT, CACHE_CONTROL, CONTENT_LENGTH, CONTENT_TYPE};
+use hyper::service::Service;
+use hyper::{Body, Method, Request, Response, StatusCode};
+use launchdarkly_server_sdk_evaluation::Context;
+use serde_json::Value;
+
+use crate::{Client, FlagDetailConfig};
+
+const DEFAULT_UPDATE_INTERVAL: Duration = Duration::from_secs(1);
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(180);
+const DEFAULT_MAX_BODY_SIZE: usize = 64 * 1024;
+const DEFAULT_MAX_STREAMS: usize = 1000;
+
+/// Contains methods for configuring a [BootstrapService].
+#[derive(Clone)]
+pub struct BootstrapServiceBuilder {
+    flag_detail_config: FlagDetailConfig,
+    secure_mode_hash: bool,
+    streaming: bool,
+    update_interval: Duration,
+    max_body_size: usize,
+    max_streams: usize,
+}
+
+impl BootstrapServiceBuilder {
+    /// Create a new instance of the [BootstrapServiceBuilder] with default values.
+    pub fn new() -> Self {
+        Self {
+            flag_detail_config: FlagDetailConfig::new(),
+            secure_mode_hash: true,
+            streaming: false,
+            update_interval: DEFAULT_UPDATE_INTERVAL,
+            max_body_size: DEFAULT_MAX_BODY_SIZE,
+            max_streams: DEFAULT_MAX_STREAMS,
+        }
+    }
+
+    /// Sets the configuration used to evaluate flags for each context. For usage see
+    /// [FlagDetailConfig].
+    pub fn flag_detail_config(&mut self, flag_detail_config: FlagDetailConfig) -> &mut Self {
+        self.flag_detail_config = flag_detail_config;
+        self
+    }
+
+    /// Whether responses include the context's secure mode hash. By default, this is true.
+    pub fn secure_mode_hash(&mut self, enabled: bool) -> &mut Self {
+        self.secure_mode_hash = enabled;
+        self
+    }
+
+    /// Whether requests which accept `text/event-stream` receive a stream of updated flag state.
+    /// By default, this is false.
+    ///
+    /// Each open stream evaluates every flag for its context, in the same way as
+    /// [Client::all_flags_detail], whenever the client's flag data changes. With data stores
+    /// which cannot tell when their data changes, such as persistent stores, streams instead
+    /// re-evaluate at every update interval. See [BootstrapServiceBuilder::update_interval] and
+    /// [BootstrapServiceBuilder::max_streams].
+    pub fn streaming(&mut self, enabled: bool) -> &mut Self {
+        self.streaming = enabled;
+        self
+    }
+
+    /// Sets how often streams check whether the client's flag data has changed, and whether
+    /// their requester has disconnected.
+    ///
+    /// The default is one second.
+    pub fn update_interval(&mut self, update_interval: Duration) -> &mut Self {
+        self.update_interval = update_interval;
+        self
+    }
+
+    /// Sets the largest `POST` body, in bytes, which is read as a context. Larger requests receive
+    /// `413 Payload Too Large`.
+    ///
+    /// The default is 64 KiB.
+    pub fn max_body_size(&mut self, max_body_size: usize) -> &mut Self {
+        self.max_body_size = max_body_size;
+        self
+    }
+
+    /// Sets the largest number of streams which may be open at once. Further streaming requests
+    /// receive `503 Service Unavailable` until an open stream closes.
+    ///
+    /// The default is 1000.
+    pub fn max_streams(&mut self, max_streams: usize) -> &mut Self {
+        self.max_streams = max_streams;
+        self
+    }
+
+    /// Create a service which evaluates flags with the given client.
+    pub fn build(&self, client: Arc<Client>) -> BootstrapService {
+        BootstrapService {
+            client,
+            settings: self.clone(),
+            streams: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+}
+
+impl Default for BootstrapServiceBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// An HTTP service which returns the payload used to bootstrap LaunchDarkly's client-side SDKs.
+///
+/// The context is given either as the JSON body of a `POST` request, or as the base64url-encoded
+/// JSON in the final path segment of a `GET` request, so that the service can be mounted at any
+/// path. Responses are JSON objects holding the [crate::FlagDetail] for the context under `flags`
+/// and, unless disabled, the context's secure mode hash under `secureModeHash`.
+///
+/// If streaming is enabled, requests which accept `text/event-stream` instead receive a `put`
+/// event holding the same payload, followed by another whenever the context's flag state changes.
+///
+/// # Examples
+///
+/// ```no_run
+/// # use std::convert::Infallible;
+/// # use std::sync::Arc;
+/// # use hyper::service::make_service_fn;
+/// # use launchdarkly_server_sdk::{BootstrapServiceBuilder, Client, ConfigBuilder};
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+///     let client = Arc::new(Client::build(ConfigBuilder::new("sdk-key").build()?)?);
+///     client.start_with_default_executor();
+///
+///     let service = BootstrapServiceBuilder::new().streaming(true).build(client);
+///     let make_service = make_service_fn(move |_| {
+///         let service = service.clone();
+///         async move { Ok::<_, Infallible>(service) }
+///     });
+///     hyper::Server::bind(&([0, 0, 0, 0], 8080).into())
+///         .serve(make_service)
+///         .await?;
+/// #   Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct BootstrapService {
+    client: Arc<Client>,
+    settings: BootstrapServiceBuilder,
+    // The number of streams currently open, shared by every clone of the service.
+    streams: Arc<AtomicUsize>,
+}
+
+impl Service<Request<Body>> for BootstrapService {
+    type Response = Response<Body>;
+    type Error = Infallible;
+    type Future = BoxFuture<'static, Result<Response<Body>, Infallible>>;
+
+    fn poll_ready(&mut self, _cx: &mut TaskContext<'_>) -> Poll<Result<(), Infallible>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: Request<Body>) -> Self::Future {
+        let service = self.clone();
+        async move { Ok(service.handle(request).await) }.boxed()
+    }
+}
+
+impl BootstrapService {
+    async fn handle(&self, request: Request<Body>) -> Response<Body> {
+        let streaming = self.settings.streaming
+            && request
+                .headers()
+                .get(ACCEPT)
+                .and_then(|accept| accept.to_str().ok())
+                .is_some_and(|accept| accept.contains("text/event-stream"));
+
+        let context = match read_context(request, self.settings.max_body_size).await {
+            Ok(context) => context,
+            Err(response) => return response,
+        };
+
+        if streaming {
+            self.stream(context)
+        } else {
+            Response::builder()
+                .header(CONTENT_TYPE, "application/json")
+                .body(Body::from(self.payload(&context).to_string()))
+                .expect("response headers are valid")
+        }
+    }
+
+    fn payload(&self, context: &Context) -> Value {
+        let flags = self
+            .client
+            .all_flags_detail(context, self.settings.flag_detail_config);
+        let mut payload = serde_json::json!({ "flags": flags });
+        if self.settings.secure_mode_hash {
+            payload["secureModeHash"] = Value::String(self.client.secure_mode_hash(context));
+        }
+        payload
+    }
+
+    // Evaluation may block on a persistent store, so it is kept off the runtime's worker threads.
+    async fn evaluate(&self, context: &Context) -> Option<Value> {
+        let service = self.clone();
+        let context = context.clone();
+        tokio::task::spawn_blocking(move || service.payload(&context))
+            .await
+            .ok()
+    }
+
+    // Each stream re-evaluates all flags for its own context whenever the client's flag data
+    // changes, as there is no cheaper way to tell whether a change affects that context.
+    fn stream(&self, context: Context) -> Response<Body> {
+        let slot = match StreamSlot::reserve(&self.streams, self.settings.max_streams) {
+            Some(slot) => slot,
+            None => {
+                return Response::builder()
+                    .status(StatusCode::SERVICE_UNAVAILABLE)
+                    .body(Body::empty())
+                    .expect("response headers are valid")
+            }
+        };
+
+        let (mut sender, body) = Body::channel();
+        let service = self.clone();
+        tokio::spawn(async move {
+            let _slot = slot;
+            let mut previous = None;
+            let mut evaluated = None;
+            let mut since_last_event = Duration::ZERO;
+            loop {
+                // Stores which cannot tell when they change are re-evaluated every time.
+                let version = service.client.all_flags_version();
+                let mut data = None;
+                if version.is_none() || version != evaluated {
+                    evaluated = version;
+                    let payload = match service.evaluate(&context).await {
+                        Some(payload) => payload,
+                        None => return,
+                    };
+                    if previous.as_ref() != Some(&payload) {
+                        data = Some(Bytes::from(format!("event: put\ndata: {payload}\n\n")));
+                        previous = Some(payload);
+                    }
+                }
+                if data.is_none() && since_last_event >= HEARTBEAT_INTERVAL {
+                    data = Some(Bytes::from_static(b":\n\n"));
+                }
+
+                if let Some(data) = data {
+                    since_last_event = Duration::ZERO;
+                    // Sending fails once the requester has disconnected.
+                    if sender.send_data(data).await.is_err() {
+                        return;
+                    }
+                }
+
+                tokio::time::sleep(service.settings.update_interval).await;
+                since_last_event += service.settings.update_interval;
+
+                // Events may be rare, so disconnects are also checked for between them, to
+                // release the stream's slot promptly.
+                let ready = futures::future::poll_fn(|cx| sender.poll_ready(cx)).now_or_never();
+                if let Some(Err(_)) = ready {
+                    return;
+                }
+            }
+        });
+
+        Response::builder()
+            .header(CONTENT_TYPE, "text/event-stream")
+            .header(CACHE_CONTROL, "no-cache")
+            .body(body)
+            .expect("response headers are valid")
+    }
+}
+
+// One of a service's limited number of open streams, released when the stream ends.
+struct StreamSlot(Arc<AtomicUsize>);
+
+impl StreamSlot {
+    fn reserve(streams: &Arc<AtomicUsize>, max_streams: usize) -> Option<Self> {
+        streams
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |open| {
+                (open < max_streams).then_some(open + 1)
+            })
+            .ok()?;
+        Some(Self(streams.clone()))
+    }
+}
+
+impl Drop for StreamSlot {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+async fn read_context(
+    request: Request<Body>,
+    max_body_size: usize,
+) -> Result<Context, Response<Body>> {
+    let json = match *request.method() {
+        Method::GET => {
+            let encoded = request
+                .uri()
+                .path()
+                .rsplit('/')
+                .next()
+                .unwrap_or_default()
+                .trim_end_matches('=');
+            data_encoding::BASE64URL_NOPAD
+                .decode(encoded.as_bytes())
+                .map_err(|e| bad_request(format!("context is not valid base64url: {e}")))?
+        }
+        Method::POST => read_body(request, max_body_size).await?,
+        _ => {
+            return Err(Response::builder()
+                .status(StatusCode::METHOD_NOT_ALLOWED)
+                .body(Body::empty())
+                .expect("response headers are valid"))
+        }
+    };
+
+    serde_json::from_slice(&json).map_err(|e| bad_request(format!("invalid context: {e}")))
+}
+
+// Reads the body, stopping as soon as it is known to be too large whether or not the request
+// declares its length.
+async fn read_body(
+    request: Request<Body>,
+    max_body_size: usize,
+) -> Result<Vec<u8>, Response<Body>> {
+    let declared_length = request
+        .headers()
+        .get(CONTENT_LENGTH)
+        .and_then(|length| length.to_str().ok()?.parse::<u64>().ok());
+    if declared_length.is_some_and(|length| length > max_body_size as u64) {
+        return Err(payload_too_large());
+    }
+
+    let mut body = request.into_body();
+    let mut json = Vec::new();
+    while let Some(chunk) = body.data().await {
+        let chunk = chunk.map_err(|e| bad_request(format!("failed to read request body: {e}")))?;
+        if json.len() + chunk.len() > max_body_size {
+            return Err(payload_too_large());
+        }
+        json.extend_from_slice(&chunk);
+    }
+    Ok(json)
+}
+
+fn payload_too_large() -> Response<Body> {
+    Response::builder()
+        .status(StatusCode::PAYLOAD_TOO_LARGE)
+        .body(Body::empty())
+        .expect("response headers are valid")
+}
+
+fn bad_request(message: String) -> Response<Body> {
+    Response::builder()
+        .status(StatusCode::BAD_REQUEST)
+        .body(Body::from(message))
+        .expect("response headers are valid")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data_source::MockDataSource;
+    use crate::data_source_builders::MockDataSourceBuilder;
+    use crate::stores::store_types::{PatchTarget, StorageItem};
+    use crate::test_common::{basic_flag, basic_off_flag};
+    use crate::{ConfigBuilder, NullEventProcessorBuilder};
+
+    fn client() -> Arc<Client> {
+        let updates = Arc::new(MockDataSource::new_with_init_delay(0));
+        let config = ConfigBuilder::new("sdk-key")
+            .data_source(MockDataSourceBuilder::new().data_source(updates))
+            .event_processor(&NullEventProcessorBuilder::new())
+            .build()
+            .expect("config should build");
+        let client = Client::build(config).expect("client should build");
+        client.start_with_default_executor();
+        client
+            .data_store
+            .write()
+            .upsert(
+                "myFlag",
+                PatchTarget::Flag(StorageItem::Item(basic_flag("myFlag"))),
+            )
+            .expect("patch should apply");
+        Arc::new(client)
+    }
+
+    fn context_json() -> Value {
+        serde_json::json!({ "kind": "user", "key": "bob" })
+    }
+
+    async fn json_body(response: Response<Body>) -> Value {
+        let body = hyper::body::to_bytes(response.into_body())
+            .await
+            .expect("body should be read");
+        serde_json::from_slice(&body).expect("body should be JSON")
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn contexts_can_be_posted_or_encoded_in_the_path() {
+        let client = client();
+        let mut service = BootstrapServiceBuilder::new().build(client.clone());
+        let context: Context = serde_json::from_value(context_json()).expect("valid context");
+
+        let posted = service
+            .call(
+                Request::post("/bootstrap")
+                    .body(Body::from(context_json().to_string()))
+                    .expect("request should build"),
+            )
+            .await
+            .expect("service is infallible");
+        assert_eq!(StatusCode::OK, posted.status());
+        let posted = json_body(posted).await;
+        assert_eq!(true, posted["flags"]["myFlag"]);
+        assert_eq!(true, posted["flags"]["$valid"]);
+        assert_eq!(client.secure_mode_hash(&context), posted["secureModeHash"]);
+
+        let encoded = data_encoding::BASE64URL.encode(context_json().to_string().as_bytes());
+        let fetched = service
+            .call(
+                Request::get(format!("/bootstrap/{encoded}"))
+                    .body(Body::empty())
+                    .expect("request should build"),
+            )
+            .await
+            .expect("service is infallible");
+        assert_eq!(posted, json_body(fetched).await);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn invalid_requests_are_rejected() {
+        let mut service = BootstrapServiceBuilder::new()
+            .secure_mode_hash(false)
+            .build(client());
+
+        for (request, expected) in [
+            (Request::get("/bootstrap/!!!"), StatusCode::BAD_REQUEST),
+            (Request::post("/bootstrap"), StatusCode::BAD_REQUEST),
+            (
+                Request::delete("/bootstrap"),
+                StatusCode::METHOD_NOT_ALLOWED,
+            ),
+        ] {
+            let response = service
+                .call(
+                    request
+                        .body(Body::from("{}"))
+                        .expect("request should build"),
+                )
+                .await
+                .expect("service is infallible");
+            assert_eq!(expected, response.status());
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn oversized_bodies_are_rejected() {
+        let mut service = BootstrapServiceBuilder::new()
+            .max_body_size(16)
+            .build(client());
+        let oversized = format!("{{\"key\": \"{}\"}}", "x".repeat(16));
+
+        let declared = service
+            .call(
+                Request::post("/bootstrap")
+                    .header(CONTENT_LENGTH, oversized.len())
+                    .body(Body::from(oversized.clone()))
+                    .expect("request should build"),
+            )
+            .await
+            .expect("service is infallible");
+        assert_eq!(StatusCode::PAYLOAD_TOO_LARGE, declared.status());
+
+        // Chunked bodies do not declare their length, so are limited as they are read.
+        let (mut sender, body) = Body::channel();
+        tokio::spawn(async move {
+            for chunk in [oversized[..10].to_string(), oversized[10..].to_string()] {
+                let _ = sender.send_data(Bytes::from(chunk)).await;
+            }
+        });
+        let chunked = service
+            .call(
+                Request::post("/bootstrap")
+                    .body(body)
+                    .expect("request should build"),
+            )
+            .await
+            .expect("service is infallible");
+        assert_eq!(StatusCode::PAYLOAD_TOO_LARGE, chunked.status());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn flag_state_changes_are_streamed() {
+        let client = client();
+        let mut service = BootstrapServiceBuilder::new()
+            .streaming(true)
+            .update_interval(Duration::from_millis(10))
+            .build(client.clone());
+
+        let response = service
+            .call(
+                Request::post("/bootstrap")
+                    .header(ACCEPT, "text/event-stream")
+                    .body(Body::from(context_json().to_string()))
+                    .expect("request should build"),
+            )
+            .await
+            .expect("service is infallible");
+        assert_eq!("text/event-stream", response.headers()[CONTENT_TYPE]);
+        let mut body = response.into_body();
+
+        let first = body.data().await.expect("event").expect("event data");
+        assert!(String::from_utf8_lossy(&first).contains("\"myFlag\":true"));
+
+        let mut flag = basic_off_flag("myFlag");
+        flag.version += 1;
+        client
+            .data_store
+            .write()
+            .upsert("myFlag", PatchTarget::Flag(StorageItem::Item(flag)))
+            .expect("patch should apply");
+
+        let second = body.data().await.expect("event").expect("event data");
+        let second = String::from_utf8_lossy(&second);
+        assert!(second.starts_with("event: put\ndata: "));
+        assert!(second.contains("\"myFlag\":null"));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn open_streams_are_limited() {
+        let mut service = BootstrapServiceBuilder::new()
+            .streaming(true)
+            .update_interval(Duration::from_millis(10))
+            .max_streams(1)
+            .build(client());
+        let request = || {
+            Request::post("/bootstrap")
+                .header(ACCEPT, "text/event-stream")
+                .body(Body::from(context_json().to_string()))
+                .expect("request should build")
+        };
+
+        let first = service
+            .call(request())
+            .await
+            .expect("service is infallible");
+        assert_eq!(StatusCode::OK, first.status());
+        let second = service
+            .call(request())
+            .await
+            .expect("service is infallible");
+        assert_eq!(StatusCode::SERVICE_UNAVAILABLE, second.status());
+
+        // Disconnecting releases the stream's slot, even though no further events are due.
+        drop(first);
+        let mut reopened = false;
+        for _ in 0..200 {
+            let response = service
+                .call(request())
+                .await
+                .expect("service is infallible");
+            if response.status() == StatusCode::OK {
+                reopened = true;
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert!(reopened, "the closed stream's slot should be released");
+    }
+}
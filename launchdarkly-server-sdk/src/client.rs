@@ -15,7 +15,9 @@ use super::config::Config;
 use super::data_source::{DataSource, EventReceived};
 use super::data_source_builders::BuildError as DataSourceError;
 use super::evaluation::{FlagDetail, FlagDetailConfig};
-use super::stores::store::DataStore;
+use super::flag_overrides::{EvaluationSource, FlagOverrides};
+use super::flag_registry::{FlagRegistry, FlagValidationError};
+use super::stores::store::{DataStore, PrefetchedStore};
 use super::stores::store_builders::BuildError as DataStoreError;
 use crate::config::BuildError as ConfigBuildError;
 use crate::events::event::EventFactory;
@@ -116,6 +118,23 @@ impl From<usize> for ClientInitState {
     }
 }
 
+// Reports whether a client is initialized, as Client::initialized does, for components which
+// outlive their borrow of the client.
+#[cfg(feature = "relay")]
+#[derive(Clone)]
+pub(crate) struct InitializedHandle {
+    init_state: Arc<AtomicUsize>,
+    always_initialized: bool,
+}
+
+#[cfg(feature = "relay")]
+impl InitializedHandle {
+    pub(crate) fn initialized(&self) -> bool {
+        self.always_initialized
+            || ClientInitState::Initialized == self.init_state.load(Ordering::SeqCst)
+    }
+}
+
 /// A client for the LaunchDarkly API.
 ///
 /// In order to create a client instance, first create a config using [crate::ConfigBuilder].
@@ -148,15 +167,16 @@ impl From<usize> for ClientInitState {
 pub struct Client {
     event_processor: Arc<dyn EventProcessor>,
     data_source: Arc<dyn DataSource>,
-    data_store: Arc<RwLock<dyn DataStore>>,
+    pub(crate) data_store: Arc<RwLock<dyn DataStore>>,
     events_default: EventsScope,
     events_with_reasons: EventsScope,
+    flag_overrides: Option<FlagOverrides>,
     init_notify: Arc<Semaphore>,
     init_state: Arc<AtomicUsize>,
     started: AtomicBool,
     offline: bool,
     daemon_mode: bool,
-    sdk_key: String,
+    pub(crate) sdk_key: String,
     shutdown_broadcast: broadcast::Sender<()>,
     runtime: RwLock<Option<Runtime>>,
 }
@@ -170,6 +190,15 @@ impl Client {
             info!("Started LaunchDarkly Client in daemon mode");
         }
 
+        if let Some(overrides) = config.flag_overrides() {
+            warn!(
+                "Flag overrides are enabled ({} currently in effect, from {}); overridden flags \
+                 will not reflect their values in LaunchDarkly",
+                overrides.len(),
+                overrides.sources().join(", ")
+            );
+        }
+
         let tags = config.application_tag();
 
         let endpoints = config.service_endpoints_builder().build()?;
@@ -209,6 +238,7 @@ impl Client {
             data_store,
             events_default,
             events_with_reasons,
+            flag_overrides: config.flag_overrides().cloned(),
             init_notify: Arc::new(Semaphore::new(0)),
             init_state: Arc::new(AtomicUsize::new(ClientInitState::Initializing as usize)),
             started: AtomicBool::new(false),
@@ -305,11 +335,9 @@ impl Client {
             warn!("wait_for_initialization was configured to block for up to {} seconds. We recommend blocking no longer than 60 seconds.", timeout.as_secs());
         }
 
-        let initialized = tokio::time::timeout(timeout, self.initialized_async_internal()).await;
-        match initialized {
-            Ok(result) => Some(result),
-            Err(_) => None,
-        }
+        tokio::time::timeout(timeout, self.initialized_async_internal())
+            .await
+            .ok()
     }
 
     async fn initialized_async_internal(&self) -> bool {
@@ -337,6 +365,23 @@ impl Client {
             || ClientInitState::Initialized == self.init_state.load(Ordering::SeqCst)
     }
 
+    // Identifies everything all_flags_detail depends on, if the data store can tell when it
+    // changes, so that repeated evaluations can be skipped while it is unchanged.
+    #[cfg(feature = "bootstrap")]
+    pub(crate) fn all_flags_version(&self) -> Option<(bool, u64, u64)> {
+        let store = self.data_store.read().version()?;
+        let overrides = self.flag_overrides.as_ref().map_or(0, |o| o.version());
+        Some((self.initialized(), store, overrides))
+    }
+
+    #[cfg(feature = "relay")]
+    pub(crate) fn initialized_handle(&self) -> InitializedHandle {
+        InitializedHandle {
+            init_state: self.init_state.clone(),
+            always_initialized: self.offline || self.daemon_mode,
+        }
+    }
+
     /// Close shuts down the LaunchDarkly client. After calling this, the LaunchDarkly client
     /// should no longer be used. The method will block until all pending analytics events (if any)
     /// been sent.
@@ -610,10 +655,29 @@ impl Client {
 
         let mut flag_detail = FlagDetail::new(true);
         flag_detail.populate(&*data_store, context, flag_state_config);
+        if let Some(overrides) = &self.flag_overrides {
+            flag_detail.apply_overrides(overrides.all(context), flag_state_config);
+        }
 
         flag_detail
     }
 
+    /// Checks every flag in the registry against the flags in the data store, reporting flags
+    /// which do not exist and flags with variations of a different type than they were declared
+    /// with. This is intended for smoke tests and startup checks, to catch mistyped keys which
+    /// would otherwise silently evaluate to their defaults.
+    ///
+    /// The client must have finished initializing, or a single
+    /// [FlagValidationError::ClientNotReady] is returned.
+    pub fn validate_flags(&self, registry: &FlagRegistry) -> Result<(), Vec<FlagValidationError>> {
+        if !self.initialized() {
+            return Err(vec![FlagValidationError::ClientNotReady]);
+        }
+
+        let data_store = self.data_store.read();
+        registry.validate(data_store.to_store())
+    }
+
     /// This method is the same as [Client::variation], but also returns further information about
     /// how the value was calculated. The "reason" data will also be included in analytics events.
     ///
@@ -630,6 +694,26 @@ impl Client {
         detail
     }
 
+    /// This method is the same as [Client::variation_detail], but also reports whether the value
+    /// was forced by the overrides configured with [crate::ConfigBuilder::flag_overrides].
+    ///
+    /// The overrides are only consulted once, so the result is consistent even while an overrides
+    /// file is being reloaded.
+    pub fn variation_detail_with_source<T: Into<FlagValue> + Clone>(
+        &self,
+        context: &Context,
+        flag_key: &str,
+        default: T,
+    ) -> (Detail<FlagValue>, EvaluationSource) {
+        if let Some(detail) = self.overridden_detail(context, flag_key) {
+            return (detail, EvaluationSource::Overridden);
+        }
+
+        let (detail, _) =
+            self.evaluate_internal(context, flag_key, default, &self.events_with_reasons);
+        (detail, EvaluationSource::Evaluated)
+    }
+
     /// This is a generic function which returns the value of a feature flag for a given context.
     ///
     /// This method is an alternatively to the type specified methods (e.g.
@@ -650,6 +734,149 @@ impl Client {
         detail.value.unwrap()
     }
 
+    /// Returns the value of a boolean feature flag for a given context, awaiting the data store
+    /// rather than blocking on it.
+    ///
+    /// This behaves like [Client::bool_variation]. The difference only matters when the client
+    /// is configured with a persistent data store created through
+    /// [crate::PersistentDataStoreBuilder::new_async]; reads from any other store never block.
+    pub async fn bool_variation_async(
+        &self,
+        context: &Context,
+        flag_key: &str,
+        default: bool,
+    ) -> bool {
+        let val = self.variation_async(context, flag_key, default).await;
+        if let Some(b) = val.as_bool() {
+            b
+        } else {
+            warn!(
+                "bool_variation_async called for a non-bool flag {:?} (got {:?})",
+                flag_key, val
+            );
+            default
+        }
+    }
+
+    /// Returns the value of a string feature flag for a given context, awaiting the data store
+    /// rather than blocking on it.
+    ///
+    /// See [Client::bool_variation_async] for when this differs from [Client::str_variation].
+    pub async fn str_variation_async(
+        &self,
+        context: &Context,
+        flag_key: &str,
+        default: String,
+    ) -> String {
+        let val = self
+            .variation_async(context, flag_key, default.clone())
+            .await;
+        if let Some(s) = val.as_string() {
+            s
+        } else {
+            warn!(
+                "str_variation_async called for a non-string flag {:?} (got {:?})",
+                flag_key, val
+            );
+            default
+        }
+    }
+
+    /// Returns the value of a float feature flag for a given context, awaiting the data store
+    /// rather than blocking on it.
+    ///
+    /// See [Client::bool_variation_async] for when this differs from [Client::float_variation].
+    pub async fn float_variation_async(
+        &self,
+        context: &Context,
+        flag_key: &str,
+        default: f64,
+    ) -> f64 {
+        let val = self.variation_async(context, flag_key, default).await;
+        if let Some(f) = val.as_float() {
+            f
+        } else {
+            warn!(
+                "float_variation_async called for a non-float flag {:?} (got {:?})",
+                flag_key, val
+            );
+            default
+        }
+    }
+
+    /// Returns the value of a integer feature flag for a given context, awaiting the data store
+    /// rather than blocking on it.
+    ///
+    /// See [Client::bool_variation_async] for when this differs from [Client::int_variation].
+    pub async fn int_variation_async(
+        &self,
+        context: &Context,
+        flag_key: &str,
+        default: i64,
+    ) -> i64 {
+        let val = self.variation_async(context, flag_key, default).await;
+        if let Some(f) = val.as_int() {
+            f
+        } else {
+            warn!(
+                "int_variation_async called for a non-int flag {:?} (got {:?})",
+                flag_key, val
+            );
+            default
+        }
+    }
+
+    /// Returns the value of a feature flag for the given context, allowing the value to be of any
+    /// JSON type, awaiting the data store rather than blocking on it.
+    ///
+    /// See [Client::bool_variation_async] for when this differs from [Client::json_variation].
+    pub async fn json_variation_async(
+        &self,
+        context: &Context,
+        flag_key: &str,
+        default: serde_json::Value,
+    ) -> serde_json::Value {
+        self.variation_async(context, flag_key, default.clone())
+            .await
+            .as_json()
+            .unwrap_or(default)
+    }
+
+    /// This method is the same as [Client::variation_async], but also returns further information
+    /// about how the value was calculated. The "reason" data will also be included in analytics
+    /// events.
+    pub async fn variation_detail_async<T: Into<FlagValue> + Clone>(
+        &self,
+        context: &Context,
+        flag_key: &str,
+        default: T,
+    ) -> Detail<FlagValue> {
+        let (detail, _) = self
+            .variation_internal_async(context, flag_key, default, &self.events_with_reasons)
+            .await;
+        detail
+    }
+
+    /// This is a generic function which returns the value of a feature flag for a given context,
+    /// awaiting the data store rather than blocking on it.
+    ///
+    /// When the client is configured with a persistent data store created through
+    /// [crate::PersistentDataStoreBuilder::new_async], the flag and everything its evaluation
+    /// depends on (prerequisite flags and segments) are read from the store asynchronously
+    /// before the flag is evaluated. For any other data store this method is equivalent to
+    /// [Client::variation].
+    pub async fn variation_async<T: Into<FlagValue> + Clone>(
+        &self,
+        context: &Context,
+        flag_key: &str,
+        default: T,
+    ) -> FlagValue {
+        let (detail, _) = self
+            .variation_internal_async(context, flag_key, default, &self.events_default)
+            .await;
+        detail.value.unwrap()
+    }
+
     /// This method returns the migration stage of the migration feature flag for the given
     /// evaluation context.
     ///
@@ -791,6 +1018,21 @@ impl Client {
         flag_key: &str,
         default: T,
         events_scope: &EventsScope,
+    ) -> (Detail<FlagValue>, Option<eval::Flag>) {
+        if let Some(detail) = self.overridden_detail(context, flag_key) {
+            return (detail, None);
+        }
+
+        self.evaluate_internal(context, flag_key, default, events_scope)
+    }
+
+    // Evaluates the flag without consulting the overrides.
+    fn evaluate_internal<T: Into<FlagValue> + Clone>(
+        &self,
+        context: &Context,
+        flag_key: &str,
+        default: T,
+        events_scope: &EventsScope,
     ) -> (Detail<FlagValue>, Option<eval::Flag>) {
         if self.offline {
             return (
@@ -806,50 +1048,141 @@ impl Client {
             ),
             true => {
                 let data_store = self.data_store.read();
-                match data_store.flag(flag_key) {
-                    Some(flag) => {
-                        let result = eval::evaluate(
-                            data_store.to_store(),
-                            &flag,
-                            context,
-                            Some(&*events_scope.prerequisite_event_recorder),
-                        )
-                        .map(|v| v.clone())
-                        .or(default.clone().into());
-
-                        (Some(flag), result)
-                    }
-                    None => (
-                        None,
-                        Detail::err_default(eval::Error::FlagNotFound, default.clone().into()),
-                    ),
-                }
+                Self::evaluate_in_store(
+                    data_store.to_store(),
+                    context,
+                    flag_key,
+                    default.clone(),
+                    events_scope,
+                )
             }
         };
 
-        if !events_scope.disabled {
-            let event = match &flag {
-                Some(f) => events_scope.event_factory.new_eval_event(
-                    flag_key,
-                    context.clone(),
-                    f,
-                    result.clone(),
-                    default.into(),
-                    None,
-                ),
-                None => events_scope.event_factory.new_unknown_flag_event(
-                    flag_key,
-                    context.clone(),
-                    result.clone(),
-                    default.into(),
-                ),
-            };
-            self.send_internal(event);
+        self.send_evaluation_event(context, flag_key, &flag, &result, default, events_scope);
+
+        (result, flag)
+    }
+
+    async fn variation_internal_async<T: Into<FlagValue> + Clone>(
+        &self,
+        context: &Context,
+        flag_key: &str,
+        default: T,
+        events_scope: &EventsScope,
+    ) -> (Detail<FlagValue>, Option<eval::Flag>) {
+        if let Some(detail) = self.overridden_detail(context, flag_key) {
+            return (detail, None);
+        }
+
+        if self.offline {
+            return (
+                Detail::err_default(eval::Error::ClientNotReady, default.into()),
+                None,
+            );
+        }
+
+        if !self.initialized() {
+            return self.evaluate_internal(context, flag_key, default, events_scope);
         }
 
+        let async_store = self.data_store.read().to_async_store();
+        let (flag, result) = match async_store {
+            // Stores without an asynchronous read path never block, so there is nothing to await.
+            None => {
+                let data_store = self.data_store.read();
+                Self::evaluate_in_store(
+                    data_store.to_store(),
+                    context,
+                    flag_key,
+                    default.clone(),
+                    events_scope,
+                )
+            }
+            Some(async_store) => {
+                let snapshot = PrefetchedStore::load(&*async_store, flag_key, context).await;
+                Self::evaluate_in_store(&snapshot, context, flag_key, default.clone(), events_scope)
+            }
+        };
+
+        self.send_evaluation_event(context, flag_key, &flag, &result, default, events_scope);
+
         (result, flag)
     }
 
+    // Overridden values are reported as falling through without a variation index, which a
+    // genuine fallthrough always has; see EvaluationSource. No analytics events are sent for
+    // them.
+    fn overridden_detail(&self, context: &Context, flag_key: &str) -> Option<Detail<FlagValue>> {
+        let value = self.flag_overrides.as_ref()?.get(context, flag_key)?;
+        debug!("Flag {flag_key} is overridden locally");
+        Some(Detail {
+            value: Some(value),
+            variation_index: None,
+            reason: eval::Reason::Fallthrough {
+                in_experiment: false,
+            },
+        })
+    }
+
+    fn evaluate_in_store<T: Into<FlagValue> + Clone>(
+        store: &dyn eval::Store,
+        context: &Context,
+        flag_key: &str,
+        default: T,
+        events_scope: &EventsScope,
+    ) -> (Option<eval::Flag>, Detail<FlagValue>) {
+        match store.flag(flag_key) {
+            Some(flag) => {
+                let result = eval::evaluate(
+                    store,
+                    &flag,
+                    context,
+                    Some(&*events_scope.prerequisite_event_recorder),
+                )
+                .map(|v| v.clone())
+                .or(default.into());
+
+                (Some(flag), result)
+            }
+            None => (
+                None,
+                Detail::err_default(eval::Error::FlagNotFound, default.into()),
+            ),
+        }
+    }
+
+    fn send_evaluation_event<T: Into<FlagValue>>(
+        &self,
+        context: &Context,
+        flag_key: &str,
+        flag: &Option<eval::Flag>,
+        result: &Detail<FlagValue>,
+        default: T,
+        events_scope: &EventsScope,
+    ) {
+        if events_scope.disabled {
+            return;
+        }
+
+        let event = match flag {
+            Some(f) => events_scope.event_factory.new_eval_event(
+                flag_key,
+                context.clone(),
+                f,
+                result.clone(),
+                default.into(),
+                None,
+            ),
+            None => events_scope.event_factory.new_unknown_flag_event(
+                flag_key,
+                context.clone(),
+                result.clone(),
+                default.into(),
+            ),
+        };
+        self.send_internal(event);
+    }
+
     fn send_internal(&self, event: InputEvent) {
         self.event_processor.send(event);
     }
@@ -862,7 +1195,7 @@ mod tests {
     use eval::{ContextBuilder, MultiContextBuilder};
     use futures::FutureExt;
     use hyper::client::HttpConnector;
-    use launchdarkly_server_sdk_evaluation::{Flag, Reason, Segment};
+    use launchdarkly_server_sdk_evaluation::{Flag, MigrationFlagParameters, Reason, Segment};
     use maplit::hashmap;
     use std::collections::HashMap;
     use tokio::time::Instant;
@@ -872,16 +1205,20 @@ mod tests {
     use crate::events::create_event_sender;
     use crate::events::event::{OutputEvent, VariationKey};
     use crate::events::processor_builders::EventProcessorBuilder;
-    use crate::stores::persistent_store::tests::InMemoryPersistentDataStore;
+    use crate::flag_overrides::{EvaluationSource, FlagOverridesBuilder};
+    use crate::stores::persistent_store::tests::{
+        InMemoryPersistentDataStore, YieldingPersistentDataStore,
+    };
     use crate::stores::store_types::{PatchTarget, StorageItem};
     use crate::test_common::{
         self, basic_flag, basic_flag_with_prereq, basic_flag_with_prereqs_and_visibility,
         basic_flag_with_visibility, basic_int_flag, basic_migration_flag, basic_off_flag,
     };
     use crate::{
-        AllData, ConfigBuilder, MigratorBuilder, NullEventProcessorBuilder, Operation, Origin,
-        PersistentDataStore, PersistentDataStoreBuilder, PersistentDataStoreFactory,
-        SerializedItem,
+        AllData, AsyncPersistentDataStore, AsyncPersistentDataStoreFactory, ConfigBuilder,
+        MigratorBuilder, NullEventProcessorBuilder, Operation, Origin, PersistentDataStore,
+        PersistentDataStoreBuilder, PersistentDataStoreFactory, ReadFallback, SerializedItem,
+        TypedFlag, WriteRetryPolicy,
     };
     use test_case::test_case;
 
@@ -895,6 +1232,7 @@ mod tests {
     }
 
     #[tokio::test]
+    #[allow(deprecated)]
     async fn client_asynchronously_initializes() {
         let (client, _event_rx) = make_mocked_client_with_delay(1000, false, false);
         client.start_with_default_executor();
@@ -1305,6 +1643,42 @@ mod tests {
         }
     }
 
+    #[test]
+    fn typed_flags_evaluate_and_validate_against_the_store() {
+        const ENABLED: TypedFlag<bool> = TypedFlag::new("enabled", false);
+        const MISSING: TypedFlag<&str> = TypedFlag::new("missing", "default");
+
+        let (client, _event_rx) = make_mocked_client();
+        let mut registry = FlagRegistry::new();
+        registry.register(&ENABLED).register(&MISSING);
+        assert_eq!(
+            Err(vec![FlagValidationError::ClientNotReady]),
+            client.validate_flags(&registry)
+        );
+
+        client.start_with_default_executor();
+        client
+            .data_store
+            .write()
+            .upsert(
+                "enabled",
+                PatchTarget::Flag(StorageItem::Item(basic_flag("enabled"))),
+            )
+            .expect("patch should apply");
+        let context = ContextBuilder::new("bob")
+            .build()
+            .expect("Failed to create context");
+
+        assert!(ENABLED.variation(&client, &context));
+        assert_eq!("default", MISSING.variation(&client, &context));
+        assert_eq!(
+            Err(vec![FlagValidationError::FlagNotFound(
+                "missing".to_string()
+            )]),
+            client.validate_flags(&registry)
+        );
+    }
+
     #[test]
     fn variation_detail_handles_debug_events_correctly() {
         let (client, event_rx) = make_mocked_client();
@@ -1441,7 +1815,7 @@ mod tests {
     impl PersistentDataStoreFactory for InMemoryPersistentDataStoreFactory {
         fn create_persistent_data_store(
             &self,
-        ) -> Result<Box<(dyn PersistentDataStore + 'static)>, std::io::Error> {
+        ) -> Result<Box<dyn PersistentDataStore + 'static>, std::io::Error> {
             let serialized_data =
                 AllData::<SerializedItem, SerializedItem>::try_from(self.data.clone())?;
             Ok(Box::new(InMemoryPersistentDataStore {
@@ -1499,18 +1873,36 @@ mod tests {
         });
     }
 
-    #[test]
-    fn daemon_mode_is_quiet_if_store_is_not_initialized() {
-        testing_logger::setup();
+    struct YieldingPersistentDataStoreFactory {
+        data: AllData<Flag, Segment>,
+    }
 
-        let factory = InMemoryPersistentDataStoreFactory {
+    impl AsyncPersistentDataStoreFactory for YieldingPersistentDataStoreFactory {
+        fn create_async_persistent_data_store(
+            &self,
+        ) -> Result<Box<dyn AsyncPersistentDataStore + 'static>, std::io::Error> {
+            let serialized_data =
+                AllData::<SerializedItem, SerializedItem>::try_from(self.data.clone())?;
+            Ok(Box::new(YieldingPersistentDataStore::new(
+                InMemoryPersistentDataStore {
+                    data: serialized_data,
+                    initialized: true,
+                },
+            )))
+        }
+    }
+
+    fn make_async_store_client() -> Client {
+        let factory = YieldingPersistentDataStoreFactory {
             data: AllData {
-                flags: HashMap::new(),
+                flags: hashmap![
+                    "flag".into() => basic_flag_with_prereq("flag", "prereq"),
+                    "prereq".into() => basic_flag("prereq"),
+                ],
                 segments: HashMap::new(),
             },
-            initialized: false,
         };
-        let builder = PersistentDataStoreBuilder::new(Arc::new(factory));
+        let builder = PersistentDataStoreBuilder::new_async(Arc::new(factory));
 
         let config = ConfigBuilder::new("sdk-key")
             .daemon_mode(true)
@@ -1519,17 +1911,95 @@ mod tests {
             .build()
             .expect("config should build");
 
-        let client = Client::build(config).expect("Should be built.");
+        Client::build(config).expect("Should be built.")
+    }
 
+    #[tokio::test]
+    async fn variation_async_reads_from_async_persistent_store() {
+        let client = make_async_store_client();
         client.start_with_default_executor();
 
         let context = ContextBuilder::new("bob")
             .build()
             .expect("Failed to create context");
 
-        client.variation_detail(&context, "flag", FlagValue::Bool(false));
+        let detail = client
+            .variation_detail_async(&context, "flag", FlagValue::Bool(false))
+            .await;
+        assert!(detail.value.unwrap().as_bool().unwrap());
+        assert!(matches!(
+            detail.reason,
+            Reason::Fallthrough {
+                in_experiment: false
+            }
+        ));
 
-        testing_logger::validate(|captured_logs| {
+        assert!(client.bool_variation_async(&context, "flag", false).await);
+        assert_eq!(
+            client.int_variation_async(&context, "flag", 7).await,
+            7,
+            "wrong type should return the default"
+        );
+
+        let detail = client
+            .variation_detail_async(&context, "missing", FlagValue::Bool(false))
+            .await;
+        assert!(!detail.value.unwrap().as_bool().unwrap());
+        assert!(matches!(
+            detail.reason,
+            Reason::Error {
+                error: eval::Error::FlagNotFound
+            }
+        ));
+
+        client.close();
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn sync_variation_reads_from_async_persistent_store() {
+        let client = make_async_store_client();
+        client.start_with_default_executor();
+
+        let context = ContextBuilder::new("bob")
+            .build()
+            .expect("Failed to create context");
+
+        assert!(client.bool_variation(&context, "flag", false));
+
+        client.close();
+    }
+
+    #[test]
+    fn daemon_mode_is_quiet_if_store_is_not_initialized() {
+        testing_logger::setup();
+
+        let factory = InMemoryPersistentDataStoreFactory {
+            data: AllData {
+                flags: HashMap::new(),
+                segments: HashMap::new(),
+            },
+            initialized: false,
+        };
+        let builder = PersistentDataStoreBuilder::new(Arc::new(factory));
+
+        let config = ConfigBuilder::new("sdk-key")
+            .daemon_mode(true)
+            .data_store(&builder)
+            .event_processor(&NullEventProcessorBuilder::new())
+            .build()
+            .expect("config should build");
+
+        let client = Client::build(config).expect("Should be built.");
+
+        client.start_with_default_executor();
+
+        let context = ContextBuilder::new("bob")
+            .build()
+            .expect("Failed to create context");
+
+        client.variation_detail(&context, "flag", FlagValue::Bool(false));
+
+        testing_logger::validate(|captured_logs| {
             assert_eq!(captured_logs.len(), 1);
             assert_eq!(
                 captured_logs[0].body,
@@ -1945,6 +2415,91 @@ mod tests {
         Ok(())
     }
 
+    #[test_case(false)]
+    #[test_case(true)]
+    fn flag_overrides_take_precedence_over_the_data_store(offline: bool) {
+        let updates = Arc::new(MockDataSource::new_with_init_delay(0));
+        let (event_sender, event_rx) = create_event_sender();
+        let mut overrides = FlagOverridesBuilder::new();
+        overrides
+            .flag("myFlag", false)
+            .flag_for_context("stage-flag", "bob", "live".to_string());
+        let config = ConfigBuilder::new("sdk-key")
+            .offline(offline)
+            .data_source(MockDataSourceBuilder::new().data_source(updates))
+            .event_processor(
+                EventProcessorBuilder::<HttpConnector>::new().event_sender(Arc::new(event_sender)),
+            )
+            .flag_overrides(&overrides)
+            .build()
+            .expect("config should build");
+        let client = Client::build(config).expect("Should be built.");
+        client.start_with_default_executor();
+        client
+            .data_store
+            .write()
+            .upsert(
+                "myFlag",
+                PatchTarget::Flag(StorageItem::Item(basic_flag("myFlag"))),
+            )
+            .expect("patch should apply");
+
+        let bob = ContextBuilder::new("bob")
+            .build()
+            .expect("Failed to create context");
+        let alice = ContextBuilder::new("alice")
+            .build()
+            .expect("Failed to create context");
+
+        let detail = client.variation_detail(&bob, "myFlag", FlagValue::Bool(true));
+        assert_eq!(Some(FlagValue::Bool(false)), detail.value);
+        assert_eq!(None, detail.variation_index);
+        assert_eq!(
+            Reason::Fallthrough {
+                in_experiment: false
+            },
+            detail.reason
+        );
+        let (detail, source) = client.variation_detail_with_source(&bob, "myFlag", true);
+        assert_eq!(Some(FlagValue::Bool(false)), detail.value);
+        assert_eq!(EvaluationSource::Overridden, source);
+
+        let (stage, _tracker) = client.migration_variation(&bob, "stage-flag", Stage::Off);
+        assert_eq!(Stage::Live, stage);
+        let (_, source) = client.variation_detail_with_source(&alice, "stage-flag", Stage::Off);
+        assert_eq!(EvaluationSource::Evaluated, source);
+        let (stage, _tracker) = client.migration_variation(&alice, "stage-flag", Stage::Off);
+        assert_eq!(Stage::Off, stage);
+
+        if !offline {
+            let mut config = FlagDetailConfig::new();
+            config.with_reasons();
+            let all_flags = serde_json::to_value(client.all_flags_detail(&bob, config))
+                .expect("detail should serialize");
+            assert_eq!(json!(false), all_flags["myFlag"]);
+            assert_eq!(json!("live"), all_flags["stage-flag"]);
+            assert_eq!(
+                json!({"reason": {"kind": "FALLTHROUGH"}}),
+                all_flags["$flagsState"]["myFlag"]
+            );
+        }
+
+        client.flush();
+        client.close();
+
+        // Only the evaluation which was not overridden is reported.
+        let summarized = event_rx
+            .iter()
+            .filter_map(|event| match event {
+                OutputEvent::Summary(summary) => Some(summary.features.into_keys()),
+                _ => None,
+            })
+            .flatten()
+            .collect::<Vec<_>>();
+        let expected = if offline { vec![] } else { vec!["stage-flag"] };
+        assert_eq!(expected, summarized);
+    }
+
     #[test]
     fn migration_handles_flag_not_found() {
         let (client, _event_rx) = make_mocked_client();
@@ -2010,6 +2565,111 @@ mod tests {
         assert_eq!(evaluated_stage, stage);
     }
 
+    #[test_case(Stage::Shadow, None, 1)]
+    #[test_case(Stage::Live, None, 1)]
+    #[test_case(Stage::Live, Some(0), 0)]
+    #[tokio::test]
+    async fn migration_reports_mismatches(
+        stage: Stage,
+        check_ratio: Option<u32>,
+        expected_mismatches: usize,
+    ) {
+        let (client, _event_rx) = make_mocked_client();
+        let client = Arc::new(client);
+        client.start_with_default_executor();
+
+        let mut flag = basic_migration_flag("stage-flag", stage);
+        flag.migration_settings = Some(MigrationFlagParameters { check_ratio });
+        client
+            .data_store
+            .write()
+            .upsert("stage-flag", PatchTarget::Flag(StorageItem::Item(flag)))
+            .expect("patch should apply");
+
+        let mismatches = Arc::new(Mutex::new(Vec::new()));
+        let recorded = mismatches.clone();
+        let migrator = MigratorBuilder::new(client.clone())
+            .read(
+                |&payload: &u32| async move { Ok(payload) }.boxed(),
+                |&payload: &u32| async move { Ok(payload + 1) }.boxed(),
+                Some(|a, b| a == b),
+            )
+            .write(
+                |&payload: &u32| async move { Ok(payload) }.boxed(),
+                |&payload: &u32| async move { Ok(payload) }.boxed(),
+            )
+            .on_mismatch(move |mismatch| {
+                recorded.lock().unwrap().push((
+                    mismatch.flag_key.to_string(),
+                    mismatch.stage,
+                    *mismatch.payload,
+                    *mismatch.old,
+                    *mismatch.new,
+                ));
+            })
+            .build()
+            .expect("migrator should build");
+
+        let context = ContextBuilder::new("bob")
+            .build()
+            .expect("Failed to create context");
+        migrator
+            .read(&context, "stage-flag".into(), Stage::Off, 10)
+            .await;
+        migrator
+            .write(&context, "stage-flag".into(), Stage::Off, 10)
+            .await;
+
+        let mismatches = mismatches.lock().unwrap();
+        assert_eq!(expected_mismatches, mismatches.len());
+        for mismatch in mismatches.iter() {
+            assert_eq!(&("stage-flag".to_string(), stage, 10, 10, 11), mismatch);
+        }
+    }
+
+    #[tokio::test]
+    async fn migration_does_not_report_consistent_reads() {
+        let (client, _event_rx) = make_mocked_client();
+        let client = Arc::new(client);
+        client.start_with_default_executor();
+        client
+            .data_store
+            .write()
+            .upsert(
+                "stage-flag",
+                PatchTarget::Flag(StorageItem::Item(basic_migration_flag(
+                    "stage-flag",
+                    Stage::Shadow,
+                ))),
+            )
+            .expect("patch should apply");
+
+        let mismatches = Arc::new(Mutex::new(0));
+        let recorded = mismatches.clone();
+        let migrator = MigratorBuilder::new(client.clone())
+            .read(
+                |&payload: &u32| async move { Ok(payload) }.boxed(),
+                |&payload: &u32| async move { Ok(payload) }.boxed(),
+                Some(|a, b| a == b),
+            )
+            .write(
+                |&payload: &u32| async move { Ok(payload) }.boxed(),
+                |&payload: &u32| async move { Ok(payload) }.boxed(),
+            )
+            .on_mismatch(move |_| *recorded.lock().unwrap() += 1)
+            .build()
+            .expect("migrator should build");
+
+        let context = ContextBuilder::new("bob")
+            .build()
+            .expect("Failed to create context");
+        migrator
+            .read(&context, "stage-flag".into(), Stage::Off, 10)
+            .await;
+
+        assert_eq!(0, *mismatches.lock().unwrap());
+    }
+
     #[tokio::test]
     async fn migration_tracks_invoked_correctly() {
         migration_tracks_invoked_correctly_driver(Stage::Off, Operation::Read, vec![Origin::Old])
@@ -2095,7 +2755,7 @@ mod tests {
             )
             .expect("patch should apply");
 
-        let mut migrator = MigratorBuilder::new(client.clone())
+        let migrator = MigratorBuilder::new(client.clone())
             .read(
                 |_| async move { Ok(serde_json::Value::Null) }.boxed(),
                 |_| async move { Ok(serde_json::Value::Null) }.boxed(),
@@ -2209,7 +2869,7 @@ mod tests {
             )
             .expect("patch should apply");
 
-        let mut migrator = MigratorBuilder::new(client.clone())
+        let migrator = MigratorBuilder::new(client.clone())
             .track_latency(true)
             .read(
                 |_| {
@@ -2311,7 +2971,7 @@ mod tests {
             )
             .expect("patch should apply");
 
-        let mut migrator = MigratorBuilder::new(client.clone())
+        let migrator = MigratorBuilder::new(client.clone())
             .track_latency(true)
             .read(
                 |_| async move { Err("fail".into()) }.boxed(),
@@ -2351,6 +3011,585 @@ mod tests {
         }
     }
 
+    #[tokio::test]
+    async fn migration_times_out_slow_nonauthoritative_reads() {
+        let (client, event_rx) = make_mocked_client();
+        let client = Arc::new(client);
+        client.start_with_default_executor();
+        client
+            .data_store
+            .write()
+            .upsert(
+                "stage-flag",
+                PatchTarget::Flag(StorageItem::Item(basic_migration_flag(
+                    "stage-flag",
+                    Stage::Shadow,
+                ))),
+            )
+            .expect("patch should apply");
+
+        let migrator = MigratorBuilder::new(client.clone())
+            .track_latency(true)
+            .timeout(Origin::New, Duration::from_millis(50))
+            .read(
+                |_| async move { Ok("old".to_string()) }.boxed(),
+                |_| {
+                    async move {
+                        tokio::time::sleep(Duration::from_secs(5)).await;
+                        Ok("new".to_string())
+                    }
+                    .boxed()
+                },
+                Some(|_: &String, _: &String| true),
+            )
+            .write(
+                |_| async move { Ok("old".to_string()) }.boxed(),
+                |_| async move { Ok("new".to_string()) }.boxed(),
+            )
+            .build()
+            .expect("migrator should build");
+
+        let context = ContextBuilder::new("bob")
+            .build()
+            .expect("Failed to create context");
+
+        let start = Instant::now();
+        let result = migrator
+            .read(
+                &context,
+                "stage-flag".into(),
+                Stage::Off,
+                serde_json::Value::Null,
+            )
+            .await;
+        assert!(start.elapsed() < Duration::from_secs(1));
+        assert_eq!(Ok("old".to_string()), result.result);
+
+        client.flush();
+        client.close();
+
+        let events = event_rx.iter().collect::<Vec<OutputEvent>>();
+        assert_eq!(events.len(), 3);
+        match &events[1] {
+            OutputEvent::MigrationOp(event) => {
+                assert_eq!(vec![&Origin::New], event.errors.iter().collect::<Vec<_>>());
+                assert!(event.latency.contains_key(&Origin::New));
+                assert!(event.consistency_check.is_none());
+            }
+            _ => panic!("Expected migration event"),
+        }
+    }
+
+    #[test_case(Stage::Shadow, Origin::New)]
+    #[test_case(Stage::Live, Origin::Old)]
+    #[tokio::test]
+    async fn migration_compares_slow_nonauthoritative_reads_within_timeout(
+        stage: Stage,
+        nonauthoritative: Origin,
+    ) {
+        let (client, event_rx) = make_mocked_client();
+        let client = Arc::new(client);
+        client.start_with_default_executor();
+        client
+            .data_store
+            .write()
+            .upsert(
+                "stage-flag",
+                PatchTarget::Flag(StorageItem::Item(basic_migration_flag("stage-flag", stage))),
+            )
+            .expect("patch should apply");
+
+        let slow = Duration::from_millis(200);
+        let (old_delay, new_delay) = match nonauthoritative {
+            Origin::Old => (slow, Duration::ZERO),
+            Origin::New => (Duration::ZERO, slow),
+        };
+
+        let migrator = MigratorBuilder::new(client.clone())
+            .track_latency(true)
+            .timeout(nonauthoritative, Duration::from_secs(30))
+            .read(
+                move |_| {
+                    async move {
+                        tokio::time::sleep(old_delay).await;
+                        Ok("old".to_string())
+                    }
+                    .boxed()
+                },
+                move |_| {
+                    async move {
+                        tokio::time::sleep(new_delay).await;
+                        Ok("new".to_string())
+                    }
+                    .boxed()
+                },
+                Some(|_: &String, _: &String| true),
+            )
+            .write(
+                |_: &serde_json::Value| async move { Ok("old".to_string()) }.boxed(),
+                |_| async move { Ok("new".to_string()) }.boxed(),
+            )
+            .build()
+            .expect("migrator should build");
+
+        let context = ContextBuilder::new("bob")
+            .build()
+            .expect("Failed to create context");
+
+        let start = Instant::now();
+        let result = migrator
+            .read(
+                &context,
+                "stage-flag".into(),
+                Stage::Off,
+                serde_json::Value::Null,
+            )
+            .await;
+        assert!(start.elapsed() >= slow);
+        assert!(start.elapsed() < Duration::from_secs(30));
+        assert!(result.result.is_ok());
+        assert_ne!(nonauthoritative, result.origin);
+
+        client.flush();
+        client.close();
+
+        let events = event_rx.iter().collect::<Vec<OutputEvent>>();
+        assert_eq!(events.len(), 3);
+        match &events[1] {
+            OutputEvent::MigrationOp(event) => {
+                assert!(event.errors.is_empty());
+                assert!(event.invoked.contains(&nonauthoritative));
+                assert_eq!(Some(true), event.consistency_check);
+            }
+            _ => panic!("Expected migration event"),
+        }
+    }
+
+    #[test_case(Stage::Shadow, ReadFallback::NonAuthoritative, false, Ok("new"), true)]
+    #[test_case(Stage::Live, ReadFallback::NonAuthoritative, false, Ok("old"), true)]
+    #[test_case(Stage::Live, ReadFallback::NonAuthoritative, true, Ok("old"), true)]
+    #[test_case(Stage::Live, ReadFallback::Disabled, false, Err("new"), false)]
+    #[tokio::test(flavor = "multi_thread")]
+    async fn migration_read_falls_back_when_authoritative_origin_fails(
+        stage: Stage,
+        fallback: ReadFallback,
+        in_background: bool,
+        expected: Result<&str, &str>,
+        expected_fallback: bool,
+    ) {
+        let (client, event_rx) = make_mocked_client();
+        let client = Arc::new(client);
+        client.start_with_default_executor();
+        client
+            .data_store
+            .write()
+            .upsert(
+                "stage-flag",
+                PatchTarget::Flag(StorageItem::Item(basic_migration_flag("stage-flag", stage))),
+            )
+            .expect("patch should apply");
+
+        // Whichever origin is authoritative fails.
+        let migrator = MigratorBuilder::new(client.clone())
+            .track_errors(false)
+            .read_fallback(fallback)
+            .nonauthoritative_in_background(in_background)
+            .read(
+                move |_: &serde_json::Value| {
+                    async move {
+                        match stage {
+                            Stage::Shadow => Err("old".to_string()),
+                            _ => Ok("old".to_string()),
+                        }
+                    }
+                    .boxed()
+                },
+                move |_| {
+                    async move {
+                        match stage {
+                            Stage::Shadow => Ok("new".to_string()),
+                            _ => Err("new".to_string()),
+                        }
+                    }
+                    .boxed()
+                },
+                None,
+            )
+            .write_with_types(
+                |_: &serde_json::Value| async move { Ok(()) }.boxed(),
+                |_| async move { Ok(()) }.boxed(),
+            )
+            .build()
+            .expect("migrator should build");
+
+        let context = ContextBuilder::new("bob")
+            .build()
+            .expect("Failed to create context");
+
+        let result = migrator
+            .read(
+                &context,
+                "stage-flag".into(),
+                Stage::Off,
+                serde_json::Value::Null,
+            )
+            .await;
+        assert_eq!(
+            expected.map(String::from).map_err(String::from),
+            result.result
+        );
+        assert_eq!(expected_fallback, result.fallback);
+
+        client.flush();
+        client.close();
+
+        let events = event_rx.iter().collect::<Vec<OutputEvent>>();
+        assert_eq!(events.len(), 3);
+        match &events[1] {
+            OutputEvent::MigrationOp(event) => {
+                let authoritative = match stage {
+                    Stage::Shadow => Origin::Old,
+                    _ => Origin::New,
+                };
+                let expected_errors = if expected_fallback {
+                    vec![&authoritative]
+                } else {
+                    vec![]
+                };
+                assert_eq!(expected_errors, event.errors.iter().collect::<Vec<_>>());
+                assert_eq!(2, event.invoked.len());
+            }
+            _ => panic!("Expected migration event"),
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn migration_reads_nonauthoritative_origin_in_background() {
+        let (client, event_rx) = make_mocked_client();
+        let client = Arc::new(client);
+        client.start_with_default_executor();
+        client
+            .data_store
+            .write()
+            .upsert(
+                "stage-flag",
+                PatchTarget::Flag(StorageItem::Item(basic_migration_flag(
+                    "stage-flag",
+                    Stage::Shadow,
+                ))),
+            )
+            .expect("patch should apply");
+
+        let (sender, receiver) = std::sync::mpsc::channel();
+        let migrator = MigratorBuilder::new(client.clone())
+            .track_latency(true)
+            .nonauthoritative_in_background(true)
+            .read(
+                |_| async move { Ok("read".to_string()) }.boxed(),
+                move |_| {
+                    let sender = sender.clone();
+                    async move {
+                        tokio::time::sleep(Duration::from_millis(200)).await;
+                        sender.send(()).unwrap();
+                        Ok("read".to_string())
+                    }
+                    .boxed()
+                },
+                Some(|_: &String, _: &String| true),
+            )
+            .write(
+                |_| async move { Ok("write".to_string()) }.boxed(),
+                |_| async move { Ok("write".to_string()) }.boxed(),
+            )
+            .build()
+            .expect("migrator should build");
+
+        let context = ContextBuilder::new("bob")
+            .build()
+            .expect("Failed to create context");
+
+        let start = Instant::now();
+        let result = migrator
+            .read(
+                &context,
+                "stage-flag".into(),
+                Stage::Off,
+                serde_json::Value::Null,
+            )
+            .await;
+        assert!(start.elapsed() < Duration::from_millis(150));
+        assert_eq!(Ok("read".to_string()), result.result);
+
+        receiver
+            .recv_timeout(Duration::from_secs(5))
+            .expect("non-authoritative read should run");
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        client.flush();
+        client.close();
+
+        let events = event_rx.iter().collect::<Vec<OutputEvent>>();
+        assert_eq!(events.len(), 3);
+        match &events[1] {
+            OutputEvent::MigrationOp(event) => {
+                assert_eq!(2, event.invoked.len());
+                assert!(event.latency.contains_key(&Origin::New));
+                assert_eq!(Some(true), event.consistency_check);
+            }
+            _ => panic!("Expected migration event"),
+        }
+    }
+
+    #[test_case(Stage::DualWrite, Origin::Old, Origin::New)]
+    #[test_case(Stage::Rampdown, Origin::New, Origin::Old)]
+    #[tokio::test]
+    async fn migration_writes_nonauthoritative_origin_in_background(
+        stage: Stage,
+        authoritative: Origin,
+        nonauthoritative: Origin,
+    ) {
+        let (client, _event_rx) = make_mocked_client();
+        let client = Arc::new(client);
+        client.start_with_default_executor();
+        client
+            .data_store
+            .write()
+            .upsert(
+                "stage-flag",
+                PatchTarget::Flag(StorageItem::Item(basic_migration_flag("stage-flag", stage))),
+            )
+            .expect("patch should apply");
+
+        let (sender, mut receiver) = tokio::sync::mpsc::unbounded_channel();
+        let old_sender = sender.clone();
+        let migrator = MigratorBuilder::new(client.clone())
+            .nonauthoritative_in_background(true)
+            .read(
+                |_| async move { Ok(0) }.boxed(),
+                |_| async move { Ok(0) }.boxed(),
+                None,
+            )
+            .write(
+                move |&payload: &u32| {
+                    let sender = old_sender.clone();
+                    async move {
+                        sender.send((Origin::Old, payload)).unwrap();
+                        Ok(payload)
+                    }
+                    .boxed()
+                },
+                move |&payload: &u32| {
+                    let sender = sender.clone();
+                    async move {
+                        sender.send((Origin::New, payload)).unwrap();
+                        Ok(payload)
+                    }
+                    .boxed()
+                },
+            )
+            .build()
+            .expect("migrator should build");
+
+        let context = ContextBuilder::new("bob")
+            .build()
+            .expect("Failed to create context");
+
+        let result = migrator
+            .write(&context, "stage-flag".into(), Stage::Off, 7)
+            .await;
+        assert_eq!(authoritative, result.authoritative.origin);
+        assert!(result.nonauthoritative.is_none());
+
+        assert_eq!(Some((authoritative, 7)), receiver.recv().await);
+        assert_eq!(Some((nonauthoritative, 7)), receiver.recv().await);
+    }
+
+    #[test_case(None, 1, 1)]
+    #[test_case(Some(2), 3, 3)]
+    #[tokio::test(flavor = "multi_thread")]
+    async fn migration_reports_failed_nonauthoritative_writes(
+        max_retries: Option<u32>,
+        expected_attempts: u32,
+        expected_calls: usize,
+    ) {
+        let (client, event_rx) = make_mocked_client();
+        let client = Arc::new(client);
+        client.start_with_default_executor();
+        client
+            .data_store
+            .write()
+            .upsert(
+                "stage-flag",
+                PatchTarget::Flag(StorageItem::Item(basic_migration_flag(
+                    "stage-flag",
+                    Stage::DualWrite,
+                ))),
+            )
+            .expect("patch should apply");
+
+        let calls = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let new_calls = calls.clone();
+        let (sender, receiver) = std::sync::mpsc::channel();
+        let builder = MigratorBuilder::new(client.clone())
+            .read(
+                |_| async move { Ok(0) }.boxed(),
+                |_| async move { Ok(0) }.boxed(),
+                None,
+            )
+            .write(
+                |&payload: &u32| async move { Ok(payload) }.boxed(),
+                move |_: &u32| {
+                    new_calls.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                    async move { Err("unavailable".to_string()) }.boxed()
+                },
+            )
+            .on_write_failure(move |failure| {
+                sender
+                    .send((
+                        failure.origin,
+                        *failure.payload,
+                        failure.error.to_string(),
+                        failure.attempts,
+                    ))
+                    .unwrap();
+            });
+        let builder = match max_retries {
+            Some(max_retries) => builder.retry_failed_writes(
+                WriteRetryPolicy::new()
+                    .max_retries(max_retries)
+                    .initial_backoff(Duration::from_millis(1)),
+            ),
+            None => builder,
+        };
+        let migrator = builder.build().expect("migrator should build");
+
+        let context = ContextBuilder::new("bob")
+            .build()
+            .expect("Failed to create context");
+        let result = migrator
+            .write(&context, "stage-flag".into(), Stage::Off, 7)
+            .await;
+        assert!(result.authoritative.result.is_ok());
+
+        let failure = receiver
+            .recv_timeout(Duration::from_secs(5))
+            .expect("failure should be reported");
+        assert_eq!(
+            (Origin::New, 7, "unavailable".to_string(), expected_attempts),
+            failure
+        );
+        assert_eq!(
+            expected_calls,
+            calls.load(std::sync::atomic::Ordering::SeqCst)
+        );
+
+        client.flush();
+        client.close();
+
+        let events = event_rx.iter().collect::<Vec<OutputEvent>>();
+        assert_eq!(events.len(), 3);
+        match &events[1] {
+            OutputEvent::MigrationOp(event) => {
+                assert_eq!(vec![&Origin::New], event.errors.iter().collect::<Vec<_>>());
+            }
+            _ => panic!("Expected migration event"),
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn migration_retries_failed_nonauthoritative_writes() {
+        let (client, event_rx) = make_mocked_client();
+        let client = Arc::new(client);
+        client.start_with_default_executor();
+        client
+            .data_store
+            .write()
+            .upsert(
+                "stage-flag",
+                PatchTarget::Flag(StorageItem::Item(basic_migration_flag(
+                    "stage-flag",
+                    Stage::Live,
+                ))),
+            )
+            .expect("patch should apply");
+
+        let (sender, receiver) = std::sync::mpsc::channel();
+        let attempts = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let failures = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let recorded_failures = failures.clone();
+        let migrator = MigratorBuilder::new(client.clone())
+            .track_latency(true)
+            .read(
+                |_| async move { Ok(0) }.boxed(),
+                |_| async move { Ok(0) }.boxed(),
+                None,
+            )
+            .write(
+                move |&payload: &u32| {
+                    let attempt = attempts.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                    let sender = sender.clone();
+                    async move {
+                        if attempt == 0 {
+                            tokio::time::sleep(Duration::from_millis(1)).await;
+                            return Err("unavailable".to_string());
+                        }
+                        tokio::time::sleep(Duration::from_millis(200)).await;
+                        sender.send(payload).unwrap();
+                        Ok(payload)
+                    }
+                    .boxed()
+                },
+                |&payload: &u32| async move { Ok(payload) }.boxed(),
+            )
+            .retry_failed_writes(WriteRetryPolicy::new().initial_backoff(Duration::from_millis(1)))
+            .on_write_failure(move |_| {
+                recorded_failures.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            })
+            .build()
+            .expect("migrator should build");
+
+        let context = ContextBuilder::new("bob")
+            .build()
+            .expect("Failed to create context");
+        let result = migrator
+            .write(&context, "stage-flag".into(), Stage::Off, 7)
+            .await;
+        assert_eq!(Origin::New, result.authoritative.origin);
+        assert!(result
+            .nonauthoritative
+            .expect("old origin should be written")
+            .result
+            .is_err());
+
+        assert_eq!(
+            7,
+            receiver
+                .recv_timeout(Duration::from_secs(5))
+                .expect("write should be retried")
+        );
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        client.flush();
+        client.close();
+
+        assert_eq!(0, failures.load(std::sync::atomic::Ordering::SeqCst));
+        let events = event_rx.iter().collect::<Vec<OutputEvent>>();
+        assert_eq!(events.len(), 3);
+        match &events[1] {
+            OutputEvent::MigrationOp(event) => {
+                // The event describes the original write, not its retry.
+                assert_eq!(vec![&Origin::Old], event.errors.iter().collect::<Vec<_>>());
+                assert_eq!(2, event.invoked.len());
+                assert_eq!(
+                    Some(true),
+                    event
+                        .latency
+                        .get(&Origin::Old)
+                        .map(|latency| *latency < Duration::from_millis(200))
+                );
+            }
+            _ => panic!("Expected migration event"),
+        }
+    }
+
     #[tokio::test]
     async fn migration_tracks_authoritative_write_errors() {
         migration_tracks_authoritative_write_errors_driver(Stage::Off, vec![Origin::Old]).await;
@@ -2380,7 +3619,7 @@ mod tests {
             )
             .expect("patch should apply");
 
-        let mut migrator = MigratorBuilder::new(client.clone())
+        let migrator = MigratorBuilder::new(client.clone())
             .track_latency(true)
             .read(
                 |_| async move { Ok(serde_json::Value::Null) }.boxed(),
@@ -2471,7 +3710,7 @@ mod tests {
             )
             .expect("patch should apply");
 
-        let mut migrator = MigratorBuilder::new(client.clone())
+        let migrator = MigratorBuilder::new(client.clone())
             .track_latency(true)
             .read(
                 |_| async move { Ok(serde_json::Value::Null) }.boxed(),
@@ -2556,7 +3795,7 @@ mod tests {
             )
             .expect("patch should apply");
 
-        let mut migrator = MigratorBuilder::new(client.clone())
+        let migrator = MigratorBuilder::new(client.clone())
             .track_latency(true)
             .read(
                 |_| {
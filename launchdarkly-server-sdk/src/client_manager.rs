@@ -0,0 +1,295 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use hyper::client::connect::Connection;
+use hyper::service::Service;
+use hyper::Uri;
+use launchdarkly_server_sdk_evaluation::{self as eval, Context, Detail, FlagValue};
+use parking_lot::RwLock;
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::runtime::{Handle, Runtime};
+
+use crate::client::{BuildError, Client, StartError};
+use crate::config::ConfigBuilder;
+use crate::{EventProcessorBuilder, StreamingDataSourceBuilder};
+
+/// Hosts clients for many LaunchDarkly environments in a single process.
+///
+/// Every environment added to the manager shares the manager's HTTPS connector and runtime, so
+/// certificates are only loaded once and clients do not each start a runtime to send their
+/// analytics events on. Environments may be added and removed while the manager is in use, and
+/// evaluations are routed by an application-defined environment key.
+///
+/// Only the connector and the runtime are shared. Each client still runs its own data source
+/// tasks on the manager's runtime, and its own event dispatcher thread.
+///
+/// Environments which configure their own data source or event processor keep them, and so do
+/// not share the manager's connector or runtime for that component.
+///
+/// # Examples
+///
+/// ```
+/// # use launchdarkly_server_sdk::{ClientManager, ConfigBuilder, ContextBuilder};
+/// # use hyper_rustls::HttpsConnectorBuilder;
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+///     let connector = HttpsConnectorBuilder::new()
+///         .with_native_roots()
+///         .https_or_http()
+///         .enable_http1()
+///         .enable_http2()
+///         .build();
+///     let manager = ClientManager::new(connector)?;
+///     manager.add_environment("tenant-a", ConfigBuilder::new("sdk-key-a").offline(true))?;
+///     manager.add_environment("tenant-b", ConfigBuilder::new("sdk-key-b").offline(true))?;
+///
+///     let context = ContextBuilder::new("user-key").build()?;
+///     let enabled = manager.variation("tenant-a", &context, "new-checkout", false);
+///
+///     manager.close();
+/// #   Ok(())
+/// # }
+/// ```
+pub struct ClientManager<C> {
+    connector: C,
+    handle: Handle,
+    runtime: RwLock<Option<Runtime>>,
+    clients: RwLock<HashMap<String, Arc<Client>>>,
+}
+
+impl<C> ClientManager<C>
+where
+    C: Service<Uri> + Clone + Send + Sync + 'static,
+    C::Response: Connection + AsyncRead + AsyncWrite + Send + Unpin,
+    C::Future: Send + Unpin + 'static,
+    C::Error: Into<Box<dyn std::error::Error + Send + Sync>>,
+{
+    /// Create a new manager which shares the given connector between its environments, and runs
+    /// them on a runtime created for the manager.
+    pub fn new(connector: C) -> Result<Self, StartError> {
+        let runtime = Runtime::new().map_err(StartError::SpawnFailed)?;
+        Ok(Self {
+            connector,
+            handle: runtime.handle().clone(),
+            runtime: RwLock::new(Some(runtime)),
+            clients: RwLock::new(HashMap::new()),
+        })
+    }
+
+    /// Create a new manager which shares the given connector between its environments, and runs
+    /// them on an existing runtime.
+    pub fn with_runtime(connector: C, runtime: Handle) -> Self {
+        Self {
+            connector,
+            handle: runtime,
+            runtime: RwLock::new(None),
+            clients: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// Builds and starts a client for the environment, returning the client.
+    ///
+    /// If the manager already hosts an environment with the same key, that environment's client
+    /// is closed and replaced.
+    pub fn add_environment(
+        &self,
+        env_key: impl Into<String>,
+        config: ConfigBuilder,
+    ) -> Result<Arc<Client>, BuildError> {
+        let mut data_source = StreamingDataSourceBuilder::<C>::new();
+        data_source.https_connector(self.connector.clone());
+        let mut event_processor = EventProcessorBuilder::<C>::new();
+        event_processor
+            .https_connector(self.connector.clone())
+            .runtime(self.handle.clone());
+
+        let config = config
+            .default_components(&data_source, &event_processor)
+            .build()
+            .map_err(BuildError::from)?;
+
+        // The client's data source is spawned onto whichever runtime is entered.
+        let _guard = self.handle.enter();
+        let client = Arc::new(Client::build(config)?);
+        client.start_with_default_executor();
+
+        let previous = self.clients.write().insert(env_key.into(), client.clone());
+        if let Some(previous) = previous {
+            previous.close();
+        }
+
+        Ok(client)
+    }
+
+    /// Removes the environment from the manager and closes its client, returning the client if
+    /// the environment existed.
+    pub fn remove_environment(&self, env_key: &str) -> Option<Arc<Client>> {
+        let client = self.clients.write().remove(env_key)?;
+        client.close();
+        Some(client)
+    }
+
+    /// Returns the client for the environment, if the manager hosts it.
+    pub fn client(&self, env_key: &str) -> Option<Arc<Client>> {
+        self.clients.read().get(env_key).cloned()
+    }
+
+    /// The keys of every environment the manager hosts, in no particular order.
+    pub fn environments(&self) -> Vec<String> {
+        self.clients.read().keys().cloned().collect()
+    }
+
+    /// Evaluates the flag in the given environment, as [Client::variation] does.
+    ///
+    /// Returns `default` if the manager does not host the environment.
+    pub fn variation<T: Into<FlagValue> + Clone>(
+        &self,
+        env_key: &str,
+        context: &Context,
+        flag_key: &str,
+        default: T,
+    ) -> FlagValue {
+        match self.client(env_key) {
+            Some(client) => client.variation(context, flag_key, default),
+            None => default.into(),
+        }
+    }
+
+    /// Evaluates the flag in the given environment, as [Client::variation_detail] does.
+    ///
+    /// Returns `default` along with a [eval::Error::ClientNotReady] error if the manager does not
+    /// host the environment.
+    pub fn variation_detail<T: Into<FlagValue> + Clone>(
+        &self,
+        env_key: &str,
+        context: &Context,
+        flag_key: &str,
+        default: T,
+    ) -> Detail<FlagValue> {
+        match self.client(env_key) {
+            Some(client) => client.variation_detail(context, flag_key, default),
+            None => Detail::err_default(eval::Error::ClientNotReady, default.into()),
+        }
+    }
+
+    /// Closes every environment's client, and then the runtime if it was created by the
+    /// manager. The manager should no longer be used after calling this.
+    ///
+    /// The manager's runtime is shut down without waiting for its remaining tasks, so this may be
+    /// called from asynchronous code as well.
+    pub fn close(&self) {
+        let clients = std::mem::take(&mut *self.clients.write());
+        for client in clients.values() {
+            client.close();
+        }
+        if let Some(runtime) = self.runtime.write().take() {
+            runtime.shutdown_background();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use hyper::client::HttpConnector;
+    use launchdarkly_server_sdk_evaluation::{ContextBuilder, Reason};
+
+    use super::*;
+    use crate::data_source::MockDataSource;
+    use crate::data_source_builders::MockDataSourceBuilder;
+    use crate::stores::store_types::{PatchTarget, StorageItem};
+    use crate::test_common::basic_flag;
+
+    fn add_mocked_environment(
+        manager: &ClientManager<HttpConnector>,
+        env_key: &str,
+        flag_key: &str,
+    ) -> Arc<Client> {
+        let updates = Arc::new(MockDataSource::new_with_init_delay(0));
+        let config = ConfigBuilder::new(&format!("{env_key}-sdk-key"))
+            .data_source(MockDataSourceBuilder::new().data_source(updates));
+        let client = manager
+            .add_environment(env_key, config)
+            .expect("environment should be added");
+        client
+            .data_store
+            .write()
+            .upsert(
+                flag_key,
+                PatchTarget::Flag(StorageItem::Item(basic_flag(flag_key))),
+            )
+            .expect("patch should apply");
+        client
+    }
+
+    #[test]
+    fn variations_are_routed_by_environment() {
+        let manager = ClientManager::new(HttpConnector::new()).expect("manager should start");
+        add_mocked_environment(&manager, "a", "a-flag");
+        add_mocked_environment(&manager, "b", "b-flag");
+
+        let context = ContextBuilder::new("bob")
+            .build()
+            .expect("Failed to create context");
+
+        assert_eq!(
+            FlagValue::Bool(true),
+            manager.variation("a", &context, "a-flag", false)
+        );
+        assert_eq!(
+            FlagValue::Bool(false),
+            manager.variation("a", &context, "b-flag", false)
+        );
+        assert_eq!(
+            FlagValue::Bool(true),
+            manager.variation("b", &context, "b-flag", false)
+        );
+
+        let mut environments = manager.environments();
+        environments.sort();
+        assert_eq!(vec!["a", "b"], environments);
+
+        manager.close();
+        assert!(manager.environments().is_empty());
+    }
+
+    #[test]
+    fn environments_can_be_removed_and_replaced() {
+        let manager = ClientManager::new(HttpConnector::new()).expect("manager should start");
+        add_mocked_environment(&manager, "a", "first-flag");
+        add_mocked_environment(&manager, "a", "second-flag");
+
+        let context = ContextBuilder::new("bob")
+            .build()
+            .expect("Failed to create context");
+        assert_eq!(
+            FlagValue::Bool(false),
+            manager.variation("a", &context, "first-flag", false)
+        );
+        assert_eq!(
+            FlagValue::Bool(true),
+            manager.variation("a", &context, "second-flag", false)
+        );
+
+        assert!(manager.remove_environment("a").is_some());
+        assert!(manager.remove_environment("a").is_none());
+
+        let detail = manager.variation_detail("a", &context, "second-flag", false);
+        assert_eq!(Some(FlagValue::Bool(false)), detail.value);
+        assert!(matches!(
+            detail.reason,
+            Reason::Error {
+                error: eval::Error::ClientNotReady
+            }
+        ));
+
+        manager.close();
+    }
+
+    #[tokio::test]
+    async fn managers_can_be_closed_from_async_code() {
+        let manager = ClientManager::new(HttpConnector::new()).expect("manager should start");
+        add_mocked_environment(&manager, "a", "a-flag");
+
+        manager.close();
+        assert!(manager.environments().is_empty());
+    }
+}
@@ -0,0 +1,477 @@
+use std::collections::{BTreeMap, HashSet};
+use std::fmt::Write as _;
+use std::fs;
+use std::iter;
+use std::path::Path;
+
+use launchdarkly_server_sdk_evaluation::{Flag, Segment};
+use serde_json::Value;
+use thiserror::Error;
+
+use crate::flag_registry::FlagKind;
+use crate::AllData;
+
+/// Error type used to represent failures when generating flag accessors.
+#[non_exhaustive]
+#[derive(Debug, Error)]
+pub enum CodegenError {
+    /// Error used when the snapshot could not be read, or the module could not be written.
+    #[error("failed to access file: {0}")]
+    Io(#[from] std::io::Error),
+    /// Error used when the snapshot is not a valid set of flags and segments.
+    #[error("failed to parse snapshot: {0}")]
+    InvalidSnapshot(#[from] serde_json::Error),
+    /// Error used when two flag keys map onto the same Rust identifier.
+    #[error("flags {first:?} and {second:?} would both be generated as {name}")]
+    DuplicateName {
+        /// The generated identifier.
+        name: String,
+        /// The key of the first flag.
+        first: String,
+        /// The key of the second flag.
+        second: String,
+    },
+}
+
+/// Generates a Rust module with a typed accessor for every flag in the snapshot, which must be
+/// in the format returned by LaunchDarkly's polling endpoint: an object holding `flags` and
+/// `segments`, each indexed by key.
+///
+/// Each accessor takes a [crate::Client] and a [crate::Context] and evaluates the flag, falling
+/// back to the flag's off variation, or its first variation if it has none.
+///
+/// - Flags with boolean variations return a `bool`.
+/// - Flags with numeric variations return an `i64` if every variation is a whole number, and an
+///   `f64` otherwise.
+/// - Flags with string variations return an enum with one variant per variation.
+/// - All other flags return a `serde_json::Value`, so the generated module must be compiled in a
+///   crate which depends on `serde_json`.
+pub fn generate_flag_module(snapshot: &str) -> Result<String, CodegenError> {
+    let data: AllData<Flag, Segment> = serde_json::from_str(snapshot)?;
+    let flags = data
+        .flags
+        .into_iter()
+        .map(|(key, flag)| Ok((key, serde_json::to_value(flag)?)))
+        .collect::<Result<BTreeMap<_, _>, serde_json::Error>>()?;
+
+    // Accessors are snake case and enums pascal case, so they cannot collide with each other.
+    let mut names = BTreeMap::new();
+    for (key, flag) in &flags {
+        let name = snake_case(key);
+        let enum_name = (kind_of(variations(flag)) == FlagKind::String).then(|| pascal_case(&name));
+        for name in iter::once(name).chain(enum_name) {
+            if let Some(first) = names.insert(name.clone(), key) {
+                return Err(CodegenError::DuplicateName {
+                    name,
+                    first: first.clone(),
+                    second: key.clone(),
+                });
+            }
+        }
+    }
+
+    // Paths in the generated code are fully qualified, as the enums generated for string flags
+    // may have any name.
+    let mut module = String::from(
+        "// This module was generated from a LaunchDarkly environment snapshot; do not edit it by\n\
+         // hand. Regenerate it when flags are added to or removed from the environment.\n",
+    );
+    for (key, flag) in &flags {
+        module.push('\n');
+        write_accessor(&mut module, key, flag);
+    }
+
+    Ok(module)
+}
+
+/// Reads the snapshot at `snapshot_path`, and writes the module generated from it by
+/// [generate_flag_module] to `module_path`. This is intended to be called from a build script.
+pub fn generate_flag_module_file(
+    snapshot_path: impl AsRef<Path>,
+    module_path: impl AsRef<Path>,
+) -> Result<(), CodegenError> {
+    let snapshot = fs::read_to_string(snapshot_path)?;
+    fs::write(module_path, generate_flag_module(&snapshot)?)?;
+    Ok(())
+}
+
+fn variations(flag: &Value) -> &[Value] {
+    match flag.get("variations") {
+        Some(Value::Array(variations)) if !variations.is_empty() => variations.as_slice(),
+        _ => &[],
+    }
+}
+
+fn kind_of(variations: &[Value]) -> FlagKind {
+    let kinds = variations.iter().map(FlagKind::of).collect::<HashSet<_>>();
+    match kinds.len() {
+        1 => kinds.into_iter().next().unwrap_or(FlagKind::Json),
+        _ => FlagKind::Json,
+    }
+}
+
+fn write_accessor(module: &mut String, key: &str, flag: &Value) {
+    let variations = variations(flag);
+    let default = flag
+        .get("offVariation")
+        .and_then(Value::as_u64)
+        .and_then(|index| variations.get(index as usize))
+        .or_else(|| variations.first())
+        .unwrap_or(&Value::Null);
+
+    let kind = kind_of(variations);
+
+    let name = snake_case(key);
+    let key = format!("{key:?}");
+    if kind == FlagKind::String {
+        write_enum_accessor(module, &name, &key, variations, default);
+        return;
+    }
+
+    let (return_type, body) = match kind {
+        FlagKind::Bool => (
+            "bool",
+            format!("client.bool_variation(context, {key}, {default})"),
+        ),
+        // Numeric variations are held as floats, so whole numbers are identified by value.
+        FlagKind::Number if variations.iter().all(is_whole_number) => (
+            "i64",
+            format!(
+                "client.int_variation(context, {key}, {})",
+                default.as_f64().unwrap_or_default() as i64
+            ),
+        ),
+        FlagKind::Number => (
+            "f64",
+            format!(
+                "client.float_variation(context, {key}, {:?}_f64)",
+                default.as_f64().unwrap_or_default()
+            ),
+        ),
+        _ => (
+            "::serde_json::Value",
+            format!("client.json_variation(context, {key}, ::serde_json::json!({default}))"),
+        ),
+    };
+    write_function(module, &name, &key, return_type, &body);
+}
+
+// Writes the accessor for a flag, which evaluates it with `body`.
+fn write_function(module: &mut String, name: &str, key: &str, return_type: &str, body: &str) {
+    let _ = writeln!(
+        module,
+        "/// Evaluates the {key} flag.\n\
+         pub fn {name}(\n    \
+             client: &::launchdarkly_server_sdk::Client,\n    \
+             context: &::launchdarkly_server_sdk::Context,\n\
+         ) -> {return_type} {{\n    \
+             {body}\n\
+         }}"
+    );
+}
+
+fn is_whole_number(value: &Value) -> bool {
+    value
+        .as_f64()
+        .is_some_and(|number| number.fract() == 0.0 && number.abs() < i64::MAX as f64)
+}
+
+fn write_enum_accessor(
+    module: &mut String,
+    name: &str,
+    key: &str,
+    variations: &[Value],
+    default: &Value,
+) {
+    let enum_name = pascal_case(name);
+    let mut seen = HashSet::new();
+    let variants = variations
+        .iter()
+        .filter(|value| seen.insert(value.as_str()))
+        .enumerate()
+        .map(|(index, value)| {
+            (
+                variant_name(value.as_str().unwrap_or_default()),
+                index,
+                value,
+            )
+        })
+        .collect::<Vec<_>>();
+    // Variations whose names collide are distinguished by their position, or by the first
+    // position after it which does not produce the name of another variation.
+    let reserved = variants
+        .iter()
+        .map(|(variant, _, _)| variant.clone())
+        .collect::<HashSet<_>>();
+    let mut names = HashSet::new();
+    let variants = variants
+        .into_iter()
+        .map(|(variant, index, value)| {
+            if names.insert(variant.clone()) {
+                return (variant, value);
+            }
+            let variant = (index..)
+                .map(|suffix| format!("{variant}{suffix}"))
+                .find(|name| !reserved.contains(name) && !names.contains(name))
+                .unwrap_or_default();
+            names.insert(variant.clone());
+            (variant, value)
+        })
+        .collect::<Vec<_>>();
+    let default_variant = variants
+        .iter()
+        .find(|(_, value)| *value == default)
+        .map(|(variant, _)| variant.as_str())
+        .unwrap_or_default();
+
+    let _ = writeln!(module, "/// The variations of the {key} flag.");
+    let _ = writeln!(module, "#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]");
+    let _ = writeln!(module, "pub enum {enum_name} {{");
+    for (variant, value) in &variants {
+        let value = value.as_str().unwrap_or_default();
+        let _ = writeln!(module, "    /// The {value:?} variation.\n    {variant},");
+    }
+    let _ = writeln!(module, "}}\n\nimpl {enum_name} {{");
+    let _ = writeln!(
+        module,
+        "    /// The value of the variation.\n    pub fn as_str(&self) -> &'static str {{\n        match self {{"
+    );
+    for (variant, value) in &variants {
+        let value = value.as_str().unwrap_or_default();
+        let _ = writeln!(module, "            {enum_name}::{variant} => {value:?},");
+    }
+    let _ = writeln!(module, "        }}\n    }}\n");
+    let _ = writeln!(
+        module,
+        "    /// Returns the variant for the given value, if it is one of the flag's variations.\n    \
+         pub fn from_variation(value: &str) -> ::core::option::Option<Self> {{\n        match value {{"
+    );
+    for (variant, value) in &variants {
+        let value = value.as_str().unwrap_or_default();
+        let _ = writeln!(
+            module,
+            "            {value:?} => ::core::option::Option::Some({enum_name}::{variant}),"
+        );
+    }
+    let _ = writeln!(
+        module,
+        "            _ => ::core::option::Option::None,\n        }}\n    }}\n}}\n"
+    );
+    let body = format!(
+        "let default = {enum_name}::{default_variant};\n    \
+         let value = client.str_variation(context, {key}, default.as_str().to_string());\n    \
+         {enum_name}::from_variation(&value).unwrap_or(default)"
+    );
+    write_function(module, name, key, &enum_name, &body);
+}
+
+const KEYWORDS: &[&str] = &[
+    "as", "async", "await", "box", "break", "const", "continue", "crate", "do", "dyn", "else",
+    "enum", "extern", "false", "final", "fn", "for", "gen", "if", "impl", "in", "let", "loop",
+    "macro", "match", "mod", "move", "mut", "override", "priv", "pub", "ref", "return", "self",
+    "static", "struct", "super", "trait", "true", "try", "type", "typeof", "unsafe", "unsized",
+    "use", "virtual", "where", "while", "yield",
+];
+
+// Splits a flag key or variation into lower case words joined by underscores.
+fn words(value: &str) -> String {
+    let mut name = String::new();
+    let mut previous_lower = false;
+    for c in value.chars() {
+        if c.is_ascii_alphanumeric() {
+            if c.is_ascii_uppercase() && previous_lower {
+                name.push('_');
+            }
+            name.push(c.to_ascii_lowercase());
+            previous_lower = c.is_ascii_lowercase() || c.is_ascii_digit();
+        } else {
+            if !name.is_empty() && !name.ends_with('_') {
+                name.push('_');
+            }
+            previous_lower = false;
+        }
+    }
+
+    name.trim_end_matches('_').to_string()
+}
+
+// Converts a flag key into a snake case identifier.
+fn snake_case(value: &str) -> String {
+    let name = words(value);
+    if name.is_empty() || name.starts_with(|c: char| c.is_ascii_digit()) {
+        format!("flag_{name}").trim_end_matches('_').to_string()
+    } else if KEYWORDS.contains(&name.as_str()) {
+        format!("{name}_flag")
+    } else {
+        name
+    }
+}
+
+fn variant_name(value: &str) -> String {
+    let name = pascal_case(&words(value));
+    if name.is_empty() || name.starts_with(|c: char| c.is_ascii_digit()) {
+        format!("Variation{name}")
+    } else if name == "Self" {
+        format!("{name}_")
+    } else {
+        name
+    }
+}
+
+fn pascal_case(snake_case: &str) -> String {
+    snake_case
+        .split('_')
+        .map(|word| {
+            let mut chars = word.chars();
+            match chars.next() {
+                Some(first) => first.to_ascii_uppercase().to_string() + chars.as_str(),
+                None => String::new(),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    fn snapshot(flags: Value) -> String {
+        json!({ "flags": flags, "segments": {} }).to_string()
+    }
+
+    fn flag(key: &str, variations: Value, off_variation: Option<u64>) -> Value {
+        json!({
+            "key": key,
+            "version": 1,
+            "on": true,
+            "targets": [],
+            "rules": [],
+            "prerequisites": [],
+            "fallthrough": {"variation": 0},
+            "offVariation": off_variation,
+            "variations": variations,
+            "salt": "salt"
+        })
+    }
+
+    #[test_case("my-flag", "my_flag")]
+    #[test_case("myFlag", "my_flag")]
+    #[test_case("new.checkout-v2", "new_checkout_v2")]
+    #[test_case("2fa", "flag_2fa")]
+    #[test_case("type", "type_flag")]
+    #[test_case("---", "flag")]
+    fn flag_keys_become_identifiers(key: &str, expected: &str) {
+        assert_eq!(expected, snake_case(key));
+    }
+
+    #[test_case("dark-mode", "DarkMode")]
+    #[test_case("self", "Self_")]
+    #[test_case("2x", "Variation2x")]
+    #[test_case("", "Variation")]
+    fn variations_become_variant_names(value: &str, expected: &str) {
+        assert_eq!(expected, variant_name(value));
+    }
+
+    #[test]
+    fn generates_typed_accessors() {
+        let module = generate_flag_module(&snapshot(json!({
+            "enabled": flag("enabled", json!([false, true]), Some(1)),
+            "limit": flag("limit", json!([1, 10]), None),
+            "ratio": flag("ratio", json!([0.5, 1]), None),
+            "config": flag("config", json!([{"a": 1}, [1]]), None),
+        })))
+        .expect("module should generate");
+
+        assert!(module.contains(
+            "pub fn enabled(\n    \
+                 client: &::launchdarkly_server_sdk::Client,\n    \
+                 context: &::launchdarkly_server_sdk::Context,\n\
+             ) -> bool {\n    \
+                 client.bool_variation(context, \"enabled\", true)\n\
+             }"
+        ));
+        assert!(module.contains(") -> i64 {\n    client.int_variation(context, \"limit\", 1)\n}"));
+        assert!(module.contains("client.float_variation(context, \"ratio\", 0.5_f64)"));
+        assert!(module.contains(
+            ") -> ::serde_json::Value {\n    \
+             client.json_variation(context, \"config\", ::serde_json::json!({\"a\":1}))\n}"
+        ));
+    }
+
+    #[test]
+    fn string_flags_become_enums() {
+        let module = generate_flag_module(&snapshot(json!({
+            "checkout-theme": flag("checkout-theme", json!(["dark-mode", "light", "Light"]), Some(1)),
+        })))
+        .expect("module should generate");
+
+        assert!(module.contains("pub enum CheckoutTheme {"));
+        assert!(module.contains("    DarkMode,\n"));
+        assert!(module.contains("    Light,\n"));
+        assert!(module.contains("    Light2,\n"));
+        assert!(module.contains("CheckoutTheme::Light2 => \"Light\","));
+        assert!(module
+            .contains("\"dark-mode\" => ::core::option::Option::Some(CheckoutTheme::DarkMode),"));
+        assert!(module.contains(
+            "pub fn checkout_theme(\n    \
+                 client: &::launchdarkly_server_sdk::Client,\n    \
+                 context: &::launchdarkly_server_sdk::Context,\n\
+             ) -> CheckoutTheme {\n    \
+                 let default = CheckoutTheme::Light;"
+        ));
+    }
+
+    #[test]
+    fn suffixed_variants_do_not_collide_with_other_variations() {
+        let module = generate_flag_module(&snapshot(json!({
+            "theme": flag("theme", json!(["Light", "light", "Light1"]), None),
+        })))
+        .expect("module should generate");
+
+        assert!(module.contains("Theme::Light => \"Light\","));
+        assert!(module.contains("Theme::Light2 => \"light\","));
+        assert!(module.contains("Theme::Light1 => \"Light1\","));
+    }
+
+    #[test]
+    fn colliding_flag_keys_are_reported() {
+        let result = generate_flag_module(&snapshot(json!({
+            "my-flag": flag("my-flag", json!([false, true]), None),
+            "my.flag": flag("my.flag", json!([false, true]), None),
+        })));
+
+        match result {
+            Err(CodegenError::DuplicateName { name, .. }) => assert_eq!("my_flag", name),
+            _ => panic!("expected a duplicate name error"),
+        }
+    }
+
+    #[test]
+    fn colliding_enum_names_are_reported() {
+        let result = generate_flag_module(&snapshot(json!({
+            "flag-1": flag("flag-1", json!(["a", "b"]), None),
+            "flag1": flag("flag1", json!(["c", "d"]), None),
+        })));
+
+        match result {
+            Err(CodegenError::DuplicateName { name, .. }) => assert_eq!("Flag1", name),
+            _ => panic!("expected a duplicate name error"),
+        }
+
+        // Flags which do not generate enums only need distinct accessors.
+        assert!(generate_flag_module(&snapshot(json!({
+            "flag-1": flag("flag-1", json!([false, true]), None),
+            "flag1": flag("flag1", json!([false, true]), None),
+        })))
+        .is_ok());
+    }
+
+    #[test]
+    fn invalid_snapshots_are_reported() {
+        assert!(matches!(
+            generate_flag_module("{\"flags\": []}"),
+            Err(CodegenError::InvalidSnapshot(_))
+        ));
+    }
+}
@@ -4,6 +4,7 @@ use crate::data_source_builders::{DataSourceFactory, NullDataSourceBuilder};
 use crate::events::processor_builders::{
     EventProcessorBuilder, EventProcessorFactory, NullEventProcessorBuilder,
 };
+use crate::flag_overrides::{FlagOverrides, FlagOverridesBuilder};
 use crate::stores::store_builders::{DataStoreFactory, InMemoryDataStoreBuilder};
 use crate::{ServiceEndpointsBuilder, StreamingDataSourceBuilder};
 
@@ -126,6 +127,7 @@ pub struct Config {
     data_source_builder: Box<dyn DataSourceFactory>,
     event_processor_builder: Box<dyn EventProcessorFactory>,
     application_tag: Option<String>,
+    flag_overrides: Option<FlagOverrides>,
     offline: bool,
     daemon_mode: bool,
 }
@@ -142,17 +144,17 @@ impl Config {
     }
 
     /// Returns the DataStoreFactory
-    pub fn data_store_builder(&self) -> &(dyn DataStoreFactory) {
+    pub fn data_store_builder(&self) -> &dyn DataStoreFactory {
         self.data_store_builder.borrow()
     }
 
     /// Returns the DataSourceFactory
-    pub fn data_source_builder(&self) -> &(dyn DataSourceFactory) {
+    pub fn data_source_builder(&self) -> &dyn DataSourceFactory {
         self.data_source_builder.borrow()
     }
 
     /// Returns the EventProcessorFactory
-    pub fn event_processor_builder(&self) -> &(dyn EventProcessorFactory) {
+    pub fn event_processor_builder(&self) -> &dyn EventProcessorFactory {
         self.event_processor_builder.borrow()
     }
 
@@ -170,6 +172,11 @@ impl Config {
     pub fn application_tag(&self) -> &Option<String> {
         &self.application_tag
     }
+
+    /// Returns the flag overrides if provided
+    pub fn flag_overrides(&self) -> Option<&FlagOverrides> {
+        self.flag_overrides.as_ref()
+    }
 }
 
 /// Error type used to represent failures when building a Config instance.
@@ -194,6 +201,7 @@ pub struct ConfigBuilder {
     data_source_builder: Option<Box<dyn DataSourceFactory>>,
     event_processor_builder: Option<Box<dyn EventProcessorFactory>>,
     application_info: Option<ApplicationInfo>,
+    flag_overrides: Option<FlagOverridesBuilder>,
     offline: bool,
     daemon_mode: bool,
     sdk_key: String,
@@ -210,6 +218,7 @@ impl ConfigBuilder {
             offline: false,
             daemon_mode: false,
             application_info: None,
+            flag_overrides: None,
             sdk_key: sdk_key.to_string(),
         }
     }
@@ -275,6 +284,39 @@ impl ConfigBuilder {
         self
     }
 
+    /// Forces the values of flags locally, taking precedence over the values from LaunchDarkly.
+    /// For usage see [FlagOverridesBuilder].
+    ///
+    /// Overrides apply in offline mode as well, and the client logs a warning when it is built
+    /// with any configured so that they are not left on unnoticed.
+    ///
+    /// Overridden evaluations are not reported to LaunchDarkly: they produce no analytics events,
+    /// and do not appear in flag usage summaries. Their details carry a
+    /// [launchdarkly_server_sdk_evaluation::Reason::Fallthrough] reason without a variation
+    /// index, both from [crate::Client::variation_detail] and in [crate::Client::all_flags_detail];
+    /// use [crate::Client::variation_detail_with_source] to tell them apart from evaluated
+    /// values.
+    pub fn flag_overrides(mut self, builder: &FlagOverridesBuilder) -> Self {
+        self.flag_overrides = Some(builder.clone());
+        self
+    }
+
+    // Used by the client manager to share its connector and runtime with environments which do
+    // not configure their own data source or event processor.
+    pub(crate) fn default_components(
+        mut self,
+        data_source: &dyn DataSourceFactory,
+        event_processor: &dyn EventProcessorFactory,
+    ) -> Self {
+        if self.data_source_builder.is_none() && !self.offline && !self.daemon_mode {
+            self.data_source_builder = Some(data_source.to_owned());
+        }
+        if self.event_processor_builder.is_none() && !self.offline {
+            self.event_processor_builder = Some(event_processor.to_owned());
+        }
+        self
+    }
+
     /// Create a new instance of [Config] based on the [ConfigBuilder] configuration.
     pub fn build(self) -> Result<Config, BuildError> {
         let service_endpoints_builder = match &self.service_endpoints_builder {
@@ -335,6 +377,12 @@ impl ConfigBuilder {
             _ => None,
         };
 
+        let flag_overrides = self
+            .flag_overrides
+            .map(|builder| builder.build())
+            .transpose()
+            .map_err(|e| BuildError::InvalidConfig(e.to_string()))?;
+
         Ok(Config {
             sdk_key: self.sdk_key,
             service_endpoints_builder,
@@ -342,6 +390,7 @@ impl ConfigBuilder {
             data_source_builder,
             event_processor_builder,
             application_tag,
+            flag_overrides,
             offline: self.offline,
             daemon_mode: self.daemon_mode,
         })
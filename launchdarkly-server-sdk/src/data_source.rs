@@ -6,15 +6,17 @@ use crate::stores::store::{DataStore, UpdateError};
 use crate::LAUNCHDARKLY_TAGS_HEADER;
 use es::{Client, ClientBuilder, ReconnectOptionsBuilder};
 use eventsource_client as es;
-use futures::StreamExt;
+use futures::{FutureExt, StreamExt};
 use hyper::client::connect::Connection;
 use hyper::service::Service;
 use hyper::Uri;
 use launchdarkly_server_sdk_evaluation::{Flag, Segment};
 use parking_lot::RwLock;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{self, LineWriter, Write};
 use std::sync::{Arc, Mutex, Once};
-use std::time::Duration;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use tokio::io::{AsyncRead, AsyncWrite};
 use tokio::sync::broadcast;
 use tokio::time;
@@ -76,6 +78,7 @@ pub struct StreamingDataSource {
 }
 
 impl StreamingDataSource {
+    #[allow(clippy::result_large_err)]
     pub fn new<C>(
         base_url: &str,
         sdk_key: &str,
@@ -183,13 +186,7 @@ impl DataSource for StreamingDataSource {
 
                         debug!("data source got an event: {}", event.event_type);
 
-                        let stored = match event.event_type.as_str() {
-                            "put" => process_put(&mut *data_store, event),
-                            "patch" => process_patch(&mut *data_store, event),
-                            "delete" => process_delete(&mut *data_store, event),
-                            _ => Err(Error::InvalidEventType(event.event_type)),
-                        };
-                        if let Err(e) = stored {
+                        if let Err(e) = process_event(&mut *data_store, event) {
                             init_success = false;
                             error!("error processing update: {:?}", e);
                         }
@@ -298,6 +295,142 @@ impl DataSource for NullDataSource {
     }
 }
 
+/// An event received by a data source, as stored in a recording.
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct RecordedEvent {
+    event: String,
+    data: String,
+    /// When the event was received, in milliseconds since the Unix epoch.
+    timestamp: u64,
+}
+
+/// RecordingDataSource appends every event received by the wrapped data source to a file, one
+/// line of JSON per event.
+pub(crate) struct RecordingDataSource {
+    data_source: Arc<dyn DataSource>,
+    recording: Arc<Mutex<LineWriter<File>>>,
+}
+
+impl RecordingDataSource {
+    pub fn new(data_source: Arc<dyn DataSource>, recording: File) -> Self {
+        Self {
+            data_source,
+            recording: Arc::new(Mutex::new(LineWriter::new(recording))),
+        }
+    }
+}
+
+impl DataSource for RecordingDataSource {
+    fn subscribe(
+        &self,
+        data_store: Arc<RwLock<dyn DataStore>>,
+        init_complete: Arc<dyn Fn(bool) + Send + Sync>,
+        event_received: EventReceived,
+        shutdown_receiver: broadcast::Receiver<()>,
+    ) {
+        let recording = self.recording.clone();
+        let record_event: EventReceived = Arc::new(move |sse| {
+            if let es::SSE::Event(event) = sse {
+                let recorded = RecordedEvent {
+                    event: event.event_type.clone(),
+                    data: event.data.clone(),
+                    timestamp: SystemTime::now()
+                        .duration_since(UNIX_EPOCH)
+                        .map_or(0, |elapsed| elapsed.as_millis() as u64),
+                };
+                if let Ok(mut recording) = recording.lock() {
+                    let written = serde_json::to_writer(&mut *recording, &recorded)
+                        .map_err(io::Error::from)
+                        .and_then(|_| writeln!(recording));
+                    if let Err(e) = written {
+                        warn!("failed to record data source event: {}", e);
+                    }
+                }
+            }
+            event_received(sse);
+        });
+
+        self.data_source
+            .subscribe(data_store, init_complete, record_event, shutdown_receiver);
+    }
+}
+
+/// ReplayDataSource applies previously recorded events to the data store, in their original
+/// order.
+pub(crate) struct ReplayDataSource {
+    events: Arc<Vec<RecordedEvent>>,
+    original_timing: bool,
+}
+
+impl ReplayDataSource {
+    pub fn new(events: Vec<RecordedEvent>, original_timing: bool) -> Self {
+        Self {
+            events: Arc::new(events),
+            original_timing,
+        }
+    }
+}
+
+impl DataSource for ReplayDataSource {
+    fn subscribe(
+        &self,
+        data_store: Arc<RwLock<dyn DataStore>>,
+        init_complete: Arc<dyn Fn(bool) + Send + Sync>,
+        event_received: EventReceived,
+        shutdown_receiver: broadcast::Receiver<()>,
+    ) {
+        let events = self.events.clone();
+        let original_timing = self.original_timing;
+
+        tokio::spawn(async move {
+            let shutdown_stream = BroadcastStream::new(shutdown_receiver);
+            let mut shutdown_future = shutdown_stream.into_future();
+            let notify_init = Once::new();
+            let mut init_success = true;
+            let mut previous_timestamp = None;
+
+            for recorded in events.iter() {
+                let delay = match previous_timestamp {
+                    Some(previous) if original_timing => {
+                        Duration::from_millis(recorded.timestamp.saturating_sub(previous))
+                    }
+                    _ => Duration::ZERO,
+                };
+                previous_timestamp = Some(recorded.timestamp);
+
+                let sleep = time::sleep(delay).fuse();
+                futures::pin_mut!(sleep);
+                futures::select! {
+                    _ = shutdown_future => return,
+                    _ = sleep => (),
+                }
+
+                let event = es::Event {
+                    event_type: recorded.event.clone(),
+                    data: recorded.data.clone(),
+                    id: None,
+                    retry: None,
+                };
+                event_received(&es::SSE::Event(event.clone()));
+
+                debug!("data source replayed an event: {}", event.event_type);
+
+                if let Err(e) = process_event(&mut *data_store.write(), event) {
+                    init_success = false;
+                    error!("error processing replayed update: {:?}", e);
+                }
+
+                notify_init.call_once(|| (init_complete)(init_success));
+            }
+
+            notify_init.call_once(|| {
+                warn!("recording contained no events; data source is not initialized");
+                (init_complete)(false)
+            });
+        });
+    }
+}
+
 #[cfg(test)]
 pub(crate) struct MockDataSource {
     delay_init: u64,
@@ -338,6 +471,15 @@ fn parse_event_data<'a, T: Deserialize<'a>>(event: &'a es::Event) -> Result<T> {
     })
 }
 
+fn process_event(data_store: &mut dyn DataStore, event: es::Event) -> Result<()> {
+    match event.event_type.as_str() {
+        "put" => process_put(data_store, event),
+        "patch" => process_patch(data_store, event),
+        "delete" => process_delete(data_store, event),
+        _ => Err(Error::InvalidEventType(event.event_type)),
+    }
+}
+
 fn process_put(data_store: &mut dyn DataStore, event: es::Event) -> Result<()> {
     let put: PutData = parse_event_data(&event)?;
     if put.path == "/" || put.path.is_empty() {
@@ -395,8 +537,13 @@ mod tests {
     use test_case::test_case;
     use tokio::sync::broadcast;
 
-    use super::{DataSource, PollingDataSource, StreamingDataSource};
+    use super::{
+        DataSource, PollingDataSource, RecordedEvent, RecordingDataSource, ReplayDataSource,
+        StreamingDataSource,
+    };
     use crate::feature_requester_builders::HyperFeatureRequesterBuilder;
+    use crate::stores::store::DataStore;
+    use crate::test_common::basic_flag;
     use crate::{stores::store::InMemoryDataStore, LAUNCHDARKLY_TAGS_HEADER};
 
     #[test_case(Some("application-id/abc:application-sha/xyz".into()), "application-id/abc:application-sha/xyz")]
@@ -513,4 +660,140 @@ mod tests {
 
         mock.assert()
     }
+
+    async fn wait_for(condition: impl Fn() -> bool) {
+        for _ in 0..50 {
+            if condition() {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+        panic!("condition was not met within 5 seconds");
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn recorded_streams_can_be_replayed() {
+        let put =
+            json!({"path": "/", "data": {"flags": {"flag": basic_flag("flag")}, "segments": {}}});
+        let mut patched_flag = basic_flag("patched");
+        patched_flag.version = 2;
+        let patch = json!({"path": "/flags/patched", "data": patched_flag});
+        let delete = json!({"path": "/flags/flag", "version": 43});
+
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("GET", "/all")
+            .with_status(200)
+            .with_body(format!(
+                "event:put\ndata:{put}\n\nevent:patch\ndata:{patch}\n\nevent:delete\ndata:{delete}\n\n"
+            ))
+            .create_async()
+            .await;
+
+        let path =
+            std::env::temp_dir().join(format!("ld-recording-{}.jsonl", uuid::Uuid::new_v4()));
+        let streaming = StreamingDataSource::new(
+            &server.url(),
+            "sdk-key",
+            Duration::from_secs(60),
+            &None,
+            HttpConnector::new(),
+        )
+        .unwrap();
+        let recording = RecordingDataSource::new(
+            Arc::new(streaming),
+            std::fs::File::create(&path).expect("recording should be created"),
+        );
+
+        let (shutdown_tx, _) = broadcast::channel::<()>(1);
+        let recorded_store = Arc::new(RwLock::new(InMemoryDataStore::new()));
+        let received = Arc::new(Mutex::new(Vec::new()));
+        let event_types = received.clone();
+        recording.subscribe(
+            recorded_store.clone(),
+            Arc::new(|_| {}),
+            Arc::new(move |sse| {
+                if let eventsource_client::SSE::Event(event) = sse {
+                    event_types.lock().unwrap().push(event.event_type.clone());
+                }
+            }),
+            shutdown_tx.subscribe(),
+        );
+        // The stream ends after these events, so the delay stops it reconnecting during the test.
+        wait_for(|| received.lock().unwrap().len() >= 3).await;
+        let _ = shutdown_tx.send(());
+        assert_eq!(vec!["put", "patch", "delete"], *received.lock().unwrap());
+
+        let events: Vec<RecordedEvent> = std::fs::read_to_string(&path)
+            .expect("recording should be readable")
+            .lines()
+            .map(|line| serde_json::from_str(line).expect("line should be an event"))
+            .collect();
+        std::fs::remove_file(&path).expect("recording should be removed");
+        assert_eq!(3, events.len());
+        assert_eq!("patch", events[1].event);
+        assert_eq!(patch.to_string(), events[1].data);
+
+        let (shutdown_tx, _) = broadcast::channel::<()>(1);
+        let replayed_store = Arc::new(RwLock::new(InMemoryDataStore::new()));
+        let initialized = Arc::new(AtomicBool::new(false));
+        let init_state = initialized.clone();
+        ReplayDataSource::new(events, false).subscribe(
+            replayed_store.clone(),
+            Arc::new(move |success| init_state.store(success, Ordering::SeqCst)),
+            Arc::new(|_| {}),
+            shutdown_tx.subscribe(),
+        );
+        wait_for(|| replayed_store.read().to_store().flag("patched").is_some()).await;
+
+        assert!(initialized.load(Ordering::SeqCst));
+        for store in [&recorded_store, &replayed_store] {
+            let store = store.read();
+            assert!(store.to_store().flag("flag").is_none());
+            assert_eq!(
+                2,
+                store
+                    .to_store()
+                    .flag("patched")
+                    .expect("flag should be patched")
+                    .version
+            );
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn replays_can_keep_original_timing() {
+        let put = json!({"path": "/", "data": {"flags": {}, "segments": {}}}).to_string();
+        let patch = json!({"path": "/flags/flag", "data": basic_flag("flag")}).to_string();
+        let events = vec![
+            RecordedEvent {
+                event: "put".into(),
+                data: put,
+                timestamp: 1_000,
+            },
+            RecordedEvent {
+                event: "patch".into(),
+                data: patch,
+                timestamp: 1_500,
+            },
+        ];
+
+        let (shutdown_tx, _) = broadcast::channel::<()>(1);
+        let data_store = Arc::new(RwLock::new(InMemoryDataStore::new()));
+        let initialized = Arc::new(AtomicBool::new(false));
+        let init_state = initialized.clone();
+        ReplayDataSource::new(events, true).subscribe(
+            data_store.clone(),
+            Arc::new(move |success| init_state.store(success, Ordering::SeqCst)),
+            Arc::new(|_| {}),
+            shutdown_tx.subscribe(),
+        );
+
+        wait_for(|| initialized.load(Ordering::SeqCst)).await;
+        assert!(data_store.read().to_store().flag("flag").is_none());
+
+        wait_for(|| data_store.read().to_store().flag("flag").is_some()).await;
+        assert!(data_store.read().to_store().flag("flag").is_some());
+        let _ = shutdown_tx.send(());
+    }
 }
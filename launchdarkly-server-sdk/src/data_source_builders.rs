@@ -1,9 +1,14 @@
 use super::service_endpoints;
-use crate::data_source::{DataSource, NullDataSource, PollingDataSource, StreamingDataSource};
+use crate::data_source::{
+    DataSource, NullDataSource, PollingDataSource, RecordedEvent, RecordingDataSource,
+    ReplayDataSource, StreamingDataSource,
+};
 use crate::feature_requester_builders::{FeatureRequesterFactory, HyperFeatureRequesterBuilder};
 use hyper::{client::connect::Connection, service::Service, Uri};
 #[cfg(feature = "rustls")]
 use hyper_rustls::HttpsConnectorBuilder;
+use std::fs::{self, File};
+use std::path::PathBuf;
 use std::sync::{Arc, Mutex};
 use std::time::Duration;
 use thiserror::Error;
@@ -315,6 +320,158 @@ impl<C> Default for PollingDataSourceBuilder<C> {
     }
 }
 
+/// Contains methods for configuring a data source which records the events it receives.
+///
+/// The recording data source wraps another data source, typically a
+/// [StreamingDataSourceBuilder], and appends every event received from LaunchDarkly to a file. Each
+/// line of the file is a JSON object holding the event's type as `event`, its payload as `data`,
+/// and the time it was received as `timestamp`, in milliseconds since the Unix epoch. The file is
+/// truncated when the data source is built. Recordings can be played back with
+/// [ReplayDataSourceBuilder].
+///
+/// Only the streaming data source reports the events it receives, so recording any other data
+/// source produces an empty file.
+///
+/// # Examples
+///
+/// ```
+/// # use launchdarkly_server_sdk::{RecordingDataSourceBuilder, StreamingDataSourceBuilder, ConfigBuilder};
+/// # use hyper_rustls::HttpsConnector;
+/// # use hyper::client::HttpConnector;
+/// # fn main() {
+///     let streaming = StreamingDataSourceBuilder::<HttpsConnector<HttpConnector>>::new();
+///     ConfigBuilder::new("sdk-key")
+///         .data_source(&RecordingDataSourceBuilder::new("stream.jsonl", &streaming));
+/// # }
+/// ```
+pub struct RecordingDataSourceBuilder {
+    path: PathBuf,
+    data_source: Box<dyn DataSourceFactory>,
+}
+
+impl RecordingDataSourceBuilder {
+    /// Create a new instance of the [RecordingDataSourceBuilder], which records the events
+    /// received by the given data source to the file at `path`.
+    pub fn new(path: impl Into<PathBuf>, data_source: &dyn DataSourceFactory) -> Self {
+        Self {
+            path: path.into(),
+            data_source: data_source.to_owned(),
+        }
+    }
+}
+
+impl Clone for RecordingDataSourceBuilder {
+    fn clone(&self) -> Self {
+        Self {
+            path: self.path.clone(),
+            data_source: self.data_source.to_owned(),
+        }
+    }
+}
+
+impl DataSourceFactory for RecordingDataSourceBuilder {
+    fn build(
+        &self,
+        endpoints: &service_endpoints::ServiceEndpoints,
+        sdk_key: &str,
+        tags: Option<String>,
+    ) -> Result<Arc<dyn DataSource>, BuildError> {
+        let data_source = self.data_source.build(endpoints, sdk_key, tags)?;
+        let recording = File::create(&self.path).map_err(|e| {
+            BuildError::InvalidConfig(format!(
+                "failed to create recording {}: {}",
+                self.path.display(),
+                e
+            ))
+        })?;
+        Ok(Arc::new(RecordingDataSource::new(data_source, recording)))
+    }
+
+    fn to_owned(&self) -> Box<dyn DataSourceFactory> {
+        Box::new(self.clone())
+    }
+}
+
+/// Contains methods for configuring a data source which replays a recording made with
+/// [RecordingDataSourceBuilder].
+///
+/// The recorded events are applied to the data store in order, exactly as the streaming data
+/// source would have applied them, and no connection is made to LaunchDarkly. The client is
+/// initialized once the first event has been replayed. This allows flag state seen in production
+/// to be reproduced deterministically in tests.
+///
+/// # Examples
+///
+/// ```no_run
+/// # use launchdarkly_server_sdk::{ReplayDataSourceBuilder, ConfigBuilder};
+/// # fn main() {
+///     ConfigBuilder::new("sdk-key")
+///         .data_source(ReplayDataSourceBuilder::new("stream.jsonl").original_timing(true));
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct ReplayDataSourceBuilder {
+    path: PathBuf,
+    original_timing: bool,
+}
+
+impl ReplayDataSourceBuilder {
+    /// Create a new instance of the [ReplayDataSourceBuilder], which replays the recording at
+    /// `path`.
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            original_timing: false,
+        }
+    }
+
+    /// Whether events are replayed with the delays between them when they were recorded. By
+    /// default, this is false and events are replayed as fast as possible.
+    pub fn original_timing(&mut self, original_timing: bool) -> &mut Self {
+        self.original_timing = original_timing;
+        self
+    }
+}
+
+impl DataSourceFactory for ReplayDataSourceBuilder {
+    fn build(
+        &self,
+        _: &service_endpoints::ServiceEndpoints,
+        _: &str,
+        _: Option<String>,
+    ) -> Result<Arc<dyn DataSource>, BuildError> {
+        let path = self.path.display();
+        let recording = fs::read_to_string(&self.path).map_err(|e| {
+            BuildError::InvalidConfig(format!("failed to read recording {}: {}", path, e))
+        })?;
+
+        let events = recording
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty())
+            .map(|(index, line)| {
+                serde_json::from_str::<RecordedEvent>(line).map_err(|e| {
+                    BuildError::InvalidConfig(format!(
+                        "invalid event on line {} of recording {}: {}",
+                        index + 1,
+                        path,
+                        e
+                    ))
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Arc::new(ReplayDataSource::new(
+            events,
+            self.original_timing,
+        )))
+    }
+
+    fn to_owned(&self) -> Box<dyn DataSourceFactory> {
+        Box::new(self.clone())
+    }
+}
+
 /// For testing you can use this builder to inject the MockDataSource.
 #[cfg(test)]
 #[derive(Clone)]
@@ -414,4 +571,26 @@ mod tests {
         builder.initial_reconnect_delay(Duration::from_secs(1234));
         assert_eq!(builder.initial_reconnect_delay, Duration::from_secs(1234));
     }
+
+    #[test]
+    fn replay_builder_rejects_invalid_recordings() {
+        let endpoints = crate::ServiceEndpointsBuilder::new().build().unwrap();
+        let path =
+            std::env::temp_dir().join(format!("ld-recording-{}.jsonl", uuid::Uuid::new_v4()));
+
+        let result = ReplayDataSourceBuilder::new(&path).build(&endpoints, "test", None);
+        assert!(matches!(result, Err(BuildError::InvalidConfig(_))));
+
+        std::fs::write(
+            &path,
+            "{\"event\":\"put\",\"data\":\"{}\",\"timestamp\":1}\n\nnot json\n",
+        )
+        .unwrap();
+        let result = ReplayDataSourceBuilder::new(&path).build(&endpoints, "test", None);
+        std::fs::remove_file(&path).unwrap();
+        match result {
+            Err(BuildError::InvalidConfig(message)) => assert!(message.contains("line 3")),
+            _ => panic!("Build did not return the right type of error"),
+        };
+    }
 }
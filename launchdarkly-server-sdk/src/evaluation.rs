@@ -105,9 +105,9 @@ pub struct FlagDetail {
 
 /// DirectPrerequisiteRecorder records only the direct (top-level) prerequisites of a
 /// flag.
-struct DirectPrerequisiteRecorder {
+pub(crate) struct DirectPrerequisiteRecorder {
     target_flag_key: String,
-    prerequisites: RefCell<Vec<String>>,
+    pub(crate) prerequisites: RefCell<Vec<String>>,
 }
 
 impl DirectPrerequisiteRecorder {
@@ -215,6 +215,36 @@ impl FlagDetail {
         self.evaluations = evaluations;
         self.flag_state = flag_state;
     }
+
+    // Replaces the evaluations of overridden flags with their forced values. These carry no
+    // version or variation index and are never tracked. Only the store knows which flags are
+    // client-side, so with client_side_only just the flags which were already included are
+    // overridden.
+    pub(crate) fn apply_overrides(
+        &mut self,
+        overrides: HashMap<String, FlagValue>,
+        config: FlagDetailConfig,
+    ) {
+        for (key, value) in overrides {
+            if config.client_side_only && !self.evaluations.contains_key(&key) {
+                continue;
+            }
+
+            let reason = (config.with_reasons && !config.details_only_for_tracked_flags).then_some(
+                Reason::Fallthrough {
+                    in_experiment: false,
+                },
+            );
+            self.evaluations.insert(key.clone(), Some(value));
+            self.flag_state.insert(
+                key,
+                FlagState {
+                    reason,
+                    ..FlagState::default()
+                },
+            );
+        }
+    }
 }
 
 #[cfg(test)]
@@ -229,7 +259,8 @@ mod tests {
     };
     use crate::FlagDetailConfig;
     use assert_json_diff::assert_json_eq;
-    use launchdarkly_server_sdk_evaluation::ContextBuilder;
+    use launchdarkly_server_sdk_evaluation::{ContextBuilder, FlagValue};
+    use maplit::hashmap;
 
     #[test]
     fn flag_detail_handles_default_configuration() {
@@ -265,6 +296,48 @@ mod tests {
         );
     }
 
+    #[test]
+    fn overrides_replace_client_side_flags_only() {
+        let context = ContextBuilder::new("bob")
+            .build()
+            .expect("Failed to create context");
+        let mut store = InMemoryDataStore::new();
+
+        store
+            .upsert(
+                "client-flag",
+                PatchTarget::Flag(StorageItem::Item(basic_flag_with_visibility(
+                    "client-flag",
+                    true,
+                ))),
+            )
+            .expect("patch should apply");
+
+        let mut config = FlagDetailConfig::new();
+        config.client_side_only().with_reasons();
+        let mut flag_detail = FlagDetail::new(true);
+        flag_detail.populate(&store, &context, config);
+        flag_detail.apply_overrides(
+            hashmap! {
+                "client-flag".to_string() => FlagValue::Bool(false),
+                "unknown-flag".to_string() => FlagValue::Bool(true),
+            },
+            config,
+        );
+
+        let expected = json!({
+            "client-flag": false,
+            "$flagsState": {
+                "client-flag": {
+                    "reason": {"kind": "FALLTHROUGH"}
+                }
+            },
+            "$valid": true
+        });
+
+        assert_json_eq!(expected, flag_detail);
+    }
+
     #[test]
     fn flag_detail_handles_experimentation_reasons_correctly() {
         let context = ContextBuilder::new("bob")
@@ -99,17 +99,26 @@ impl EventDispatcher {
         let flush_ticker = tick(self.events_configuration.flush_interval);
         let (event_result_tx, event_result_rx) = bounded::<EventSenderResult>(self.thread_count);
 
-        let rt = tokio::runtime::Builder::new_multi_thread()
-            .worker_threads(self.thread_count)
-            .enable_io()
-            .enable_time()
-            .build();
-
-        let rt = match rt {
-            Ok(rt) => rt,
-            Err(e) => {
-                error!("Could not start runtime for event sending: {}", e);
-                return;
+        // A runtime is only created if one was not provided; it must outlive the loop below.
+        let (_owned_runtime, rt) = match &self.events_configuration.runtime {
+            Some(handle) => (None, handle.clone()),
+            None => {
+                let rt = tokio::runtime::Builder::new_multi_thread()
+                    .worker_threads(self.thread_count)
+                    .enable_io()
+                    .enable_time()
+                    .build();
+
+                match rt {
+                    Ok(rt) => {
+                        let handle = rt.handle().clone();
+                        (Some(rt), handle)
+                    }
+                    Err(e) => {
+                        error!("Could not start runtime for event sending: {}", e);
+                        return;
+                    }
+                }
             }
         };
 
@@ -602,6 +611,50 @@ mod tests {
         }
     }
 
+    #[test]
+    fn dispatcher_sends_events_on_provided_runtime() {
+        let runtime = tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(1)
+            .enable_all()
+            .build()
+            .expect("runtime should build");
+        let context = ContextBuilder::new("foo")
+            .build()
+            .expect("Failed to create context");
+
+        let (event_sender, event_rx) = create_event_sender();
+        let mut events_configuration =
+            create_events_configuration(event_sender, Duration::from_secs(100));
+        events_configuration.runtime = Some(runtime.handle().clone());
+        let (inbox_tx, inbox_rx) = bounded(events_configuration.capacity);
+
+        let dispatcher_handle = thread::Builder::new()
+            .spawn(move || create_dispatcher(events_configuration).start(inbox_rx))
+            .unwrap();
+
+        inbox_tx
+            .send(EventDispatcherMessage::EventMessage(
+                EventFactory::new(true).new_identify(context),
+            ))
+            .expect("event send failed");
+        inbox_tx
+            .send(EventDispatcherMessage::Flush)
+            .expect("flush failed");
+
+        let (tx, rx) = bounded(1);
+        inbox_tx
+            .send(EventDispatcherMessage::Close(tx))
+            .expect("failed to close");
+        rx.recv().expect("failed to notify on close");
+        dispatcher_handle.join().unwrap();
+
+        let kinds = event_rx
+            .iter()
+            .map(|event| event.kind())
+            .collect::<Vec<_>>();
+        assert_eq!(vec!["identify"], kinds);
+    }
+
     #[test]
     fn dispatcher_only_notices_identity_event_once() {
         let (event_sender, _) = create_event_sender();
@@ -24,6 +24,7 @@ pub struct EventsConfiguration {
     private_attributes: HashSet<Reference>,
     omit_anonymous_contexts: bool,
     on_success: OnEventSenderResultSuccess,
+    runtime: Option<tokio::runtime::Handle>,
 }
 
 #[cfg(test)]
@@ -41,6 +42,7 @@ fn create_events_configuration(
         private_attributes: HashSet::new(),
         omit_anonymous_contexts: false,
         on_success: Arc::new(|_| ()),
+        runtime: None,
     }
 }
 
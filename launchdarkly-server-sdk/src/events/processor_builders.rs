@@ -87,6 +87,7 @@ pub struct EventProcessorBuilder<C> {
     compress_events: bool,
     // diagnostic_recording_interval: Duration
     on_success: OnEventSenderResultSuccess,
+    runtime: Option<tokio::runtime::Handle>,
 }
 
 impl<C> EventProcessorFactory for EventProcessorBuilder<C>
@@ -157,6 +158,7 @@ where
             private_attributes: self.private_attributes.clone(),
             omit_anonymous_contexts: self.omit_anonymous_contexts,
             on_success: self.on_success.clone(),
+            runtime: self.runtime.clone(),
         };
 
         let events_processor =
@@ -186,6 +188,7 @@ impl<C> EventProcessorBuilder<C> {
             connector: None,
             compress_events: false,
             on_success: Arc::new(|_| ()),
+            runtime: None,
         }
     }
 
@@ -284,6 +287,15 @@ impl<C> EventProcessorBuilder<C> {
         self
     }
 
+    /// Sets the runtime that event payloads are sent on. This allows a single runtime to be shared
+    /// between multiple client instances.
+    ///
+    /// By default, the event processor creates its own runtime.
+    pub fn runtime(&mut self, runtime: tokio::runtime::Handle) -> &mut Self {
+        self.runtime = Some(runtime);
+        self
+    }
+
     #[cfg(test)]
     /// Test only functionality that allows us to override the event sender.
     pub fn event_sender(&mut self, event_sender: Arc<dyn EventSender>) -> &mut Self {
@@ -0,0 +1,623 @@
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::net::TcpListener;
+use std::sync::Arc;
+use std::time::Duration;
+
+use hyper::body::Bytes;
+use hyper::header::{CACHE_CONTROL, CONTENT_TYPE};
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Method, Request, Response, Server, StatusCode};
+use launchdarkly_server_sdk_evaluation::{Flag, Segment};
+use parking_lot::{Mutex, RwLock};
+use serde::Deserialize;
+use serde_json::Value;
+use thiserror::Error;
+use tokio::sync::{broadcast, oneshot, Notify};
+
+use crate::stores::store_types::AllData;
+use crate::ServiceEndpointsBuilder;
+
+const UPDATE_CAPACITY: usize = 1000;
+
+/// Error type used to represent failures when starting a [FakeLaunchDarkly] service.
+#[non_exhaustive]
+#[derive(Debug, Error)]
+pub enum FakeLaunchDarklyError {
+    /// Error used when the service could not listen on a local port.
+    #[error("failed to listen: {0}")]
+    Io(#[from] std::io::Error),
+    /// Error used when the server could not be started on the listener.
+    #[error("server failed: {0}")]
+    Server(#[from] hyper::Error),
+}
+
+/// An analytics event posted to a [FakeLaunchDarkly] service.
+///
+/// Events are deserialized from the JSON form in which the SDK sends them, as documented by
+/// LaunchDarkly's events API, and so can also be read from other captures of that form.
+#[non_exhaustive]
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum CapturedEvent {
+    /// Sent for an evaluation of a flag which tracks events.
+    Feature(CapturedFeatureEvent),
+    /// Sent for an evaluation of a flag while debugging is enabled for it.
+    Debug(CapturedFeatureEvent),
+    /// Sent by [crate::Client::track_event] and the other `track` methods.
+    Custom(CapturedCustomEvent),
+    /// Sent by [crate::Client::identify].
+    Identify(CapturedContextEvent),
+    /// Sent the first time a context appears in other events.
+    Index(CapturedContextEvent),
+    /// Counts the evaluations of each flag since the previous summary.
+    Summary(CapturedSummaryEvent),
+    /// Sent for each migration operation. The event is kept as it was posted.
+    MigrationOp(Value),
+}
+
+impl CapturedEvent {
+    /// The kind of the event, such as `"feature"`, `"custom"`, `"identify"`, `"index"` or
+    /// `"summary"`.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            CapturedEvent::Feature(_) => "feature",
+            CapturedEvent::Debug(_) => "debug",
+            CapturedEvent::Custom(_) => "custom",
+            CapturedEvent::Identify(_) => "identify",
+            CapturedEvent::Index(_) => "index",
+            CapturedEvent::Summary(_) => "summary",
+            CapturedEvent::MigrationOp(_) => "migration_op",
+        }
+    }
+}
+
+/// A `feature` or `debug` event posted to a [FakeLaunchDarkly] service.
+#[non_exhaustive]
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct CapturedFeatureEvent {
+    /// When the flag was evaluated, in milliseconds since the Unix epoch.
+    pub creation_date: u64,
+    /// The key of the evaluated flag.
+    pub key: String,
+    /// The evaluated context, for events which include it in full.
+    pub context: Option<Value>,
+    /// The keys of the evaluated context by kind, for events which do not include it in full.
+    pub context_keys: Option<HashMap<String, String>>,
+    /// The value the flag evaluated to.
+    pub value: Value,
+    /// The index of the variation the flag evaluated to, if any.
+    pub variation: Option<usize>,
+    /// The default value given to the evaluation.
+    pub default: Value,
+    /// The reason for the evaluation's result, if reasons were requested or required.
+    pub reason: Option<Value>,
+    /// The version of the flag, if it exists.
+    pub version: Option<u64>,
+    /// The key of the flag this was evaluated as a prerequisite of, if any.
+    pub prereq_of: Option<String>,
+}
+
+/// A `custom` event posted to a [FakeLaunchDarkly] service.
+#[non_exhaustive]
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct CapturedCustomEvent {
+    /// When the event was tracked, in milliseconds since the Unix epoch.
+    pub creation_date: u64,
+    /// The key of the event.
+    pub key: String,
+    /// The keys of the context the event was tracked for, by kind.
+    pub context_keys: HashMap<String, String>,
+    /// The data attached to the event, or null if there was none.
+    #[serde(default)]
+    pub data: Value,
+    /// The metric value attached to the event, if any.
+    pub metric_value: Option<f64>,
+}
+
+/// An `identify` or `index` event posted to a [FakeLaunchDarkly] service.
+#[non_exhaustive]
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct CapturedContextEvent {
+    /// When the event was created, in milliseconds since the Unix epoch.
+    pub creation_date: u64,
+    /// The context, with any private attributes redacted.
+    pub context: Value,
+}
+
+/// A `summary` event posted to a [FakeLaunchDarkly] service.
+#[non_exhaustive]
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct CapturedSummaryEvent {
+    /// The time of the first summarized evaluation, in milliseconds since the Unix epoch.
+    pub start_date: u64,
+    /// The time of the last summarized evaluation, in milliseconds since the Unix epoch.
+    pub end_date: u64,
+    /// The evaluation counters of each flag, by flag key.
+    pub features: HashMap<String, Value>,
+}
+
+/// An in-process stand-in for LaunchDarkly's services, for testing applications end-to-end
+/// against a real [crate::Client] without network access.
+///
+/// The service listens on a local port and serves the same endpoints the SDK uses:
+///
+/// - `GET /all`, used by the streaming data source. Each connection receives a `put` event with
+///   every flag and segment, followed by the updates pushed by the test.
+/// - `GET /sdk/latest-all`, used by the polling data source.
+/// - `POST /bulk`, used by the event processor. Posted events are captured for inspection.
+///
+/// Clients use the service by passing [FakeLaunchDarkly::service_endpoints] to
+/// [crate::ConfigBuilder::service_endpoints]. The service stops when it is dropped.
+///
+/// # Examples
+///
+/// ```
+/// # use launchdarkly_server_sdk::{Client, ConfigBuilder, ContextBuilder, FakeLaunchDarkly};
+/// # use std::time::Duration;
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+///     let fake = FakeLaunchDarkly::start()?;
+///     let config = ConfigBuilder::new("sdk-key")
+///         .service_endpoints(&fake.service_endpoints())
+///         .build()?;
+///     let client = Client::build(config)?;
+///     client.start_with_default_executor();
+///     client.wait_for_initialization(Duration::from_secs(5)).await;
+///
+///     let context = ContextBuilder::new("user-key").build()?;
+///     client.bool_variation(&context, "new-checkout", false);
+///     client.flush();
+///     let events = fake.wait_for_events(2, Duration::from_secs(5)).await;
+/// #   client.close();
+/// #   Ok(())
+/// # }
+/// ```
+pub struct FakeLaunchDarkly {
+    url: String,
+    state: Arc<State>,
+    shutdown: Option<oneshot::Sender<()>>,
+}
+
+impl FakeLaunchDarkly {
+    /// Starts the service on an unused local port, with no flags or segments. This must be
+    /// called from within a tokio runtime.
+    pub fn start() -> Result<Self, FakeLaunchDarklyError> {
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        listener.set_nonblocking(true)?;
+        let url = format!("http://{}", listener.local_addr()?);
+
+        let (updates, _) = broadcast::channel(UPDATE_CAPACITY);
+        let state = Arc::new(State {
+            data: RwLock::new(AllData {
+                flags: Default::default(),
+                segments: Default::default(),
+            }),
+            updates: RwLock::new(Some(updates)),
+            events: Mutex::new(Vec::new()),
+            events_received: Notify::new(),
+        });
+
+        let service_state = state.clone();
+        let make_service = make_service_fn(move |_| {
+            let state = service_state.clone();
+            async move {
+                Ok::<_, Infallible>(service_fn(move |request| {
+                    let state = state.clone();
+                    async move { Ok::<_, Infallible>(state.handle(request).await) }
+                }))
+            }
+        });
+
+        let (shutdown, shutdown_rx) = oneshot::channel::<()>();
+        let server = Server::from_tcp(listener)?.serve(make_service);
+        let server_state = state.clone();
+        tokio::spawn(async move {
+            let result = server
+                .with_graceful_shutdown(async {
+                    let _ = shutdown_rx.await;
+                    // Dropping the only sender ends every open stream, so that shutdown can complete.
+                    server_state.updates.write().take();
+                })
+                .await;
+            if let Err(e) = result {
+                error!("fake LaunchDarkly service failed: {}", e);
+            }
+        });
+
+        Ok(Self {
+            url,
+            state,
+            shutdown: Some(shutdown),
+        })
+    }
+
+    /// The base URL of the service.
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// Service endpoints which direct a client's streaming, polling and events connections to
+    /// the service.
+    pub fn service_endpoints(&self) -> ServiceEndpointsBuilder {
+        let mut endpoints = ServiceEndpointsBuilder::new();
+        endpoints.relay_proxy(&self.url);
+        endpoints
+    }
+
+    /// Replaces every flag and segment, sending a `put` event to open streams.
+    pub fn put(&self, data: AllData<Flag, Segment>) {
+        self.state.update(|current| {
+            *current = data;
+            sse_event(
+                "put",
+                &serde_json::json!({ "path": "/", "data": &*current }),
+            )
+        });
+    }
+
+    /// Adds or replaces a flag, sending a `patch` event to open streams.
+    pub fn patch_flag(&self, flag: Flag) {
+        self.state.update(|current| {
+            let event = patch_event("flags", &flag.key, &flag);
+            current.flags.insert(flag.key.clone(), flag);
+            event
+        });
+    }
+
+    /// Adds or replaces a segment, sending a `patch` event to open streams.
+    pub fn patch_segment(&self, segment: Segment) {
+        self.state.update(|current| {
+            let event = patch_event("segments", &segment.key, &segment);
+            current.segments.insert(segment.key.clone(), segment);
+            event
+        });
+    }
+
+    /// Removes a flag, sending a `delete` event with the given version to open streams.
+    pub fn delete_flag(&self, key: &str, version: u64) {
+        self.state.update(|current| {
+            current.flags.remove(key);
+            delete_event("flags", key, version)
+        });
+    }
+
+    /// Removes a segment, sending a `delete` event with the given version to open streams.
+    pub fn delete_segment(&self, key: &str, version: u64) {
+        self.state.update(|current| {
+            current.segments.remove(key);
+            delete_event("segments", key, version)
+        });
+    }
+
+    /// Every event posted to the service so far, in the order received.
+    pub fn events(&self) -> Vec<CapturedEvent> {
+        self.state.events.lock().clone()
+    }
+
+    /// Removes and returns every event posted to the service so far, in the order received.
+    pub fn take_events(&self) -> Vec<CapturedEvent> {
+        std::mem::take(&mut *self.state.events.lock())
+    }
+
+    /// Waits until at least `count` events have been posted to the service, or the timeout
+    /// elapses, and then returns every event posted so far.
+    pub async fn wait_for_events(&self, count: usize, timeout: Duration) -> Vec<CapturedEvent> {
+        let _ = tokio::time::timeout(timeout, async {
+            loop {
+                let received = self.state.events_received.notified();
+                if self.state.events.lock().len() >= count {
+                    return;
+                }
+                received.await;
+            }
+        })
+        .await;
+        self.events()
+    }
+}
+
+impl Drop for FakeLaunchDarkly {
+    fn drop(&mut self) {
+        if let Some(shutdown) = self.shutdown.take() {
+            let _ = shutdown.send(());
+        }
+    }
+}
+
+struct State {
+    data: RwLock<AllData<Flag, Segment>>,
+    updates: RwLock<Option<broadcast::Sender<Arc<str>>>>,
+    events: Mutex<Vec<CapturedEvent>>,
+    events_received: Notify,
+}
+
+impl State {
+    // Updates are broadcast while the data is locked, so that streams opened concurrently see
+    // each update either in their initial put or as an event, in order.
+    fn update(&self, change: impl FnOnce(&mut AllData<Flag, Segment>) -> String) {
+        let mut data = self.data.write();
+        let event = change(&mut data);
+        if let Some(updates) = &*self.updates.read() {
+            // Sending only fails when there are no open streams.
+            let _ = updates.send(Arc::from(event));
+        }
+    }
+
+    async fn handle(&self, request: Request<Body>) -> Response<Body> {
+        match (request.method(), request.uri().path()) {
+            (&Method::GET, "/sdk/latest-all") => {
+                let body =
+                    serde_json::to_string(&*self.data.read()).expect("flag data is serializable");
+                Response::builder()
+                    .header(CONTENT_TYPE, "application/json")
+                    .body(Body::from(body))
+                    .expect("response headers are valid")
+            }
+            (&Method::GET, "/all") => self.stream(),
+            (&Method::POST, "/bulk") => self.capture_events(request).await,
+            _ => status(StatusCode::NOT_FOUND),
+        }
+    }
+
+    fn stream(&self) -> Response<Body> {
+        let (put, mut updates) = {
+            let data = self.data.read();
+            let updates = match &*self.updates.read() {
+                Some(updates) => updates.subscribe(),
+                None => return status(StatusCode::SERVICE_UNAVAILABLE),
+            };
+            let put = sse_event("put", &serde_json::json!({ "path": "/", "data": &*data }));
+            (put, updates)
+        };
+
+        let (mut sender, body) = Body::channel();
+        tokio::spawn(async move {
+            if sender.send_data(Bytes::from(put)).await.is_err() {
+                return;
+            }
+            // Streams which fall behind or are shut down are closed, and SDKs reconnect.
+            while let Ok(event) = updates.recv().await {
+                if sender
+                    .send_data(Bytes::copy_from_slice(event.as_bytes()))
+                    .await
+                    .is_err()
+                {
+                    return;
+                }
+            }
+        });
+
+        Response::builder()
+            .header(CONTENT_TYPE, "text/event-stream")
+            .header(CACHE_CONTROL, "no-cache")
+            .body(body)
+            .expect("response headers are valid")
+    }
+
+    async fn capture_events(&self, request: Request<Body>) -> Response<Body> {
+        #[cfg(feature = "event-compression")]
+        let gzipped = request
+            .headers()
+            .get(hyper::header::CONTENT_ENCODING)
+            .is_some_and(|encoding| encoding == "gzip");
+
+        let body = match hyper::body::to_bytes(request.into_body()).await {
+            Ok(body) => body,
+            Err(_) => return status(StatusCode::BAD_REQUEST),
+        };
+
+        #[cfg(feature = "event-compression")]
+        let body = if gzipped {
+            use std::io::Read;
+            let mut decompressed = Vec::new();
+            if flate2::read::GzDecoder::new(body.as_ref())
+                .read_to_end(&mut decompressed)
+                .is_err()
+            {
+                return status(StatusCode::BAD_REQUEST);
+            }
+            Bytes::from(decompressed)
+        } else {
+            body
+        };
+
+        let events: Vec<CapturedEvent> = match serde_json::from_slice(&body) {
+            Ok(events) => events,
+            Err(e) => {
+                warn!("fake LaunchDarkly service received invalid events: {}", e);
+                return status(StatusCode::BAD_REQUEST);
+            }
+        };
+
+        self.events.lock().extend(events);
+        self.events_received.notify_waiters();
+
+        status(StatusCode::ACCEPTED)
+    }
+}
+
+fn patch_event(kind: &str, key: &str, data: &impl serde::Serialize) -> String {
+    sse_event(
+        "patch",
+        &serde_json::json!({ "path": format!("/{kind}/{key}"), "data": data }),
+    )
+}
+
+fn delete_event(kind: &str, key: &str, version: u64) -> String {
+    sse_event(
+        "delete",
+        &serde_json::json!({ "path": format!("/{kind}/{key}"), "version": version }),
+    )
+}
+
+fn sse_event(event: &str, data: &Value) -> String {
+    format!("event: {event}\ndata: {data}\n\n")
+}
+
+fn status(status: StatusCode) -> Response<Body> {
+    Response::builder()
+        .status(status)
+        .body(Body::empty())
+        .expect("response headers are valid")
+}
+
+#[cfg(test)]
+mod tests {
+    use hyper::client::HttpConnector;
+    use launchdarkly_server_sdk_evaluation::ContextBuilder;
+
+    use super::*;
+    use crate::test_common::{basic_flag, basic_off_flag};
+    use crate::{
+        Client, ConfigBuilder, EventProcessorBuilder, PollingDataSourceBuilder,
+        StreamingDataSourceBuilder,
+    };
+
+    fn client(fake: &FakeLaunchDarkly, streaming: bool) -> Client {
+        let mut config = ConfigBuilder::new("sdk-key").service_endpoints(&fake.service_endpoints());
+        if streaming {
+            let mut data_source = StreamingDataSourceBuilder::<HttpConnector>::new();
+            data_source.https_connector(HttpConnector::new());
+            config = config.data_source(&data_source);
+        } else {
+            let mut data_source = PollingDataSourceBuilder::<HttpConnector>::new();
+            data_source.https_connector(HttpConnector::new());
+            config = config.data_source(&data_source);
+        }
+        let mut event_processor = EventProcessorBuilder::<HttpConnector>::new();
+        event_processor.https_connector(HttpConnector::new());
+        let config = config
+            .event_processor(&event_processor)
+            .build()
+            .expect("config should build");
+
+        let client = Client::build(config).expect("client should build");
+        client.start_with_default_executor();
+        client
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn streamed_updates_reach_the_client() {
+        let fake = FakeLaunchDarkly::start().expect("fake should start");
+        fake.patch_flag(basic_flag("myFlag"));
+        fake.patch_flag(basic_flag("doomedFlag"));
+
+        let client = client(&fake, true);
+        assert_eq!(
+            Some(true),
+            client.wait_for_initialization(Duration::from_secs(5)).await
+        );
+        let context = ContextBuilder::new("bob")
+            .build()
+            .expect("Failed to create context");
+        assert!(client.bool_variation(&context, "myFlag", false));
+        assert!(client.bool_variation(&context, "doomedFlag", false));
+
+        let mut off = basic_off_flag("myFlag");
+        off.version += 1;
+        fake.patch_flag(off);
+        fake.delete_flag("doomedFlag", 100);
+
+        let mut updated = false;
+        for _ in 0..200 {
+            if !client.bool_variation(&context, "myFlag", false)
+                && !client.bool_variation(&context, "doomedFlag", false)
+            {
+                updated = true;
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert!(updated, "client should receive the updates");
+
+        client.close();
+    }
+
+    #[test]
+    fn events_are_deserialized_by_kind() {
+        let events: Vec<CapturedEvent> = serde_json::from_value(serde_json::json!([
+            {
+                "kind": "feature",
+                "creationDate": 1000,
+                "key": "myFlag",
+                "contextKeys": {"user": "bob"},
+                "value": true,
+                "variation": 1,
+                "default": false,
+                "version": 42
+            },
+            {"kind": "migration_op", "creationDate": 1000, "operation": "read"}
+        ]))
+        .expect("events should deserialize");
+
+        match &events[0] {
+            CapturedEvent::Feature(feature) => {
+                assert_eq!("myFlag", feature.key);
+                assert_eq!(Value::Bool(true), feature.value);
+                assert_eq!(Some(1), feature.variation);
+                assert_eq!(None, feature.reason);
+            }
+            event => panic!("unexpected event {event:?}"),
+        }
+        assert_eq!("migration_op", events[1].kind());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn polled_data_and_posted_events_are_served() {
+        let fake = FakeLaunchDarkly::start().expect("fake should start");
+        let mut flags = std::collections::HashMap::new();
+        flags.insert("myFlag".to_string(), basic_flag("myFlag"));
+        fake.put(AllData {
+            flags,
+            segments: Default::default(),
+        });
+
+        let client = client(&fake, false);
+        assert_eq!(
+            Some(true),
+            client.wait_for_initialization(Duration::from_secs(5)).await
+        );
+        let context = ContextBuilder::new("bob")
+            .build()
+            .expect("Failed to create context");
+        assert!(client.bool_variation(&context, "myFlag", false));
+        client.track_event(context, "purchase");
+        client.flush();
+
+        let events = fake.wait_for_events(3, Duration::from_secs(5)).await;
+        let kinds: Vec<&str> = events.iter().map(CapturedEvent::kind).collect();
+        assert!(kinds.contains(&"index"), "{kinds:?}");
+        assert!(kinds.contains(&"custom"), "{kinds:?}");
+        assert!(kinds.contains(&"summary"), "{kinds:?}");
+        let custom = events
+            .iter()
+            .find_map(|event| match event {
+                CapturedEvent::Custom(custom) => Some(custom),
+                _ => None,
+            })
+            .expect("custom event should be captured");
+        assert_eq!("purchase", custom.key);
+        assert_eq!(
+            Some("bob"),
+            custom.context_keys.get("user").map(String::as_str)
+        );
+        let summary = events
+            .iter()
+            .find_map(|event| match event {
+                CapturedEvent::Summary(summary) => Some(summary),
+                _ => None,
+            })
+            .expect("summary event should be captured");
+        assert!(summary.features.contains_key("myFlag"));
+
+        assert_eq!(events.len(), fake.take_events().len());
+        assert!(fake.events().is_empty());
+
+        client.close();
+    }
+}
@@ -0,0 +1,269 @@
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+use hyper::client::connect::Connection;
+use hyper::service::Service;
+use hyper::Uri;
+use launchdarkly_server_sdk_evaluation::{
+    self as eval, evaluate, Context, Detail, Flag, FlagValue, Segment,
+};
+use serde::Serialize;
+use thiserror::Error;
+use tokio::io::{AsyncRead, AsyncWrite};
+
+use crate::evaluation::DirectPrerequisiteRecorder;
+use crate::feature_requester_builders::{FeatureRequesterFactory, HyperFeatureRequesterBuilder};
+use crate::stores::store::{DataStore, InMemoryDataStore};
+use crate::{AllData, FlagDetail, FlagDetailConfig};
+
+/// Error type used to represent failures when loading flag data into a [FlagInspector].
+#[non_exhaustive]
+#[derive(Debug, Error)]
+pub enum FlagInspectorError {
+    /// Error used when the snapshot file could not be read.
+    #[error("failed to read snapshot: {0}")]
+    Io(#[from] std::io::Error),
+    /// Error used when the snapshot is not a valid set of flags and segments.
+    #[error("failed to parse snapshot: {0}")]
+    InvalidSnapshot(#[from] serde_json::Error),
+    /// Error used when the polling request could not be configured.
+    #[error("invalid polling configuration: {0}")]
+    InvalidConfig(String),
+    /// Error used when LaunchDarkly did not return flag data. Details are logged at error level.
+    #[error("failed to fetch flag data")]
+    FetchFailed,
+}
+
+/// The result of evaluating a single flag with a [FlagInspector].
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FlagEvaluation {
+    /// The key of the evaluated flag.
+    pub flag_key: String,
+    /// The value, variation index and reason produced by the evaluation.
+    #[serde(flatten)]
+    pub detail: Detail<FlagValue>,
+    /// The keys of the flag's direct prerequisites which were evaluated, in evaluation order.
+    pub prerequisites: Vec<String>,
+}
+
+impl fmt::Display for FlagEvaluation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}", self.flag_key)?;
+        match &self.detail.value {
+            Some(value) => writeln!(f, "  value: {}", to_json(value))?,
+            None => writeln!(f, "  value: (none)")?,
+        }
+        match self.detail.variation_index {
+            Some(index) => writeln!(f, "  variation: {index}")?,
+            None => writeln!(f, "  variation: (none)")?,
+        }
+        writeln!(f, "  reason: {}", to_json(&self.detail.reason))?;
+        if self.prerequisites.is_empty() {
+            write!(f, "  prerequisites: (none)")
+        } else {
+            write!(f, "  prerequisites: {}", self.prerequisites.join(", "))
+        }
+    }
+}
+
+fn to_json(value: &impl Serialize) -> String {
+    serde_json::to_string(value).unwrap_or_else(|e| format!("<{e}>"))
+}
+
+/// Evaluates flags against a fixed snapshot of an environment's flag data, without starting a
+/// [crate::Client] or sending analytics events.
+///
+/// This is intended for diagnosing what a given context would receive for a flag, and backs the
+/// `ld-flag-eval` command-line tool.
+///
+/// # Examples
+///
+/// ```
+/// # use launchdarkly_server_sdk::{ContextBuilder, FlagInspector};
+/// # fn main() -> Result<(), Box<dyn std::error::Error>> {
+///     let inspector = FlagInspector::from_snapshot(r#"{"flags": {}, "segments": {}}"#)?;
+///     let context = ContextBuilder::new("user-key").build()?;
+///     let evaluation = inspector.evaluate(&context, "new-checkout");
+///     println!("{evaluation}");
+/// #   Ok(())
+/// # }
+/// ```
+pub struct FlagInspector {
+    store: InMemoryDataStore,
+}
+
+impl FlagInspector {
+    /// Create an inspector from a snapshot in the format returned by LaunchDarkly's polling
+    /// endpoint: an object holding `flags` and `segments`, each indexed by key.
+    pub fn from_snapshot(snapshot: &str) -> Result<Self, FlagInspectorError> {
+        let data: AllData<Flag, Segment> = serde_json::from_str(snapshot)?;
+        Ok(Self::from_data(data))
+    }
+
+    /// Create an inspector from a snapshot file. See [FlagInspector::from_snapshot] for the
+    /// expected format.
+    pub fn from_snapshot_file(path: impl AsRef<Path>) -> Result<Self, FlagInspectorError> {
+        Self::from_snapshot(&fs::read_to_string(path)?)
+    }
+
+    /// Create an inspector from flag data fetched once from LaunchDarkly's polling endpoint.
+    pub async fn fetch<C>(
+        polling_base_url: &str,
+        sdk_key: &str,
+        connector: C,
+    ) -> Result<Self, FlagInspectorError>
+    where
+        C: Service<Uri> + Clone + Send + Sync + 'static,
+        C::Response: Connection + AsyncRead + AsyncWrite + Send + Unpin,
+        C::Future: Send + Unpin + 'static,
+        C::Error: Into<Box<dyn std::error::Error + Send + Sync>>,
+    {
+        let mut requester = HyperFeatureRequesterBuilder::new(polling_base_url, sdk_key, connector)
+            .build(None)
+            .map_err(|e| FlagInspectorError::InvalidConfig(e.to_string()))?;
+        let data = requester
+            .get_all()
+            .await
+            .map_err(|_| FlagInspectorError::FetchFailed)?;
+        Ok(Self::from_data(data))
+    }
+
+    fn from_data(data: AllData<Flag, Segment>) -> Self {
+        let mut store = InMemoryDataStore::new();
+        store.init(data);
+        Self { store }
+    }
+
+    /// The keys of every flag in the snapshot, in sorted order.
+    pub fn flag_keys(&self) -> Vec<String> {
+        let mut keys: Vec<String> = self.store.all_flags().into_keys().collect();
+        keys.sort();
+        keys
+    }
+
+    /// Evaluates the flag for the context, returning an empty detail with a
+    /// [eval::Error::FlagNotFound] reason if the snapshot has no such flag.
+    pub fn evaluate(&self, context: &Context, flag_key: &str) -> FlagEvaluation {
+        let store = self.store.to_store();
+        let recorder = DirectPrerequisiteRecorder::new(flag_key);
+        let detail = match store.flag(flag_key) {
+            Some(flag) => evaluate(store, &flag, context, Some(&recorder)).map(|v| v.clone()),
+            None => Detail::err(eval::Error::FlagNotFound),
+        };
+
+        FlagEvaluation {
+            flag_key: flag_key.to_string(),
+            detail,
+            prerequisites: recorder.prerequisites.take(),
+        }
+    }
+
+    /// Evaluates every flag for the context, as [crate::Client::all_flags_detail] does.
+    pub fn all_flags_detail(&self, context: &Context, config: FlagDetailConfig) -> FlagDetail {
+        let mut flag_detail = FlagDetail::new(true);
+        flag_detail.populate(&self.store, context, config);
+        flag_detail
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use hyper::client::HttpConnector;
+    use launchdarkly_server_sdk_evaluation::{ContextBuilder, Reason};
+
+    use super::*;
+    use crate::test_common::{basic_flag, basic_flag_with_prereq};
+
+    fn snapshot() -> String {
+        json!({
+            "flags": {
+                "prereq": basic_flag("prereq"),
+                "toplevel": basic_flag_with_prereq("toplevel", "prereq"),
+            },
+            "segments": {},
+        })
+        .to_string()
+    }
+
+    #[test]
+    fn evaluations_include_prerequisites() {
+        let inspector = FlagInspector::from_snapshot(&snapshot()).expect("snapshot should load");
+        let context = ContextBuilder::new("bob")
+            .build()
+            .expect("Failed to create context");
+
+        assert_eq!(vec!["prereq", "toplevel"], inspector.flag_keys());
+
+        let evaluation = inspector.evaluate(&context, "toplevel");
+        assert_eq!(Some(FlagValue::Bool(true)), evaluation.detail.value);
+        assert_eq!(Some(1), evaluation.detail.variation_index);
+        assert_eq!(vec!["prereq"], evaluation.prerequisites);
+        assert_eq!(
+            "toplevel\n  value: true\n  variation: 1\n  reason: {\"kind\":\"FALLTHROUGH\"}\n  prerequisites: prereq",
+            evaluation.to_string()
+        );
+
+        let evaluation = inspector.evaluate(&context, "missing");
+        assert_eq!(None, evaluation.detail.value);
+        assert!(matches!(
+            evaluation.detail.reason,
+            Reason::Error {
+                error: eval::Error::FlagNotFound
+            }
+        ));
+        assert_eq!(
+            json!({"flagKey": "missing", "reason": {"kind": "ERROR", "errorKind": "FLAG_NOT_FOUND"}, "prerequisites": []}),
+            serde_json::to_value(&evaluation).expect("evaluation should serialize")
+        );
+    }
+
+    #[test]
+    fn invalid_snapshots_are_rejected() {
+        assert!(matches!(
+            FlagInspector::from_snapshot("{\"flags\": []}"),
+            Err(FlagInspectorError::InvalidSnapshot(_))
+        ));
+        assert!(matches!(
+            FlagInspector::from_snapshot_file("/does/not/exist.json"),
+            Err(FlagInspectorError::Io(_))
+        ));
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn flag_data_can_be_fetched() {
+        let mut server = mockito::Server::new_async().await;
+        let mock = server
+            .mock("GET", "/sdk/latest-all")
+            .match_header("authorization", "sdk-key")
+            .with_status(200)
+            .with_body(snapshot())
+            .create_async()
+            .await;
+
+        let inspector = FlagInspector::fetch(&server.url(), "sdk-key", HttpConnector::new())
+            .await
+            .expect("flag data should be fetched");
+        mock.assert_async().await;
+        assert_eq!(vec!["prereq", "toplevel"], inspector.flag_keys());
+
+        let context = ContextBuilder::new("bob")
+            .build()
+            .expect("Failed to create context");
+        let all_flags = inspector.all_flags_detail(&context, FlagDetailConfig::new());
+        let all_flags = serde_json::to_value(all_flags).expect("detail should serialize");
+        assert_eq!(json!(true), all_flags["toplevel"]);
+        assert_eq!(json!(true), all_flags["$valid"]);
+
+        server
+            .mock("GET", "/sdk/latest-all")
+            .with_status(401)
+            .create_async()
+            .await;
+        assert!(matches!(
+            FlagInspector::fetch(&server.url(), "other-key", HttpConnector::new()).await,
+            Err(FlagInspectorError::FetchFailed)
+        ));
+    }
+}
@@ -0,0 +1,501 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Weak};
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+use launchdarkly_server_sdk_evaluation::{Context, FlagValue};
+use parking_lot::RwLock;
+use serde::Deserialize;
+use thiserror::Error;
+
+const DEFAULT_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Error type used to represent failures when loading flag overrides.
+#[non_exhaustive]
+#[derive(Debug, Error)]
+pub enum FlagOverridesError {
+    /// Error used when the overrides file could not be read.
+    #[error("failed to read overrides from {path:?}: {source}")]
+    Io {
+        /// The path of the overrides file.
+        path: PathBuf,
+        /// The underlying error.
+        source: std::io::Error,
+    },
+    /// Error used when the overrides file or environment variable does not hold valid overrides.
+    #[error("invalid overrides in {origin}: {source}")]
+    Invalid {
+        /// Where the overrides were read from.
+        origin: String,
+        /// The underlying error.
+        source: serde_json::Error,
+    },
+    /// Error used when the overrides file could not be watched for changes.
+    #[error("failed to watch overrides file: {0}")]
+    WatchFailed(std::io::Error),
+}
+
+// The overrides read from a single source.
+//
+// Environment variables and files hold the same JSON representation:
+//
+// {"flags": {"flag-key": true}, "contexts": {"context-key": {"flag-key": false}}}
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default)]
+struct OverrideSet {
+    flags: HashMap<String, FlagValue>,
+    contexts: HashMap<String, HashMap<String, FlagValue>>,
+    // Incremented each time the overrides are reloaded.
+    #[serde(skip)]
+    version: u64,
+}
+
+impl OverrideSet {
+    fn parse(origin: &str, json: &str) -> Result<Self, FlagOverridesError> {
+        serde_json::from_str(json).map_err(|source| FlagOverridesError::Invalid {
+            origin: origin.to_string(),
+            source,
+        })
+    }
+
+    fn read(path: &Path) -> Result<Self, FlagOverridesError> {
+        let json = fs::read_to_string(path).map_err(|source| FlagOverridesError::Io {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        Self::parse(&path.display().to_string(), &json)
+    }
+
+    // Applies the overrides in `other` on top of these.
+    fn merge(&mut self, other: &OverrideSet) {
+        self.flags
+            .extend(other.flags.iter().map(|(k, v)| (k.clone(), v.clone())));
+        for (context_key, flags) in &other.contexts {
+            self.contexts
+                .entry(context_key.clone())
+                .or_default()
+                .extend(flags.iter().map(|(k, v)| (k.clone(), v.clone())));
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.flags.len() + self.contexts.values().map(HashMap::len).sum::<usize>()
+    }
+}
+
+/// Used to force the values of flags locally, without changing them in LaunchDarkly.
+///
+/// Overrides may be given in code, read once from an environment variable, or read from a file
+/// which is watched for changes. Later sources take precedence over earlier ones, in that order,
+/// and an override for a specific context key always takes precedence over an override for every
+/// context.
+///
+/// The environment variable and file both hold a JSON object of the form:
+///
+/// ```json
+/// {
+///     "flags": { "flag-key": true },
+///     "contexts": { "context-key": { "flag-key": false } }
+/// }
+/// ```
+///
+/// Overrides are intended for development and testing, and the client logs a warning when it is
+/// built with any configured.
+///
+/// # Examples
+///
+/// ```
+/// # use launchdarkly_server_sdk::{ConfigBuilder, FlagOverridesBuilder};
+/// # fn main() {
+///     let mut overrides = FlagOverridesBuilder::new();
+///     overrides
+///         .flag("new-checkout", true)
+///         .flag_for_context("banner-text", "user-key", "Hello".to_string())
+///         .environment_variable("LD_FLAG_OVERRIDES");
+///
+///     let config = ConfigBuilder::new("sdk-key").flag_overrides(&overrides);
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct FlagOverridesBuilder {
+    overrides: OverrideSet,
+    environment_variable: Option<String>,
+    file: Option<PathBuf>,
+    poll_interval: Duration,
+}
+
+impl FlagOverridesBuilder {
+    /// Create a new instance of the [FlagOverridesBuilder] with no overrides.
+    pub fn new() -> Self {
+        Self {
+            overrides: OverrideSet::default(),
+            environment_variable: None,
+            file: None,
+            poll_interval: DEFAULT_POLL_INTERVAL,
+        }
+    }
+
+    /// Forces the flag to the given value for every context.
+    pub fn flag(&mut self, flag_key: impl Into<String>, value: impl Into<FlagValue>) -> &mut Self {
+        self.overrides.flags.insert(flag_key.into(), value.into());
+        self
+    }
+
+    /// Forces the flag to the given value for contexts with the given key. For multi-contexts,
+    /// the key of any individual context may match.
+    pub fn flag_for_context(
+        &mut self,
+        flag_key: impl Into<String>,
+        context_key: impl Into<String>,
+        value: impl Into<FlagValue>,
+    ) -> &mut Self {
+        self.overrides
+            .contexts
+            .entry(context_key.into())
+            .or_default()
+            .insert(flag_key.into(), value.into());
+        self
+    }
+
+    /// Reads overrides from the named environment variable when the overrides are built. The
+    /// variable is ignored if it is not set.
+    pub fn environment_variable(&mut self, name: impl Into<String>) -> &mut Self {
+        self.environment_variable = Some(name.into());
+        self
+    }
+
+    /// Reads overrides from the given file, which is reloaded whenever it changes.
+    ///
+    /// If the file cannot be reloaded, the overrides it last held remain in effect.
+    pub fn file(&mut self, path: impl Into<PathBuf>) -> &mut Self {
+        self.file = Some(path.into());
+        self
+    }
+
+    /// Sets how often the overrides file is checked for changes.
+    ///
+    /// The default is one second.
+    pub fn poll_interval(&mut self, poll_interval: Duration) -> &mut Self {
+        self.poll_interval = poll_interval;
+        self
+    }
+
+    /// Loads the configured overrides, and starts watching the overrides file if one was given.
+    pub fn build(&self) -> Result<FlagOverrides, FlagOverridesError> {
+        let mut fixed = self.overrides.clone();
+        let mut sources = vec![];
+        if self.overrides.len() > 0 {
+            sources.push("code".to_string());
+        }
+        if let Some(name) = &self.environment_variable {
+            if let Ok(json) = std::env::var(name) {
+                fixed.merge(&OverrideSet::parse(name, &json)?);
+                sources.push(format!("environment variable {name}"));
+            }
+        }
+
+        let mut current = fixed.clone();
+        if let Some(path) = &self.file {
+            current.merge(&OverrideSet::read(path)?);
+            sources.push(format!("file {}", path.display()));
+        }
+
+        let overrides = FlagOverrides {
+            inner: Arc::new(RwLock::new(current)),
+            sources,
+        };
+
+        if let Some(path) = &self.file {
+            let watcher = FileWatcher {
+                path: path.clone(),
+                modified: modified(path),
+                fixed,
+                overrides: Arc::downgrade(&overrides.inner),
+            };
+            let poll_interval = self.poll_interval;
+            thread::Builder::new()
+                .name("ld-flag-overrides".into())
+                .spawn(move || watcher.run(poll_interval))
+                .map_err(FlagOverridesError::WatchFailed)?;
+        }
+
+        Ok(overrides)
+    }
+}
+
+impl Default for FlagOverridesBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Describes where the value returned by [crate::Client::variation_detail_with_source] came from.
+///
+/// The evaluation reasons have no kind of their own for overridden values, so they are reported
+/// with a [launchdarkly_server_sdk_evaluation::Reason::Fallthrough] reason which is not in an
+/// experiment, and no variation index, which a genuine fallthrough always has.
+#[non_exhaustive]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EvaluationSource {
+    /// The flag was evaluated by the client, or the default value was returned.
+    Evaluated,
+    /// The value was forced by the overrides configured with [crate::ConfigBuilder::flag_overrides].
+    Overridden,
+}
+
+/// Holds the flag values which take precedence over those in the data store. These are created
+/// by a [FlagOverridesBuilder].
+#[derive(Clone, Debug)]
+pub struct FlagOverrides {
+    inner: Arc<RwLock<OverrideSet>>,
+    sources: Vec<String>,
+}
+
+impl FlagOverrides {
+    /// Returns the value the flag is forced to for the given context, if any.
+    pub fn get(&self, context: &Context, flag_key: &str) -> Option<FlagValue> {
+        let overrides = self.inner.read();
+
+        // Sort the individual contexts of a multi-context so that the result is deterministic.
+        let mut keys = context.context_keys().into_iter().collect::<Vec<_>>();
+        keys.sort_by_key(|(kind, _)| *kind);
+
+        keys.iter()
+            .find_map(|(_, key)| overrides.contexts.get(*key)?.get(flag_key))
+            .or_else(|| overrides.flags.get(flag_key))
+            .cloned()
+    }
+
+    // Returns every flag value forced for the given context, read under a single lock so that the
+    // result is consistent even while the overrides file is being reloaded.
+    pub(crate) fn all(&self, context: &Context) -> HashMap<String, FlagValue> {
+        let overrides = self.inner.read();
+
+        let mut keys = context.context_keys().into_iter().collect::<Vec<_>>();
+        keys.sort_by_key(|(kind, _)| *kind);
+
+        let mut values = overrides.flags.clone();
+        // Apply the contexts in reverse so that the first in sorted order wins, as it does in get.
+        for (_, key) in keys.iter().rev() {
+            if let Some(flags) = overrides.contexts.get(*key) {
+                values.extend(flags.iter().map(|(k, v)| (k.clone(), v.clone())));
+            }
+        }
+        values
+    }
+
+    /// The number of flag overrides currently in effect, including those for specific contexts.
+    pub fn len(&self) -> usize {
+        self.inner.read().len()
+    }
+
+    /// Returns true if no flag overrides are in effect.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Describes where the overrides were read from, for logging.
+    pub fn sources(&self) -> &[String] {
+        &self.sources
+    }
+
+    // Changes whenever the overrides are reloaded.
+    #[cfg(feature = "bootstrap")]
+    pub(crate) fn version(&self) -> u64 {
+        self.inner.read().version
+    }
+}
+
+struct FileWatcher {
+    path: PathBuf,
+    modified: Option<SystemTime>,
+    // The overrides from code and the environment, which the file's overrides are applied to.
+    fixed: OverrideSet,
+    overrides: Weak<RwLock<OverrideSet>>,
+}
+
+impl FileWatcher {
+    fn run(mut self, poll_interval: Duration) {
+        loop {
+            thread::sleep(poll_interval);
+            // The watcher stops once every handle to the overrides has been dropped.
+            let overrides = match self.overrides.upgrade() {
+                Some(overrides) => overrides,
+                None => return,
+            };
+
+            let modified = modified(&self.path);
+            if modified == self.modified {
+                continue;
+            }
+            self.modified = modified;
+
+            match OverrideSet::read(&self.path) {
+                Ok(file) => {
+                    let mut current = self.fixed.clone();
+                    current.merge(&file);
+                    warn!(
+                        "Reloaded {} flag overrides from {}",
+                        current.len(),
+                        self.path.display()
+                    );
+                    let mut overrides = overrides.write();
+                    current.version = overrides.version + 1;
+                    *overrides = current;
+                }
+                Err(e) => warn!("Keeping previous flag overrides: {e}"),
+            }
+        }
+    }
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use launchdarkly_server_sdk_evaluation::{ContextBuilder, MultiContextBuilder};
+    use maplit::hashmap;
+
+    use super::*;
+
+    fn context(key: &str) -> Context {
+        ContextBuilder::new(key)
+            .build()
+            .expect("context should build")
+    }
+
+    fn temp_file(contents: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("ld-overrides-{}.json", uuid::Uuid::new_v4()));
+        fs::write(&path, contents).expect("file should be written");
+        path
+    }
+
+    #[test]
+    fn context_overrides_take_precedence() {
+        let overrides = FlagOverridesBuilder::new()
+            .flag("flag", true)
+            .flag_for_context("flag", "bob", false)
+            .build()
+            .expect("overrides should build");
+
+        assert_eq!(
+            Some(FlagValue::Bool(true)),
+            overrides.get(&context("alice"), "flag")
+        );
+        assert_eq!(
+            Some(FlagValue::Bool(false)),
+            overrides.get(&context("bob"), "flag")
+        );
+        assert_eq!(None, overrides.get(&context("bob"), "other-flag"));
+        assert_eq!(
+            hashmap! { "flag".to_string() => FlagValue::Bool(false) },
+            overrides.all(&context("bob"))
+        );
+        assert_eq!(2, overrides.len());
+        assert_eq!(vec!["code".to_string()], overrides.sources());
+    }
+
+    #[test]
+    fn any_key_of_a_multi_context_may_match() {
+        let overrides = FlagOverridesBuilder::new()
+            .flag_for_context("flag", "acme", "org".to_string())
+            .build()
+            .expect("overrides should build");
+
+        let mut builder = MultiContextBuilder::new();
+        builder.add_context(context("alice"));
+        builder.add_context(
+            ContextBuilder::new("acme")
+                .kind("org")
+                .build()
+                .expect("context should build"),
+        );
+        let multi = builder.build().expect("context should build");
+
+        assert_eq!(
+            Some(FlagValue::Str("org".to_string())),
+            overrides.get(&multi, "flag")
+        );
+        assert_eq!(
+            hashmap! { "flag".to_string() => FlagValue::Str("org".to_string()) },
+            overrides.all(&multi)
+        );
+    }
+
+    #[test]
+    fn environment_overrides_code() {
+        let name = format!("LD_OVERRIDES_{}", uuid::Uuid::new_v4().simple());
+        std::env::set_var(
+            &name,
+            r#"{"flags": {"flag": 2}, "contexts": {"bob": {"other": "x"}}}"#,
+        );
+
+        let overrides = FlagOverridesBuilder::new()
+            .flag("flag", 1_i64)
+            .environment_variable(&name)
+            .build()
+            .expect("overrides should build");
+        std::env::remove_var(&name);
+
+        assert_eq!(
+            Some(FlagValue::Number(2.0)),
+            overrides.get(&context("alice"), "flag")
+        );
+        assert_eq!(
+            Some(FlagValue::Str("x".to_string())),
+            overrides.get(&context("bob"), "other")
+        );
+    }
+
+    #[test]
+    fn invalid_sources_fail_to_build() {
+        let path = temp_file("not json");
+        let result = FlagOverridesBuilder::new().file(&path).build();
+        fs::remove_file(&path).expect("file should be removed");
+        assert!(matches!(result, Err(FlagOverridesError::Invalid { .. })));
+
+        let result = FlagOverridesBuilder::new()
+            .file(std::env::temp_dir().join("ld-overrides-missing.json"))
+            .build();
+        assert!(matches!(result, Err(FlagOverridesError::Io { .. })));
+    }
+
+    #[test]
+    fn file_changes_are_reloaded() {
+        let path = temp_file(r#"{"flags": {"flag": "first"}}"#);
+        let overrides = FlagOverridesBuilder::new()
+            .flag("code-flag", true)
+            .file(&path)
+            .poll_interval(Duration::from_millis(10))
+            .build()
+            .expect("overrides should build");
+        assert_eq!(
+            Some(FlagValue::Str("first".to_string())),
+            overrides.get(&context("alice"), "flag")
+        );
+
+        // Ensure the modification time changes on file systems with coarse timestamps.
+        thread::sleep(Duration::from_millis(1100));
+        fs::write(&path, r#"{"flags": {"flag": "second"}}"#).expect("file should be written");
+
+        let mut value = None;
+        for _ in 0..100 {
+            value = overrides.get(&context("alice"), "flag");
+            if value == Some(FlagValue::Str("second".to_string())) {
+                break;
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+        fs::remove_file(&path).expect("file should be removed");
+
+        assert_eq!(Some(FlagValue::Str("second".to_string())), value);
+        assert_eq!(
+            Some(FlagValue::Bool(true)),
+            overrides.get(&context("alice"), "code-flag")
+        );
+    }
+}
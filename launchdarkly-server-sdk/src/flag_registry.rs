@@ -0,0 +1,314 @@
+use std::fmt::{self, Display, Formatter};
+
+use launchdarkly_server_sdk_evaluation::{Context, Store};
+use serde_json::Value;
+use thiserror::Error;
+
+use crate::Client;
+
+/// FlagKind describes the type of the values a flag's variations hold.
+#[non_exhaustive]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum FlagKind {
+    /// Variations are booleans.
+    Bool,
+    /// Variations are numbers.
+    Number,
+    /// Variations are strings.
+    String,
+    /// Variations are arbitrary JSON values, such as arrays or objects.
+    Json,
+}
+
+impl FlagKind {
+    pub(crate) fn of(value: &Value) -> Self {
+        match value {
+            Value::Bool(_) => FlagKind::Bool,
+            Value::Number(_) => FlagKind::Number,
+            Value::String(_) => FlagKind::String,
+            _ => FlagKind::Json,
+        }
+    }
+}
+
+impl Display for FlagKind {
+    fn fmt(&self, f: &mut Formatter) -> fmt::Result {
+        match self {
+            FlagKind::Bool => write!(f, "bool"),
+            FlagKind::Number => write!(f, "number"),
+            FlagKind::String => write!(f, "string"),
+            FlagKind::Json => write!(f, "json"),
+        }
+    }
+}
+
+mod private {
+    pub trait Sealed {}
+
+    impl Sealed for bool {}
+    impl Sealed for i64 {}
+    impl Sealed for f64 {}
+    impl Sealed for &'static str {}
+}
+
+/// FlagType is implemented for the types of default value a [TypedFlag] may be declared with.
+///
+/// Flags declared with a `&'static str` default evaluate to a `String`.
+pub trait FlagType: Copy + private::Sealed {
+    /// The type the flag evaluates to.
+    type Value;
+
+    /// The kind of variation a flag of this type is expected to have.
+    const KIND: FlagKind;
+
+    #[doc(hidden)]
+    fn variation(self, client: &Client, context: &Context, flag_key: &str) -> Self::Value;
+}
+
+impl FlagType for bool {
+    type Value = bool;
+    const KIND: FlagKind = FlagKind::Bool;
+
+    fn variation(self, client: &Client, context: &Context, flag_key: &str) -> bool {
+        client.bool_variation(context, flag_key, self)
+    }
+}
+
+impl FlagType for i64 {
+    type Value = i64;
+    const KIND: FlagKind = FlagKind::Number;
+
+    fn variation(self, client: &Client, context: &Context, flag_key: &str) -> i64 {
+        client.int_variation(context, flag_key, self)
+    }
+}
+
+impl FlagType for f64 {
+    type Value = f64;
+    const KIND: FlagKind = FlagKind::Number;
+
+    fn variation(self, client: &Client, context: &Context, flag_key: &str) -> f64 {
+        client.float_variation(context, flag_key, self)
+    }
+}
+
+impl FlagType for &'static str {
+    type Value = String;
+    const KIND: FlagKind = FlagKind::String;
+
+    fn variation(self, client: &Client, context: &Context, flag_key: &str) -> String {
+        client.str_variation(context, flag_key, self.to_string())
+    }
+}
+
+/// TypedFlag declares a flag's key along with the type and value of its default, so that the
+/// flag can be declared once as a constant and evaluated without repeating either.
+///
+/// ```
+/// # use launchdarkly_server_sdk::{FlagRegistry, TypedFlag};
+/// pub const NEW_CHECKOUT: TypedFlag<bool> = TypedFlag::new("new-checkout", false);
+/// pub const BANNER_TEXT: TypedFlag<&str> = TypedFlag::new("banner-text", "Welcome");
+///
+/// let mut registry = FlagRegistry::new();
+/// registry.register(&NEW_CHECKOUT).register(&BANNER_TEXT);
+/// ```
+#[derive(Debug, Copy, Clone)]
+pub struct TypedFlag<T: FlagType> {
+    key: &'static str,
+    default: T,
+}
+
+impl<T: FlagType> TypedFlag<T> {
+    /// Declare a flag with the given key and default value.
+    pub const fn new(key: &'static str, default: T) -> Self {
+        TypedFlag { key, default }
+    }
+
+    /// The key of the flag.
+    pub fn key(&self) -> &'static str {
+        self.key
+    }
+
+    /// The value the flag evaluates to if it cannot be evaluated.
+    pub fn default_value(&self) -> T {
+        self.default
+    }
+
+    /// Evaluates the flag for the given context, returning the flag's default if the flag cannot
+    /// be evaluated or is of the wrong type.
+    pub fn variation(&self, client: &Client, context: &Context) -> T::Value {
+        self.default.variation(client, context, self.key)
+    }
+}
+
+/// Describes a registered flag which does not match the flags known to the client.
+#[non_exhaustive]
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum FlagValidationError {
+    /// Error used when the client has not finished initializing, so there are no flags to
+    /// validate against.
+    #[error("the client has not been initialized")]
+    ClientNotReady,
+    /// Error used when a registered flag does not exist.
+    #[error("flag {0:?} was not found")]
+    FlagNotFound(String),
+    /// Error used when a registered flag has a variation of a different type than was declared.
+    #[error("flag {key:?} was declared as {expected}, but has a {actual} variation")]
+    WrongType {
+        /// The key of the flag.
+        key: String,
+        /// The kind of variation the flag was declared with.
+        expected: FlagKind,
+        /// The kind of the first variation which does not match.
+        actual: FlagKind,
+    },
+}
+
+/// FlagRegistry collects [TypedFlag] declarations so that they can be checked against the flags
+/// known to the client with [Client::validate_flags].
+#[derive(Clone, Debug, Default)]
+pub struct FlagRegistry {
+    flags: Vec<(&'static str, FlagKind)>,
+}
+
+impl FlagRegistry {
+    /// Create an empty registry.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds the flag to the registry.
+    pub fn register<T: FlagType>(&mut self, flag: &TypedFlag<T>) -> &mut Self {
+        self.flags.push((flag.key, T::KIND));
+        self
+    }
+
+    /// The keys of every registered flag, in the order they were registered.
+    pub fn keys(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self.flags.iter().map(|(key, _)| *key)
+    }
+
+    pub(crate) fn validate(&self, store: &dyn Store) -> Result<(), Vec<FlagValidationError>> {
+        let errors = self
+            .flags
+            .iter()
+            .filter_map(|(key, expected)| validate_flag(store, key, *expected))
+            .collect::<Vec<_>>();
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+fn validate_flag(store: &dyn Store, key: &str, expected: FlagKind) -> Option<FlagValidationError> {
+    let flag = match store.flag(key) {
+        Some(flag) => flag,
+        None => return Some(FlagValidationError::FlagNotFound(key.to_string())),
+    };
+
+    // Variations are only visible through the flag's serialized form.
+    let variations = match serde_json::to_value(&flag) {
+        Ok(Value::Object(mut flag)) => match flag.remove("variations") {
+            Some(Value::Array(variations)) => variations,
+            _ => Vec::new(),
+        },
+        _ => Vec::new(),
+    };
+
+    variations
+        .iter()
+        .map(FlagKind::of)
+        .find(|actual| *actual != expected)
+        .map(|actual| FlagValidationError::WrongType {
+            key: key.to_string(),
+            expected,
+            actual,
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use launchdarkly_server_sdk_evaluation::{Flag, Segment};
+
+    use super::*;
+    use crate::test_common::{basic_flag, basic_int_flag};
+
+    struct TestStore {
+        flags: HashMap<String, Flag>,
+    }
+
+    impl Store for TestStore {
+        fn flag(&self, flag_key: &str) -> Option<Flag> {
+            self.flags.get(flag_key).cloned()
+        }
+
+        fn segment(&self, _segment_key: &str) -> Option<Segment> {
+            None
+        }
+    }
+
+    const ENABLED: TypedFlag<bool> = TypedFlag::new("enabled", false);
+    const LIMIT: TypedFlag<i64> = TypedFlag::new("limit", 10);
+    const RATIO: TypedFlag<f64> = TypedFlag::new("ratio", 0.5);
+    const BANNER: TypedFlag<&str> = TypedFlag::new("banner", "hello");
+
+    fn store() -> TestStore {
+        TestStore {
+            flags: HashMap::from([
+                ("enabled".to_string(), basic_flag("enabled")),
+                ("limit".to_string(), basic_int_flag("limit")),
+                ("ratio".to_string(), basic_int_flag("ratio")),
+            ]),
+        }
+    }
+
+    #[test]
+    fn registered_flags_which_match_are_valid() {
+        let mut registry = FlagRegistry::new();
+        registry
+            .register(&ENABLED)
+            .register(&LIMIT)
+            .register(&RATIO);
+
+        assert_eq!(Ok(()), registry.validate(&store()));
+        assert_eq!(
+            vec!["enabled", "limit", "ratio"],
+            registry.keys().collect::<Vec<_>>()
+        );
+    }
+
+    #[test]
+    fn missing_and_mistyped_flags_are_reported() {
+        const LIMIT_AS_BOOL: TypedFlag<bool> = TypedFlag::new("limit", true);
+
+        let mut registry = FlagRegistry::new();
+        registry
+            .register(&ENABLED)
+            .register(&BANNER)
+            .register(&LIMIT_AS_BOOL);
+
+        assert_eq!(
+            Err(vec![
+                FlagValidationError::FlagNotFound("banner".to_string()),
+                FlagValidationError::WrongType {
+                    key: "limit".to_string(),
+                    expected: FlagKind::Bool,
+                    actual: FlagKind::Number,
+                },
+            ]),
+            registry.validate(&store())
+        );
+    }
+
+    #[test]
+    fn flags_declare_their_defaults() {
+        assert_eq!("banner", BANNER.key());
+        assert_eq!("hello", BANNER.default_value());
+        assert_eq!(10, LIMIT.default_value());
+    }
+}
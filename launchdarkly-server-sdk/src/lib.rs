@@ -16,6 +16,12 @@ extern crate log;
 #[macro_use]
 extern crate serde_json;
 
+#[cfg(feature = "bootstrap")]
+pub use bootstrap::{BootstrapService, BootstrapServiceBuilder};
+#[cfg(feature = "codegen")]
+pub use codegen::{generate_flag_module, generate_flag_module_file, CodegenError};
+#[cfg(feature = "derive")]
+pub use launchdarkly_server_sdk_derive::IntoContext;
 pub use launchdarkly_server_sdk_evaluation::Error as EvalError;
 pub use launchdarkly_server_sdk_evaluation::{
     AttributeValue, Context, ContextBuilder, Detail, FlagValue, Kind, MultiContextBuilder, Reason,
@@ -27,9 +33,11 @@ pub use client::Client;
 
 // Re-export
 pub use client::{BuildError, StartError};
+pub use client_manager::ClientManager;
 pub use config::{ApplicationInfo, BuildError as ConfigBuildError, Config, ConfigBuilder};
 pub use data_source_builders::{
-    BuildError as DataSourceBuildError, PollingDataSourceBuilder, StreamingDataSourceBuilder,
+    BuildError as DataSourceBuildError, PollingDataSourceBuilder, RecordingDataSourceBuilder,
+    ReplayDataSourceBuilder, StreamingDataSourceBuilder,
 };
 pub use evaluation::{FlagDetail, FlagDetailConfig};
 pub use events::event::MigrationOpEvent;
@@ -37,30 +45,70 @@ pub use events::processor::EventProcessor;
 pub use events::processor_builders::{
     BuildError as EventProcessorBuildError, EventProcessorBuilder, NullEventProcessorBuilder,
 };
+#[cfg(feature = "fake-service")]
+pub use fake_launchdarkly::{
+    CapturedContextEvent, CapturedCustomEvent, CapturedEvent, CapturedFeatureEvent,
+    CapturedSummaryEvent, FakeLaunchDarkly, FakeLaunchDarklyError,
+};
 pub use feature_requester_builders::{
     BuildError as FeatureRequestBuilderError, FeatureRequesterFactory,
 };
+#[cfg(feature = "inspect")]
+pub use flag_inspector::{FlagEvaluation, FlagInspector, FlagInspectorError};
+pub use flag_overrides::{
+    EvaluationSource, FlagOverrides, FlagOverridesBuilder, FlagOverridesError,
+};
+pub use flag_registry::{FlagKind, FlagRegistry, FlagType, FlagValidationError, TypedFlag};
 pub use launchdarkly_server_sdk_evaluation::{Flag, Segment, Versioned};
 pub use migrations::{
-    ExecutionOrder, MigrationOpTracker, Migrator, MigratorBuilder, Operation, Origin, Stage,
+    BlockingMigrationFn, BlockingMigrator, BlockingMigratorBuilder, DynMigrator,
+    DynMigratorWithTypes, ExecutionOrder, FailedWrite, MigrationFn, MigrationMismatch,
+    MigrationOpTracker, Migrator, MigratorBuildError, MigratorBuilder, Operation, Origin,
+    ReadFallback, Stage, WriteRetryPolicy,
 };
+#[cfg(feature = "relay")]
+pub use relay::{RelayServer, RelayServerBuilder, RelayServerError};
 pub use service_endpoints::ServiceEndpointsBuilder;
-pub use stores::persistent_store::{PersistentDataStore, PersistentStoreError};
+pub use stores::persistent_store::{
+    AsyncPersistentDataStore, PersistentDataStore, PersistentDataStoreAdapter, PersistentStoreError,
+};
 pub use stores::persistent_store_builders::{
-    PersistentDataStoreBuilder, PersistentDataStoreFactory,
+    AsyncPersistentDataStoreFactory, PersistentDataStoreBuilder, PersistentDataStoreFactory,
+    PersistentDataStoreStats,
+};
+#[cfg(feature = "store-testing")]
+pub use stores::persistent_store_conformance::{
+    PersistentDataStoreTestHarness, PersistentDataStoreTestSuite,
 };
+pub use stores::persistent_store_encryption::{EncryptionError, PersistentStoreEncryption};
+#[cfg(feature = "sqlite")]
+pub use stores::sqlite_store::{SqlitePersistentDataStore, SqlitePersistentDataStoreBuilder};
+pub use stores::store_builders::InMemoryDataStoreBuilder;
 pub use stores::store_types::{AllData, DataKind, SerializedItem, StorageItem};
 pub use version::version_string;
 
+#[cfg(feature = "bootstrap")]
+mod bootstrap;
 mod client;
+mod client_manager;
+#[cfg(feature = "codegen")]
+mod codegen;
 mod config;
 mod data_source;
 mod data_source_builders;
 mod evaluation;
 mod events;
+#[cfg(feature = "fake-service")]
+mod fake_launchdarkly;
 mod feature_requester;
 mod feature_requester_builders;
+#[cfg(feature = "inspect")]
+mod flag_inspector;
+mod flag_overrides;
+mod flag_registry;
 mod migrations;
+#[cfg(feature = "relay")]
+mod relay;
 mod reqwest;
 mod sampler;
 mod service_endpoints;
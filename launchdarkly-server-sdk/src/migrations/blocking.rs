@@ -0,0 +1,562 @@
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Instant;
+
+use launchdarkly_server_sdk_evaluation::Context;
+use rand::rng;
+
+use super::migrator::{
+    check_consistency, fall_back, ConsistencyCheck, ExecutionSettings, MigrationComparisonFn,
+    MigrationMismatchFn, MigrationOriginResult, MigrationResult, MigrationWriteResult,
+};
+use crate::sampler::{Sampler, ThreadRngSampler};
+use crate::{
+    Client, ExecutionOrder, MigrationMismatch, MigrationOpTracker, MigratorBuildError, Operation,
+    Origin, ReadFallback, Stage,
+};
+
+/// A synchronous migration operation, used as every origin function of a [BlockingMigrator].
+pub type BlockingMigrationFn<P, T> = Box<dyn Fn(&P) -> MigrationResult<T> + Send + Sync>;
+
+struct BlockingConfig<P, T> {
+    old: BlockingMigrationFn<P, T>,
+    new: BlockingMigrationFn<P, T>,
+    compare: Option<MigrationComparisonFn<T>>,
+}
+
+/// The blocking migration builder is used to configure and construct an instance of a
+/// [BlockingMigrator]. It mirrors [crate::MigratorBuilder], but accepts origin functions which
+/// return their results directly instead of through a future.
+pub struct BlockingMigratorBuilder<P, T, WP, WT> {
+    client: Arc<Client>,
+    read_execution_order: ExecutionOrder,
+    settings: ExecutionSettings,
+
+    read_config: Option<BlockingConfig<P, T>>,
+    write_config: Option<BlockingConfig<WP, WT>>,
+    on_mismatch: Option<MigrationMismatchFn<P, T>>,
+}
+
+impl<P, T, WP, WT> BlockingMigratorBuilder<P, T, WP, WT>
+where
+    P: Send + Sync,
+    T: Send + Sync,
+    WP: Send + Sync,
+    WT: Send + Sync,
+{
+    /// Create a new blocking migrator builder instance with the provided client.
+    pub fn new(client: Arc<Client>) -> Self {
+        BlockingMigratorBuilder {
+            client,
+            read_execution_order: ExecutionOrder::Concurrent,
+            settings: ExecutionSettings::default(),
+            read_config: None,
+            write_config: None,
+            on_mismatch: None,
+        }
+    }
+
+    /// The read execution order influences the concurrency and execution order for read operations
+    /// involving multiple origins. With [ExecutionOrder::Concurrent], the non-authoritative read
+    /// runs on a scoped thread while the authoritative read runs on the calling thread.
+    pub fn read_execution_order(mut self, order: ExecutionOrder) -> Self {
+        self.read_execution_order = order;
+        self
+    }
+
+    /// Enable or disable latency tracking for migration operations. This latency information can
+    /// be sent upstream to LaunchDarkly to enhance migration visibility.
+    pub fn track_latency(mut self, measure: bool) -> Self {
+        self.settings.measure_latency = measure;
+        self
+    }
+
+    /// Enable or disable error tracking for migration operations. This error information can be
+    /// sent upstream to LaunchDarkly to enhance migration visibility.
+    pub fn track_errors(mut self, measure: bool) -> Self {
+        self.settings.measure_errors = measure;
+        self
+    }
+
+    /// Read fallback determines what is returned when a read executed against both origins fails
+    /// against the authoritative origin. See [crate::MigratorBuilder::read_fallback].
+    pub fn read_fallback(mut self, fallback: ReadFallback) -> Self {
+        self.settings.read_fallback = fallback;
+        self
+    }
+
+    /// Read can be used to configure the migration-read behavior of the resulting
+    /// [BlockingMigrator] instance.
+    ///
+    /// Users are required to provide two different read methods -- one to read from the old
+    /// migration origin, and one to read from the new origin. Additionally, users can opt-in to
+    /// consistency tracking by providing a comparison function.
+    pub fn read(
+        mut self,
+        old: impl Fn(&P) -> MigrationResult<T> + Send + Sync + 'static,
+        new: impl Fn(&P) -> MigrationResult<T> + Send + Sync + 'static,
+        compare: Option<MigrationComparisonFn<T>>,
+    ) -> Self {
+        self.read_config = Some(BlockingConfig {
+            old: Box::new(old),
+            new: Box::new(new),
+            compare,
+        });
+        self
+    }
+
+    /// On mismatch registers a callback which is invoked whenever a read executed against both
+    /// origins returns results which the comparison function considers inconsistent. See
+    /// [crate::MigratorBuilder::on_mismatch].
+    pub fn on_mismatch(
+        mut self,
+        on_mismatch: impl Fn(&MigrationMismatch<P, T>) + Send + Sync + 'static,
+    ) -> Self {
+        self.on_mismatch = Some(Arc::new(on_mismatch));
+        self
+    }
+
+    /// Write with types configures the migration-write behavior of the resulting
+    /// [BlockingMigrator] instance, for writes which take a different payload type, or produce a
+    /// different result type, than reads. See [crate::MigratorBuilder::write_with_types].
+    pub fn write_with_types(
+        mut self,
+        old: impl Fn(&WP) -> MigrationResult<WT> + Send + Sync + 'static,
+        new: impl Fn(&WP) -> MigrationResult<WT> + Send + Sync + 'static,
+    ) -> Self {
+        self.write_config = Some(BlockingConfig {
+            old: Box::new(old),
+            new: Box::new(new),
+            compare: None,
+        });
+        self
+    }
+
+    /// Build constructs a [BlockingMigrator] instance to support migration-based reads and
+    /// writes. A [MigratorBuildError] describing the missing configuration will be returned if
+    /// the build fails.
+    pub fn build(self) -> Result<BlockingMigrator<P, T, WP, WT>, MigratorBuildError> {
+        let read_config = self
+            .read_config
+            .ok_or(MigratorBuildError::MissingReadConfig)?;
+        let write_config = self
+            .write_config
+            .ok_or(MigratorBuildError::MissingWriteConfig)?;
+
+        Ok(BlockingMigrator {
+            client: self.client,
+            read_execution_order: self.read_execution_order,
+            settings: self.settings,
+            read_config: Arc::new(read_config),
+            write_config: Arc::new(write_config),
+            on_mismatch: self.on_mismatch,
+        })
+    }
+}
+
+impl<P, T> BlockingMigratorBuilder<P, T, P, T>
+where
+    P: Send + Sync,
+    T: Send + Sync,
+{
+    /// Write can be used to configure the migration-write behavior of the resulting
+    /// [BlockingMigrator] instance.
+    ///
+    /// Users are required to provide two different write methods -- one to write to the old
+    /// migration origin, and one to write to the new origin. Writes take the same payload type,
+    /// and produce the same result type, as reads.
+    pub fn write(
+        self,
+        old: impl Fn(&P) -> MigrationResult<T> + Send + Sync + 'static,
+        new: impl Fn(&P) -> MigrationResult<T> + Send + Sync + 'static,
+    ) -> Self {
+        self.write_with_types(old, new)
+    }
+}
+
+/// The blocking migrator executes migration operations synchronously, on the calling thread, so
+/// that migrations can be performed from code which does not run on an async runtime. It is
+/// configured through the [BlockingMigratorBuilder].
+///
+/// Evaluations and the resulting migration op events are handled exactly as they are for the
+/// [crate::Migrator]. Timeouts, background execution and write retries are not supported.
+///
+/// A blocking migrator may be shared between threads. Cloning it is cheap, and all clones share
+/// the same configuration.
+pub struct BlockingMigrator<P, T, WP, WT> {
+    client: Arc<Client>,
+    read_execution_order: ExecutionOrder,
+    settings: ExecutionSettings,
+    read_config: Arc<BlockingConfig<P, T>>,
+    write_config: Arc<BlockingConfig<WP, WT>>,
+    on_mismatch: Option<MigrationMismatchFn<P, T>>,
+}
+
+impl<P, T, WP, WT> Clone for BlockingMigrator<P, T, WP, WT> {
+    fn clone(&self) -> Self {
+        BlockingMigrator {
+            client: self.client.clone(),
+            read_execution_order: self.read_execution_order,
+            settings: self.settings,
+            read_config: self.read_config.clone(),
+            write_config: self.write_config.clone(),
+            on_mismatch: self.on_mismatch.clone(),
+        }
+    }
+}
+
+impl<P, T, WP, WT> BlockingMigrator<P, T, WP, WT>
+where
+    P: Send + Sync,
+    T: Send + Sync,
+    WP: Send + Sync,
+    WT: Send + Sync,
+{
+    /// Uses the provided flag key and context to execute a migration-backed read operation.
+    pub fn read(
+        &self,
+        context: &Context,
+        flag_key: String,
+        default_stage: Stage,
+        payload: P,
+    ) -> MigrationOriginResult<T> {
+        let (stage, tracker) = self
+            .client
+            .migration_variation(context, &flag_key, default_stage);
+
+        if let Ok(mut tracker) = tracker.lock() {
+            tracker.operation(Operation::Read);
+        } else {
+            error!("Failed to acquire tracker lock. Cannot track migration read.");
+        }
+
+        let old = self.executor(Origin::Old, &self.read_config.old, &tracker, &payload);
+        let new = self.executor(Origin::New, &self.read_config.new, &tracker, &payload);
+        let check = self.read_config.compare.map(|compare| ConsistencyCheck {
+            compare,
+            on_mismatch: self.on_mismatch.as_ref(),
+            flag_key: &flag_key,
+            stage,
+        });
+
+        let result = match stage {
+            Stage::Off | Stage::DualWrite => old.run(),
+            Stage::Shadow => read_both(old, new, check, self.read_execution_order, &tracker),
+            Stage::Live => read_both(new, old, check, self.read_execution_order, &tracker),
+            Stage::Rampdown | Stage::Complete => new.run(),
+        };
+
+        self.client.track_migration_op(tracker);
+
+        result
+    }
+
+    /// Uses the provided flag key and context to execute a migration-backed write operation.
+    pub fn write(
+        &self,
+        context: &Context,
+        flag_key: String,
+        default_stage: Stage,
+        payload: WP,
+    ) -> MigrationWriteResult<WT> {
+        let (stage, tracker) = self
+            .client
+            .migration_variation(context, &flag_key, default_stage);
+
+        if let Ok(mut tracker) = tracker.lock() {
+            tracker.operation(Operation::Write);
+        } else {
+            error!("Failed to acquire tracker lock. Cannot track migration write.");
+        }
+
+        let old = self.executor(Origin::Old, &self.write_config.old, &tracker, &payload);
+        let new = self.executor(Origin::New, &self.write_config.new, &tracker, &payload);
+
+        let result = match stage {
+            Stage::Off => MigrationWriteResult {
+                authoritative: old.run(),
+                nonauthoritative: None,
+            },
+            Stage::DualWrite | Stage::Shadow => write_both(old, new),
+            Stage::Live | Stage::Rampdown => write_both(new, old),
+            Stage::Complete => MigrationWriteResult {
+                authoritative: new.run(),
+                nonauthoritative: None,
+            },
+        };
+
+        self.client.track_migration_op(tracker);
+
+        result
+    }
+
+    fn executor<'a, X, R>(
+        &self,
+        origin: Origin,
+        function: &'a BlockingMigrationFn<X, R>,
+        tracker: &'a Arc<Mutex<MigrationOpTracker>>,
+        payload: &'a X,
+    ) -> BlockingExecutor<'a, X, R> {
+        BlockingExecutor {
+            origin,
+            function,
+            tracker,
+            settings: self.settings,
+            payload,
+        }
+    }
+}
+
+fn read_both<P, T>(
+    authoritative: BlockingExecutor<'_, P, T>,
+    nonauthoritative: BlockingExecutor<'_, P, T>,
+    check: Option<ConsistencyCheck<'_, P, T>>,
+    execution_order: ExecutionOrder,
+    tracker: &Arc<Mutex<MigrationOpTracker>>,
+) -> MigrationOriginResult<T>
+where
+    P: Send + Sync,
+    T: Send + Sync,
+{
+    let (authoritative_result, nonauthoritative_result) = match execution_order {
+        ExecutionOrder::Concurrent => thread::scope(|scope| {
+            let handle = scope.spawn(|| nonauthoritative.run());
+            let authoritative_result = authoritative.run();
+            let nonauthoritative_result = handle.join().unwrap_or_else(|_| MigrationOriginResult {
+                origin: nonauthoritative.origin,
+                result: Err("Failed to execute non-authoritative read".into()),
+                fallback: false,
+            });
+            (authoritative_result, nonauthoritative_result)
+        }),
+        ExecutionOrder::Random if ThreadRngSampler::new(rng()).sample(2) => {
+            let nonauthoritative_result = nonauthoritative.run();
+            (authoritative.run(), nonauthoritative_result)
+        }
+        _ => (authoritative.run(), nonauthoritative.run()),
+    };
+
+    if let Some(check) = check {
+        check_consistency(
+            check,
+            authoritative.payload,
+            &authoritative_result,
+            &nonauthoritative_result,
+            tracker,
+        );
+    }
+
+    match authoritative.settings.read_fallback {
+        ReadFallback::NonAuthoritative => {
+            fall_back(authoritative_result, nonauthoritative_result, tracker)
+        }
+        ReadFallback::Disabled => authoritative_result,
+    }
+}
+
+fn write_both<P, T>(
+    authoritative: BlockingExecutor<'_, P, T>,
+    nonauthoritative: BlockingExecutor<'_, P, T>,
+) -> MigrationWriteResult<T> {
+    let authoritative_result = authoritative.run();
+
+    if authoritative_result.result.is_err() {
+        return MigrationWriteResult {
+            authoritative: authoritative_result,
+            nonauthoritative: None,
+        };
+    }
+
+    MigrationWriteResult {
+        authoritative: authoritative_result,
+        nonauthoritative: Some(nonauthoritative.run()),
+    }
+}
+
+struct BlockingExecutor<'a, P, T> {
+    origin: Origin,
+    function: &'a BlockingMigrationFn<P, T>,
+    tracker: &'a Arc<Mutex<MigrationOpTracker>>,
+    settings: ExecutionSettings,
+    payload: &'a P,
+}
+
+impl<P, T> BlockingExecutor<'_, P, T> {
+    fn run(&self) -> MigrationOriginResult<T> {
+        let start = Instant::now();
+        let result = (self.function)(self.payload);
+        let elapsed = start.elapsed();
+
+        let result = match self.tracker.lock() {
+            Ok(mut tracker) => {
+                if self.settings.measure_latency {
+                    tracker.latency(self.origin, elapsed);
+                }
+
+                if self.settings.measure_errors && result.is_err() {
+                    tracker.error(self.origin);
+                }
+
+                tracker.invoked(self.origin);
+
+                result
+            }
+            Err(_) => Err("Failed to acquire lock".into()),
+        };
+
+        MigrationOriginResult {
+            origin: self.origin,
+            result,
+            fallback: false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{mpsc, Arc};
+    use std::thread;
+    use std::time::Duration;
+
+    use launchdarkly_server_sdk_evaluation::ContextBuilder;
+    use test_case::test_case;
+
+    use super::BlockingMigratorBuilder;
+    use crate::{Client, ConfigBuilder, ExecutionOrder, Origin, ReadFallback, Stage};
+
+    fn offline_client() -> Arc<Client> {
+        let config = ConfigBuilder::new("sdk-key")
+            .offline(true)
+            .build()
+            .expect("config failed to build");
+
+        Arc::new(Client::build(config).expect("client failed to build"))
+    }
+
+    #[test_case(Stage::Off, vec![Origin::Old])]
+    #[test_case(Stage::DualWrite, vec![Origin::Old])]
+    #[test_case(Stage::Shadow, vec![Origin::Old, Origin::New])]
+    #[test_case(Stage::Live, vec![Origin::New, Origin::Old])]
+    #[test_case(Stage::Rampdown, vec![Origin::New])]
+    #[test_case(Stage::Complete, vec![Origin::New])]
+    fn read_handles_correct_origin(stage: Stage, expected: Vec<Origin>) {
+        let (sender, receiver) = mpsc::channel();
+        let old_sender = sender.clone();
+        let migrator = BlockingMigratorBuilder::new(offline_client())
+            .read_execution_order(ExecutionOrder::Serial)
+            .read(
+                move |&payload: &u32| {
+                    old_sender.send(Origin::Old).unwrap();
+                    Ok(payload)
+                },
+                move |&payload: &u32| {
+                    sender.send(Origin::New).unwrap();
+                    Ok(payload + 1)
+                },
+                Some(|a, b| a == b),
+            )
+            .write(|&payload: &u32| Ok(payload), |&payload: &u32| Ok(payload))
+            .build()
+            .expect("migrator failed to build");
+
+        let context = ContextBuilder::new("user-key")
+            .build()
+            .expect("context failed to build");
+        let result = migrator.read(&context, "migration-key".into(), stage, 1);
+
+        assert_eq!(expected[0], result.origin);
+        assert_eq!(expected, receiver.try_iter().collect::<Vec<_>>());
+    }
+
+    #[test_case(Stage::Off, vec![Origin::Old])]
+    #[test_case(Stage::DualWrite, vec![Origin::Old, Origin::New])]
+    #[test_case(Stage::Shadow, vec![Origin::Old, Origin::New])]
+    #[test_case(Stage::Live, vec![Origin::New, Origin::Old])]
+    #[test_case(Stage::Rampdown, vec![Origin::New, Origin::Old])]
+    #[test_case(Stage::Complete, vec![Origin::New])]
+    fn write_handles_correct_origin(stage: Stage, expected: Vec<Origin>) {
+        let (sender, receiver) = mpsc::channel();
+        let old_sender = sender.clone();
+        let migrator = BlockingMigratorBuilder::new(offline_client())
+            .read(|_: &String| Ok(0), |_: &String| Ok(0), None)
+            .write(
+                move |record: &String| {
+                    old_sender.send(Origin::Old).unwrap();
+                    Ok(record.len())
+                },
+                move |record: &String| {
+                    sender.send(Origin::New).unwrap();
+                    Ok(record.len())
+                },
+            )
+            .build()
+            .expect("migrator failed to build");
+
+        let context = ContextBuilder::new("user-key")
+            .build()
+            .expect("context failed to build");
+        let result = migrator.write(&context, "migration-key".into(), stage, "record".into());
+
+        assert_eq!(expected[0], result.authoritative.origin);
+        assert_eq!(Ok(6), result.authoritative.result);
+        assert_eq!(expected.len() == 2, result.nonauthoritative.is_some());
+        assert_eq!(expected, receiver.try_iter().collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn concurrent_reads_run_on_separate_threads() {
+        let (sender, receiver) = mpsc::channel();
+        let old_sender = sender.clone();
+        let migrator = BlockingMigratorBuilder::new(offline_client())
+            .read_execution_order(ExecutionOrder::Concurrent)
+            .read(
+                move |_: &()| {
+                    old_sender.send(thread::current().id()).unwrap();
+                    thread::sleep(Duration::from_millis(50));
+                    Ok("old")
+                },
+                move |_: &()| {
+                    sender.send(thread::current().id()).unwrap();
+                    thread::sleep(Duration::from_millis(50));
+                    Ok("new")
+                },
+                None,
+            )
+            .write(|_: &()| Ok("old"), |_: &()| Ok("new"))
+            .build()
+            .expect("migrator failed to build");
+
+        let context = ContextBuilder::new("user-key")
+            .build()
+            .expect("context failed to build");
+        let result = migrator.read(&context, "migration-key".into(), Stage::Shadow, ());
+
+        assert_eq!(Ok("old"), result.result);
+        let threads = receiver.try_iter().collect::<Vec<_>>();
+        assert_eq!(2, threads.len());
+        assert_ne!(threads[0], threads[1]);
+    }
+
+    #[test]
+    fn read_falls_back_when_authoritative_origin_fails() {
+        let migrator = BlockingMigratorBuilder::new(offline_client())
+            .read_fallback(ReadFallback::NonAuthoritative)
+            .read(
+                |_: &()| Ok("old".to_string()),
+                |_: &()| Err("new".to_string()),
+                None,
+            )
+            .write_with_types(|_: &()| Ok(()), |_: &()| Ok(()))
+            .build()
+            .expect("migrator failed to build");
+
+        let context = ContextBuilder::new("user-key")
+            .build()
+            .expect("context failed to build");
+        let result = migrator.read(&context, "migration-key".into(), Stage::Live, ());
+
+        assert_eq!(Origin::Old, result.origin);
+        assert_eq!(Ok("old".to_string()), result.result);
+        assert!(result.fallback);
+    }
+}
@@ -1,6 +1,7 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
 use std::sync::Mutex;
-use std::time::Instant;
+use std::time::{Duration, Instant};
 
 use futures::future::join_all;
 use futures::future::BoxFuture;
@@ -8,10 +9,10 @@ use futures::future::FutureExt;
 use launchdarkly_server_sdk_evaluation::Context;
 use rand::rng;
 use serde::Serialize;
+use thiserror::Error;
 
-use crate::sampler::Sampler;
-use crate::sampler::ThreadRngSampler;
-use crate::{Client, ExecutionOrder, MigrationOpTracker, Operation, Origin, Stage};
+use crate::sampler::{Sampler, ThreadRngSampler};
+use crate::{Client, ExecutionOrder, MigrationOpTracker, Operation, Origin, ReadFallback, Stage};
 
 #[derive(Serialize)]
 #[serde(rename_all = "camelCase")]
@@ -20,13 +21,16 @@ use crate::{Client, ExecutionOrder, MigrationOpTracker, Operation, Origin, Stage
 pub struct MigrationOriginResult<T> {
     pub origin: Origin,
     pub result: MigrationResult<T>,
+    /// True when this result was read from the non-authoritative origin because the
+    /// authoritative origin failed. See [MigratorBuilder::read_fallback].
+    pub fallback: bool,
 }
 
 /// MigrationResult represents the result of a migration operation. If the operation was
 /// successful, the result will contain a pair of values representing the result of the operation
 /// and the origin it was executed against. If the operation failed, the result will contain an
 /// error.
-type MigrationResult<T> = Result<T, String>;
+pub(super) type MigrationResult<T> = Result<T, String>;
 
 /// A write result contains the operation results against both the authoritative and
 /// non-authoritative origins.
@@ -41,7 +45,124 @@ pub struct MigrationWriteResult<T> {
 
 // MigrationComparisonFn is used to compare the results of two migration operations. If the
 // provided results are equal, this method will return true and false otherwise.
-type MigrationComparisonFn<T> = fn(&T, &T) -> bool;
+pub(super) type MigrationComparisonFn<T> = fn(&T, &T) -> bool;
+
+/// Describes a migration-backed read for which the old and new origins returned inconsistent
+/// results, as determined by the comparison function given to [MigratorBuilder::read].
+pub struct MigrationMismatch<'a, P, T> {
+    /// The key of the migration flag which controlled the read.
+    pub flag_key: &'a str,
+    /// The migration stage the read was executed in.
+    pub stage: Stage,
+    /// The payload provided to both origins.
+    pub payload: &'a P,
+    /// The result read from the old origin.
+    pub old: &'a T,
+    /// The result read from the new origin.
+    pub new: &'a T,
+}
+
+pub(super) type MigrationMismatchFn<P, T> = Arc<dyn Fn(&MigrationMismatch<P, T>) + Send + Sync>;
+
+/// Describes a write to the non-authoritative origin which failed, and which will not be retried
+/// any further.
+pub struct FailedWrite<'a, P> {
+    /// The key of the migration flag which controlled the write.
+    pub flag_key: &'a str,
+    /// The migration stage the write was executed in.
+    pub stage: Stage,
+    /// The non-authoritative origin which could not be written to.
+    pub origin: Origin,
+    /// The payload which could not be written.
+    pub payload: &'a P,
+    /// The error returned by the final attempt.
+    pub error: &'a str,
+    /// The number of times the write was attempted, including the original attempt.
+    pub attempts: u32,
+}
+
+type WriteFailureFn<P> = Box<dyn Fn(&FailedWrite<P>) + Send + Sync>;
+
+/// Configures how a [Migrator] retries writes to the non-authoritative origin which fail.
+///
+/// Failed writes wait in an in-process queue, and are retried with exponential backoff. Retries
+/// are not reported to LaunchDarkly: the migration op event describes only the original write,
+/// including its error and latency. Writes which still fail after the last retry are reported to
+/// [MigratorBuilder::on_write_failure].
+#[derive(Clone, Copy, Debug)]
+pub struct WriteRetryPolicy {
+    max_retries: u32,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+    capacity: usize,
+}
+
+impl Default for WriteRetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 3,
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(5),
+            capacity: 1000,
+        }
+    }
+}
+
+impl WriteRetryPolicy {
+    /// Create a new policy with the default settings: up to 3 retries, backing off from 100ms to
+    /// at most 5s, with at most 1000 writes waiting to be retried.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The number of times a failed write is retried before it is given up on.
+    pub fn max_retries(mut self, max_retries: u32) -> Self {
+        self.max_retries = max_retries;
+        self
+    }
+
+    /// The delay before the first retry. The delay doubles with each subsequent retry.
+    pub fn initial_backoff(mut self, backoff: Duration) -> Self {
+        self.initial_backoff = backoff;
+        self
+    }
+
+    /// The longest delay between retries.
+    pub fn max_backoff(mut self, backoff: Duration) -> Self {
+        self.max_backoff = backoff;
+        self
+    }
+
+    /// The number of failed writes which may be waiting to be retried at once. Writes which fail
+    /// while the queue is full are given up on immediately.
+    pub fn capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity;
+        self
+    }
+}
+
+/// A boxed migration operation, used as every origin function of a [DynMigrator].
+pub type MigrationFn<P, T> =
+    Box<dyn for<'a> Fn(&'a P) -> BoxFuture<'a, MigrationResult<T>> + Sync + Send>;
+
+/// A [Migrator] whose origin functions have been boxed, so that its type depends only on the
+/// payload and result types. This makes it convenient to store in application state.
+///
+/// Created with [MigratorBuilder::build_dyn].
+pub type DynMigrator<P, T> = DynMigratorWithTypes<P, T, P, T>;
+
+/// A [DynMigrator] whose writes take a payload of type `WP` and produce a result of type `WT`,
+/// as configured with [MigratorBuilder::write_with_types].
+pub type DynMigratorWithTypes<P, T, WP, WT> = Migrator<
+    P,
+    T,
+    MigrationFn<P, T>,
+    MigrationFn<P, T>,
+    MigrationFn<WP, WT>,
+    MigrationFn<WP, WT>,
+    WP,
+    WT,
+>;
 
 struct MigrationConfig<P, T, FO, FN>
 where
@@ -57,45 +178,109 @@ where
     _p: std::marker::PhantomData<P>,
 }
 
+// Settings which govern how each origin function is executed and measured.
+#[derive(Clone, Copy)]
+pub(super) struct ExecutionSettings {
+    pub(super) measure_latency: bool,
+    pub(super) measure_errors: bool,
+    pub(super) old_timeout: Option<Duration>,
+    pub(super) new_timeout: Option<Duration>,
+    pub(super) read_fallback: ReadFallback,
+}
+
+impl Default for ExecutionSettings {
+    fn default() -> Self {
+        ExecutionSettings {
+            measure_latency: true,
+            measure_errors: true,
+            old_timeout: None,
+            new_timeout: None,
+            read_fallback: ReadFallback::Disabled,
+        }
+    }
+}
+
+impl ExecutionSettings {
+    fn timeout(&self, origin: Origin) -> Option<Duration> {
+        match origin {
+            Origin::Old => self.old_timeout,
+            Origin::New => self.new_timeout,
+        }
+    }
+
+    fn check_runtime(&self) -> Result<(), MigratorBuildError> {
+        let timed = self.old_timeout.is_some() || self.new_timeout.is_some();
+        if timed && tokio::runtime::Handle::try_current().is_err() {
+            return Err(MigratorBuildError::TimeoutRequiresRuntime);
+        }
+        Ok(())
+    }
+}
+
+/// Error type used to represent failures when building a [Migrator] instance.
+#[non_exhaustive]
+#[derive(Debug, Error, PartialEq, Eq)]
+pub enum MigratorBuildError {
+    /// Error used when [MigratorBuilder::read] was not called before building.
+    #[error("read configuration not provided")]
+    MissingReadConfig,
+    /// Error used when [MigratorBuilder::write] was not called before building.
+    #[error("write configuration not provided")]
+    MissingWriteConfig,
+    /// Error used when [MigratorBuilder::timeout] was called, but the migrator was not built
+    /// within a Tokio runtime to measure the timeout with.
+    #[error("timeouts require a Tokio runtime")]
+    TimeoutRequiresRuntime,
+}
+
 /// The migration builder is used to configure and construct an instance of a [Migrator]. This
 /// migrator can be used to perform LaunchDarkly assisted technology migrations through the use of
 /// migration-based feature flags.
-pub struct MigratorBuilder<P, T, FRO, FRN, FWO, FWN>
+pub struct MigratorBuilder<P, T, FRO, FRN, FWO, FWN, WP, WT>
 where
     P: Send + Sync,
     T: Send + Sync,
+    WP: Send + Sync,
+    WT: Send + Sync,
     FRO: Fn(&P) -> BoxFuture<MigrationResult<T>> + Sync + Send,
     FRN: Fn(&P) -> BoxFuture<MigrationResult<T>> + Sync + Send,
-    FWO: Fn(&P) -> BoxFuture<MigrationResult<T>> + Sync + Send,
-    FWN: Fn(&P) -> BoxFuture<MigrationResult<T>> + Sync + Send,
+    FWO: Fn(&WP) -> BoxFuture<MigrationResult<WT>> + Sync + Send,
+    FWN: Fn(&WP) -> BoxFuture<MigrationResult<WT>> + Sync + Send,
 {
     client: Arc<Client>,
     read_execution_order: ExecutionOrder,
-    measure_latency: bool,
-    measure_errors: bool,
+    settings: ExecutionSettings,
 
     read_config: Option<MigrationConfig<P, T, FRO, FRN>>,
-    write_config: Option<MigrationConfig<P, T, FWO, FWN>>,
+    write_config: Option<MigrationConfig<WP, WT, FWO, FWN>>,
+    hooks: Hooks<P, T, FRO, FRN, FWO, FWN, WP, WT>,
 }
 
-impl<P, T, FRO, FRN, FWO, FWN> MigratorBuilder<P, T, FRO, FRN, FWO, FWN>
+impl<P, T, FRO, FRN, FWO, FWN, WP, WT> MigratorBuilder<P, T, FRO, FRN, FWO, FWN, WP, WT>
 where
     P: Send + Sync,
     T: Send + Sync,
+    WP: Send + Sync,
+    WT: Send + Sync,
     FRO: Fn(&P) -> BoxFuture<MigrationResult<T>> + Sync + Send,
     FRN: Fn(&P) -> BoxFuture<MigrationResult<T>> + Sync + Send,
-    FWO: Fn(&P) -> BoxFuture<MigrationResult<T>> + Sync + Send,
-    FWN: Fn(&P) -> BoxFuture<MigrationResult<T>> + Sync + Send,
+    FWO: Fn(&WP) -> BoxFuture<MigrationResult<WT>> + Sync + Send,
+    FWN: Fn(&WP) -> BoxFuture<MigrationResult<WT>> + Sync + Send,
 {
     /// Create a new migrator builder instance with the provided client.
     pub fn new(client: Arc<Client>) -> Self {
         MigratorBuilder {
             client,
             read_execution_order: ExecutionOrder::Concurrent,
-            measure_latency: true,
-            measure_errors: true,
+            settings: ExecutionSettings::default(),
             read_config: None,
             write_config: None,
+            hooks: Hooks {
+                on_mismatch: None,
+                on_write_failure: None,
+                background: None,
+                retries: None,
+            },
         }
     }
 
@@ -109,14 +294,51 @@ where
     /// Enable or disable latency tracking for migration operations. This latency information can
     /// be sent upstream to LaunchDarkly to enhance migration visibility.
     pub fn track_latency(mut self, measure: bool) -> Self {
-        self.measure_latency = measure;
+        self.settings.measure_latency = measure;
         self
     }
 
     /// Enable or disable error tracking for migration operations. This error information can be
     /// sent upstream to LaunchDarkly to enhance migration visibility.
     pub fn track_errors(mut self, measure: bool) -> Self {
-        self.measure_errors = measure;
+        self.settings.measure_errors = measure;
+        self
+    }
+
+    /// Limits how long reads and writes against the given origin may run, whether or not the
+    /// migration stage makes it authoritative. An operation which exceeds its timeout is
+    /// cancelled, and its result is an error which is tracked like any other failure of that
+    /// origin. By default, operations are not limited.
+    ///
+    /// A timeout on the origin which is non-authoritative for a read bounds how long it can delay
+    /// the authoritative result. A non-authoritative read which finishes within its timeout is
+    /// tracked and compared as usual, even if it is slower than the authoritative read. To return
+    /// the authoritative result without waiting for the non-authoritative origin at all, see
+    /// [MigratorBuilder::nonauthoritative_in_background].
+    ///
+    /// Timeouts are measured with Tokio's timer, so the migrator must be built, and its
+    /// operations run, within a Tokio runtime which has the time driver enabled. Building a
+    /// migrator with a timeout outside of a runtime fails with
+    /// [MigratorBuildError::TimeoutRequiresRuntime], and operations which are run outside of one
+    /// are not limited.
+    pub fn timeout(mut self, origin: Origin, timeout: Duration) -> Self {
+        match origin {
+            Origin::Old => self.settings.old_timeout = Some(timeout),
+            Origin::New => self.settings.new_timeout = Some(timeout),
+        }
+        self
+    }
+
+    /// Read fallback determines what is returned when a read executed against both origins, in
+    /// the shadow and live stages, fails against the authoritative origin. With
+    /// [ReadFallback::NonAuthoritative], a successful non-authoritative result is returned in
+    /// place of the error and marked with [MigrationOriginResult::fallback]. The authoritative
+    /// error is always tracked when this happens, even if error tracking is disabled.
+    ///
+    /// If the non-authoritative origin is called in the background, it is instead called in the
+    /// foreground whenever the authoritative read fails. The default is [ReadFallback::Disabled].
+    pub fn read_fallback(mut self, fallback: ReadFallback) -> Self {
+        self.settings.read_fallback = fallback;
         self
     }
 
@@ -138,14 +360,44 @@ where
         self
     }
 
-    /// Write can be used to configure the migration-write behavior of the resulting
-    /// [crate::Migrator] instance.
+    /// On mismatch registers a callback which is invoked whenever a read executed against both
+    /// origins returns results which the comparison function considers inconsistent. The
+    /// callback receives the payload along with both results, so the differing records can be
+    /// logged or persisted for investigation.
     ///
-    /// Users are required to provide two different write methods -- one to write to the old
-    /// migration origin, and one to write to the new origin. Not every stage requires
+    /// The callback is only invoked for reads whose consistency is checked, which is governed by
+    /// the comparison function provided to [MigratorBuilder::read] and the migration flag's check
+    /// ratio.
+    pub fn on_mismatch(
+        mut self,
+        on_mismatch: impl Fn(&MigrationMismatch<P, T>) + Send + Sync + 'static,
+    ) -> Self {
+        self.hooks.on_mismatch = Some(Arc::new(on_mismatch));
+        self
+    }
+
+    /// On write failure registers a callback which is invoked whenever a write to the
+    /// non-authoritative origin fails and will not be retried, so that the write can be
+    /// compensated for or recorded in an outbox. Without this callback, the two origins may
+    /// silently diverge.
     ///
-    /// Depending on the migration stage, one or both of these write methods may be called.
-    pub fn write(mut self, old: FWO, new: FWN) -> Self {
+    /// If retries are enabled with [MigratorBuilder::retry_failed_writes], the callback is only
+    /// invoked once they are exhausted.
+    pub fn on_write_failure(
+        mut self,
+        on_write_failure: impl Fn(&FailedWrite<WP>) + Send + Sync + 'static,
+    ) -> Self {
+        self.hooks.on_write_failure = Some(Box::new(on_write_failure));
+        self
+    }
+
+    /// Write with types configures the migration-write behavior of the resulting
+    /// [crate::Migrator] instance, as [MigratorBuilder::write] does, for writes which take a
+    /// different payload type, or produce a different result type, than reads.
+    ///
+    /// The payload and result types cannot be inferred from the reads, so the write methods'
+    /// parameter and result types usually need to be annotated.
+    pub fn write_with_types(mut self, old: FWO, new: FWN) -> Self {
         self.write_config = Some(MigrationConfig {
             old,
             new,
@@ -156,76 +408,453 @@ where
     }
 
     /// Build constructs a [crate::Migrator] instance to support migration-based reads and
-    /// writes. A string describing any failure conditions will be returned if the build fails.
-    pub fn build(self) -> Result<Migrator<P, T, FRO, FRN, FWO, FWN>, String> {
-        let read_config = self.read_config.ok_or("read configuration not provided")?;
+    /// writes. A [MigratorBuildError] describing the missing configuration will be returned if
+    /// the build fails.
+    #[allow(clippy::type_complexity)]
+    pub fn build(self) -> Result<Migrator<P, T, FRO, FRN, FWO, FWN, WP, WT>, MigratorBuildError> {
+        let read_config = self
+            .read_config
+            .ok_or(MigratorBuildError::MissingReadConfig)?;
         let write_config = self
             .write_config
-            .ok_or("write configuration not provided")?;
+            .ok_or(MigratorBuildError::MissingWriteConfig)?;
+        self.settings.check_runtime()?;
 
         Ok(Migrator::new(
             self.client,
             self.read_execution_order,
-            self.measure_latency,
-            self.measure_errors,
+            self.settings,
             read_config,
             write_config,
+            self.hooks,
         ))
     }
 }
 
+impl<P, T, FRO, FRN, FWO, FWN> MigratorBuilder<P, T, FRO, FRN, FWO, FWN, P, T>
+where
+    P: Send + Sync,
+    T: Send + Sync,
+    FRO: Fn(&P) -> BoxFuture<MigrationResult<T>> + Sync + Send,
+    FRN: Fn(&P) -> BoxFuture<MigrationResult<T>> + Sync + Send,
+    FWO: Fn(&P) -> BoxFuture<MigrationResult<T>> + Sync + Send,
+    FWN: Fn(&P) -> BoxFuture<MigrationResult<T>> + Sync + Send,
+{
+    /// Write can be used to configure the migration-write behavior of the resulting
+    /// [crate::Migrator] instance.
+    ///
+    /// Users are required to provide two different write methods -- one to write to the old
+    /// migration origin, and one to write to the new origin. Writes take the same payload type,
+    /// and produce the same result type, as reads. See [MigratorBuilder::write_with_types] for
+    /// writes which do not.
+    ///
+    /// Depending on the migration stage, one or both of these write methods may be called.
+    pub fn write(self, old: FWO, new: FWN) -> Self {
+        self.write_with_types(old, new)
+    }
+}
+
+impl<P, T, FRO, FRN, FWO, FWN, WP, WT> MigratorBuilder<P, T, FRO, FRN, FWO, FWN, WP, WT>
+where
+    P: Send + Sync + 'static,
+    T: Send + Sync + 'static,
+    WP: Send + Sync + 'static,
+    WT: Send + Sync + 'static,
+    FRO: Fn(&P) -> BoxFuture<MigrationResult<T>> + Sync + Send + 'static,
+    FRN: Fn(&P) -> BoxFuture<MigrationResult<T>> + Sync + Send + 'static,
+    FWO: Fn(&WP) -> BoxFuture<MigrationResult<WT>> + Sync + Send + 'static,
+    FWN: Fn(&WP) -> BoxFuture<MigrationResult<WT>> + Sync + Send + 'static,
+{
+    /// Specifies whether the non-authoritative origin should be called in the background. When
+    /// enabled, reads in the shadow and live stages, and writes in the dual write through rampdown
+    /// stages, return as soon as the authoritative origin responds. The non-authoritative
+    /// operation, the consistency check, and the tracking of the operation then finish as a task
+    /// on the current Tokio runtime.
+    ///
+    /// Results from the non-authoritative origin are not returned to the caller in this mode, so
+    /// [MigrationWriteResult::nonauthoritative] is always `None`, and the read execution order is
+    /// ignored. If there is no current runtime, the non-authoritative operation runs before the
+    /// call returns. The default is false.
+    pub fn nonauthoritative_in_background(mut self, enabled: bool) -> Self
+    where
+        T: Clone,
+    {
+        self.hooks.background = enabled.then_some(Background {
+            clone_result: MigrationResult::<T>::clone,
+            spawn: run_in_background,
+        });
+        self
+    }
+
+    /// Retry failed writes to the non-authoritative origin according to the provided policy.
+    /// Retries run as tasks on the current Tokio runtime; if there is no current runtime, failed
+    /// writes are not retried.
+    ///
+    /// By default, failed writes are not retried.
+    pub fn retry_failed_writes(mut self, policy: WriteRetryPolicy) -> Self {
+        self.hooks.retries = Some(Retries {
+            policy,
+            pending: AtomicUsize::new(0),
+            spawn: retry_in_background,
+        });
+        self
+    }
+
+    /// Build constructs a [DynMigrator] instance, which behaves exactly like the [Migrator]
+    /// returned from [MigratorBuilder::build] but does not carry the types of the configured
+    /// origin functions.
+    pub fn build_dyn(self) -> Result<DynMigratorWithTypes<P, T, WP, WT>, MigratorBuildError> {
+        let read_config = self
+            .read_config
+            .ok_or(MigratorBuildError::MissingReadConfig)?;
+        let write_config = self
+            .write_config
+            .ok_or(MigratorBuildError::MissingWriteConfig)?;
+        self.settings.check_runtime()?;
+
+        Ok(Migrator::new(
+            self.client,
+            self.read_execution_order,
+            self.settings,
+            read_config.boxed(),
+            write_config.boxed(),
+            self.hooks.boxed(),
+        ))
+    }
+}
+
+impl<P, T, FO, FN> MigrationConfig<P, T, FO, FN>
+where
+    P: Send + Sync + 'static,
+    T: Send + Sync + 'static,
+    FO: Fn(&P) -> BoxFuture<MigrationResult<T>> + Sync + Send + 'static,
+    FN: Fn(&P) -> BoxFuture<MigrationResult<T>> + Sync + Send + 'static,
+{
+    fn boxed(self) -> MigrationConfig<P, T, MigrationFn<P, T>, MigrationFn<P, T>> {
+        MigrationConfig {
+            old: Box::new(self.old),
+            new: Box::new(self.new),
+            compare: self.compare,
+            _p: std::marker::PhantomData,
+        }
+    }
+}
+
+// Optional behaviour configured through the builder, shared by every clone of a migrator.
+#[allow(clippy::type_complexity)]
+struct Hooks<P, T, FRO, FRN, FWO, FWN, WP, WT>
+where
+    P: Send + Sync,
+    T: Send + Sync,
+    WP: Send + Sync,
+    WT: Send + Sync,
+    FRO: Fn(&P) -> BoxFuture<MigrationResult<T>> + Sync + Send,
+    FRN: Fn(&P) -> BoxFuture<MigrationResult<T>> + Sync + Send,
+    FWO: Fn(&WP) -> BoxFuture<MigrationResult<WT>> + Sync + Send,
+    FWN: Fn(&WP) -> BoxFuture<MigrationResult<WT>> + Sync + Send,
+{
+    on_mismatch: Option<MigrationMismatchFn<P, T>>,
+    on_write_failure: Option<WriteFailureFn<WP>>,
+    background: Option<Background<P, T, FRO, FRN, FWO, FWN, WP, WT>>,
+    retries: Option<Retries<P, T, FRO, FRN, FWO, FWN, WP, WT>>,
+}
+
+impl<P, T, FRO, FRN, FWO, FWN, WP, WT> Hooks<P, T, FRO, FRN, FWO, FWN, WP, WT>
+where
+    P: Send + Sync + 'static,
+    T: Send + Sync + 'static,
+    WP: Send + Sync + 'static,
+    WT: Send + Sync + 'static,
+    FRO: Fn(&P) -> BoxFuture<MigrationResult<T>> + Sync + Send + 'static,
+    FRN: Fn(&P) -> BoxFuture<MigrationResult<T>> + Sync + Send + 'static,
+    FWO: Fn(&WP) -> BoxFuture<MigrationResult<WT>> + Sync + Send + 'static,
+    FWN: Fn(&WP) -> BoxFuture<MigrationResult<WT>> + Sync + Send + 'static,
+{
+    fn boxed(self) -> DynHooks<P, T, WP, WT> {
+        Hooks {
+            on_mismatch: self.on_mismatch,
+            on_write_failure: self.on_write_failure,
+            background: self.background.map(|background| Background {
+                clone_result: background.clone_result,
+                spawn: run_in_background,
+            }),
+            retries: self.retries.map(|retries| Retries {
+                policy: retries.policy,
+                pending: retries.pending,
+                spawn: retry_in_background,
+            }),
+        }
+    }
+}
+
+type DynHooks<P, T, WP, WT> = Hooks<
+    P,
+    T,
+    MigrationFn<P, T>,
+    MigrationFn<P, T>,
+    MigrationFn<WP, WT>,
+    MigrationFn<WP, WT>,
+    WP,
+    WT,
+>;
+
+// Retries failed non-authoritative writes. As with [Background], spawning is done through a
+// function pointer so that its 'static bounds are only imposed on migrators which opt in.
+struct Retries<P, T, FRO, FRN, FWO, FWN, WP, WT>
+where
+    P: Send + Sync,
+    T: Send + Sync,
+    WP: Send + Sync,
+    WT: Send + Sync,
+    FRO: Fn(&P) -> BoxFuture<MigrationResult<T>> + Sync + Send,
+    FRN: Fn(&P) -> BoxFuture<MigrationResult<T>> + Sync + Send,
+    FWO: Fn(&WP) -> BoxFuture<MigrationResult<WT>> + Sync + Send,
+    FWN: Fn(&WP) -> BoxFuture<MigrationResult<WT>> + Sync + Send,
+{
+    policy: WriteRetryPolicy,
+    // The number of failed writes currently waiting to be retried.
+    pending: AtomicUsize,
+    // Returns the write back to the caller if it could not be queued.
+    spawn: RetryFn<P, T, FRO, FRN, FWO, FWN, WP, WT>,
+}
+
+type RetryFn<P, T, FRO, FRN, FWO, FWN, WP, WT> = fn(
+    &Migrator<P, T, FRO, FRN, FWO, FWN, WP, WT>,
+    PendingWrite<WP>,
+) -> Result<(), PendingWrite<WP>>;
+
+// A failed write to the non-authoritative origin, which may yet be retried.
+struct PendingWrite<P> {
+    origin: Origin,
+    flag_key: String,
+    stage: Stage,
+    payload: Arc<P>,
+    error: String,
+    attempts: u32,
+}
+
+fn retry_in_background<P, T, FRO, FRN, FWO, FWN, WP, WT>(
+    migrator: &Migrator<P, T, FRO, FRN, FWO, FWN, WP, WT>,
+    write: PendingWrite<WP>,
+) -> Result<(), PendingWrite<WP>>
+where
+    P: Send + Sync + 'static,
+    T: Send + Sync + 'static,
+    WP: Send + Sync + 'static,
+    WT: Send + Sync + 'static,
+    FRO: Fn(&P) -> BoxFuture<MigrationResult<T>> + Sync + Send + 'static,
+    FRN: Fn(&P) -> BoxFuture<MigrationResult<T>> + Sync + Send + 'static,
+    FWO: Fn(&WP) -> BoxFuture<MigrationResult<WT>> + Sync + Send + 'static,
+    FWN: Fn(&WP) -> BoxFuture<MigrationResult<WT>> + Sync + Send + 'static,
+{
+    let retries = match &migrator.hooks.retries {
+        Some(retries) if retries.policy.max_retries > 0 => retries,
+        _ => return Err(write),
+    };
+    let handle = match tokio::runtime::Handle::try_current() {
+        Ok(handle) => handle,
+        Err(_) => {
+            warn!("No runtime is available to retry a failed migration write.");
+            return Err(write);
+        }
+    };
+
+    let capacity = retries.policy.capacity;
+    let reserved = retries
+        .pending
+        .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |pending| {
+            (pending < capacity).then_some(pending + 1)
+        });
+    if reserved.is_err() {
+        warn!("The migration write retry queue is full; not retrying a failed write.");
+        return Err(write);
+    }
+
+    let migrator = migrator.clone();
+    handle.spawn(async move { migrator.retry_write(write).await });
+    Ok(())
+}
+
+// Hands the non-authoritative half of a migration operation to the runtime. These are function
+// pointers so that the 'static bounds which spawning requires are only imposed on migrators
+// which opt in, through [MigratorBuilder::nonauthoritative_in_background].
+struct Background<P, T, FRO, FRN, FWO, FWN, WP, WT>
+where
+    P: Send + Sync,
+    T: Send + Sync,
+    WP: Send + Sync,
+    WT: Send + Sync,
+    FRO: Fn(&P) -> BoxFuture<MigrationResult<T>> + Sync + Send,
+    FRN: Fn(&P) -> BoxFuture<MigrationResult<T>> + Sync + Send,
+    FWO: Fn(&WP) -> BoxFuture<MigrationResult<WT>> + Sync + Send,
+    FWN: Fn(&WP) -> BoxFuture<MigrationResult<WT>> + Sync + Send,
+{
+    clone_result: fn(&MigrationResult<T>) -> MigrationResult<T>,
+    // Returns the work back to the caller if there is no runtime to spawn it onto.
+    spawn: DetachFn<P, T, FRO, FRN, FWO, FWN, WP, WT>,
+}
+
+type DetachFn<P, T, FRO, FRN, FWO, FWN, WP, WT> = fn(
+    &Migrator<P, T, FRO, FRN, FWO, FWN, WP, WT>,
+    DetachedOp<P, T, WP>,
+) -> Result<(), BoxFuture<'static, ()>>;
+
+impl<P, T, FRO, FRN, FWO, FWN, WP, WT> Clone for Background<P, T, FRO, FRN, FWO, FWN, WP, WT>
+where
+    P: Send + Sync,
+    T: Send + Sync,
+    WP: Send + Sync,
+    WT: Send + Sync,
+    FRO: Fn(&P) -> BoxFuture<MigrationResult<T>> + Sync + Send,
+    FRN: Fn(&P) -> BoxFuture<MigrationResult<T>> + Sync + Send,
+    FWO: Fn(&WP) -> BoxFuture<MigrationResult<WT>> + Sync + Send,
+    FWN: Fn(&WP) -> BoxFuture<MigrationResult<WT>> + Sync + Send,
+{
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<P, T, FRO, FRN, FWO, FWN, WP, WT> Copy for Background<P, T, FRO, FRN, FWO, FWN, WP, WT>
+where
+    P: Send + Sync,
+    T: Send + Sync,
+    WP: Send + Sync,
+    WT: Send + Sync,
+    FRO: Fn(&P) -> BoxFuture<MigrationResult<T>> + Sync + Send,
+    FRN: Fn(&P) -> BoxFuture<MigrationResult<T>> + Sync + Send,
+    FWO: Fn(&WP) -> BoxFuture<MigrationResult<WT>> + Sync + Send,
+    FWN: Fn(&WP) -> BoxFuture<MigrationResult<WT>> + Sync + Send,
+{
+}
+
+// The non-authoritative half of a migration operation, detached from the caller.
+struct DetachedOp<P, T, WP> {
+    origin: Origin,
+    flag_key: String,
+    stage: Stage,
+    tracker: Arc<Mutex<MigrationOpTracker>>,
+    work: DetachedWork<P, T, WP>,
+}
+
+enum DetachedWork<P, T, WP> {
+    Read {
+        payload: Arc<P>,
+        // The authoritative result, if its consistency should be checked.
+        authoritative: Option<MigrationOriginResult<T>>,
+    },
+    Write {
+        payload: Arc<WP>,
+    },
+}
+
+fn run_in_background<P, T, FRO, FRN, FWO, FWN, WP, WT>(
+    migrator: &Migrator<P, T, FRO, FRN, FWO, FWN, WP, WT>,
+    op: DetachedOp<P, T, WP>,
+) -> Result<(), BoxFuture<'static, ()>>
+where
+    P: Send + Sync + 'static,
+    T: Send + Sync + 'static,
+    WP: Send + Sync + 'static,
+    WT: Send + Sync + 'static,
+    FRO: Fn(&P) -> BoxFuture<MigrationResult<T>> + Sync + Send + 'static,
+    FRN: Fn(&P) -> BoxFuture<MigrationResult<T>> + Sync + Send + 'static,
+    FWO: Fn(&WP) -> BoxFuture<MigrationResult<WT>> + Sync + Send + 'static,
+    FWN: Fn(&WP) -> BoxFuture<MigrationResult<WT>> + Sync + Send + 'static,
+{
+    let migrator = migrator.clone();
+    let task = async move { migrator.finish_detached(op).await }.boxed();
+
+    match tokio::runtime::Handle::try_current() {
+        Ok(handle) => {
+            handle.spawn(task);
+            Ok(())
+        }
+        Err(_) => Err(task),
+    }
+}
+
 /// The migrator is the primary interface for executing migration operations. It is configured
 /// through the [MigratorBuilder] and can be used to perform LaunchDarkly assisted technology
 /// migrations through the use of migration-based feature flags.
-pub struct Migrator<P, T, FRO, FRN, FWO, FWN>
+///
+/// A migrator may be shared between tasks and threads. Cloning it is cheap, and all clones share
+/// the same configuration.
+#[allow(clippy::type_complexity)]
+pub struct Migrator<P, T, FRO, FRN, FWO, FWN, WP, WT>
 where
     P: Send + Sync,
     T: Send + Sync,
+    WP: Send + Sync,
+    WT: Send + Sync,
     FRO: Fn(&P) -> BoxFuture<MigrationResult<T>> + Sync + Send,
     FRN: Fn(&P) -> BoxFuture<MigrationResult<T>> + Sync + Send,
-    FWO: Fn(&P) -> BoxFuture<MigrationResult<T>> + Sync + Send,
-    FWN: Fn(&P) -> BoxFuture<MigrationResult<T>> + Sync + Send,
+    FWO: Fn(&WP) -> BoxFuture<MigrationResult<WT>> + Sync + Send,
+    FWN: Fn(&WP) -> BoxFuture<MigrationResult<WT>> + Sync + Send,
 {
     client: Arc<Client>,
     read_execution_order: ExecutionOrder,
-    measure_latency: bool,
-    measure_errors: bool,
-    read_config: MigrationConfig<P, T, FRO, FRN>,
-    write_config: MigrationConfig<P, T, FWO, FWN>,
-    sampler: Box<dyn Sampler>,
+    settings: ExecutionSettings,
+    read_config: Arc<MigrationConfig<P, T, FRO, FRN>>,
+    write_config: Arc<MigrationConfig<WP, WT, FWO, FWN>>,
+    hooks: Arc<Hooks<P, T, FRO, FRN, FWO, FWN, WP, WT>>,
 }
 
-impl<P, T, FRO, FRN, FWO, FWN> Migrator<P, T, FRO, FRN, FWO, FWN>
+impl<P, T, FRO, FRN, FWO, FWN, WP, WT> Clone for Migrator<P, T, FRO, FRN, FWO, FWN, WP, WT>
 where
     P: Send + Sync,
     T: Send + Sync,
+    WP: Send + Sync,
+    WT: Send + Sync,
     FRO: Fn(&P) -> BoxFuture<MigrationResult<T>> + Sync + Send,
     FRN: Fn(&P) -> BoxFuture<MigrationResult<T>> + Sync + Send,
-    FWO: Fn(&P) -> BoxFuture<MigrationResult<T>> + Sync + Send,
-    FWN: Fn(&P) -> BoxFuture<MigrationResult<T>> + Sync + Send,
+    FWO: Fn(&WP) -> BoxFuture<MigrationResult<WT>> + Sync + Send,
+    FWN: Fn(&WP) -> BoxFuture<MigrationResult<WT>> + Sync + Send,
+{
+    fn clone(&self) -> Self {
+        Migrator {
+            client: self.client.clone(),
+            read_execution_order: self.read_execution_order,
+            settings: self.settings,
+            read_config: self.read_config.clone(),
+            write_config: self.write_config.clone(),
+            hooks: self.hooks.clone(),
+        }
+    }
+}
+
+impl<P, T, FRO, FRN, FWO, FWN, WP, WT> Migrator<P, T, FRO, FRN, FWO, FWN, WP, WT>
+where
+    P: Send + Sync,
+    T: Send + Sync,
+    WP: Send + Sync,
+    WT: Send + Sync,
+    FRO: Fn(&P) -> BoxFuture<MigrationResult<T>> + Sync + Send,
+    FRN: Fn(&P) -> BoxFuture<MigrationResult<T>> + Sync + Send,
+    FWO: Fn(&WP) -> BoxFuture<MigrationResult<WT>> + Sync + Send,
+    FWN: Fn(&WP) -> BoxFuture<MigrationResult<WT>> + Sync + Send,
 {
     fn new(
         client: Arc<Client>,
         read_execution_order: ExecutionOrder,
-        measure_latency: bool,
-        measure_errors: bool,
+        settings: ExecutionSettings,
         read_config: MigrationConfig<P, T, FRO, FRN>,
-        write_config: MigrationConfig<P, T, FWO, FWN>,
+        write_config: MigrationConfig<WP, WT, FWO, FWN>,
+        hooks: Hooks<P, T, FRO, FRN, FWO, FWN, WP, WT>,
     ) -> Self {
         Migrator {
             client,
             read_execution_order,
-            measure_latency,
-            measure_errors,
-            read_config,
-            write_config,
-            sampler: Box::new(ThreadRngSampler::new(rng())),
+            settings,
+            read_config: Arc::new(read_config),
+            write_config: Arc::new(write_config),
+            hooks: Arc::new(hooks),
         }
     }
 
     /// Uses the provided flag key and context to execute a migration-backed read operation.
     pub async fn read(
-        &mut self,
+        &self,
         context: &Context,
         flag_key: String,
         default_stage: Stage,
@@ -241,47 +870,67 @@ where
             error!("Failed to acquire tracker lock. Cannot track migration write.");
         }
 
-        let mut old = Executor {
-            origin: Origin::Old,
-            function: &self.read_config.old,
-            tracker: tracker.clone(),
-            measure_latency: self.measure_latency,
-            measure_errors: self.measure_errors,
-            payload: &payload,
-        };
-        let mut new = Executor {
-            origin: Origin::New,
-            function: &self.read_config.new,
-            tracker: tracker.clone(),
-            measure_latency: self.measure_latency,
-            measure_errors: self.measure_errors,
-            payload: &payload,
-        };
+        let payload = Arc::new(payload);
+        let mut old = self.executor(Origin::Old, &self.read_config.old, &tracker, &payload);
+        let mut new = self.executor(Origin::New, &self.read_config.new, &tracker, &payload);
+
+        if let Some(background) = self.hooks.background {
+            let detached = match stage {
+                Stage::Shadow => Some((old.run().await, Origin::New)),
+                Stage::Live => Some((new.run().await, Origin::Old)),
+                _ => None,
+            };
+
+            if let Some((result, origin)) = detached {
+                if result.result.is_err() && self.settings.read_fallback != ReadFallback::Disabled {
+                    // The non-authoritative result is needed now, so it cannot be left to run in
+                    // the background.
+                    let nonauthoritative = match origin {
+                        Origin::Old => old.run().await,
+                        Origin::New => new.run().await,
+                    };
+                    let result = fall_back(result, nonauthoritative, &tracker);
+                    self.client.track_migration_op(tracker);
+
+                    return result;
+                }
+
+                let authoritative = self.read_config.compare.map(|_| MigrationOriginResult {
+                    origin: result.origin,
+                    result: (background.clone_result)(&result.result),
+                    fallback: false,
+                });
+                let op = DetachedOp {
+                    origin,
+                    flag_key,
+                    stage,
+                    tracker,
+                    work: DetachedWork::Read {
+                        payload: payload.clone(),
+                        authoritative,
+                    },
+                };
+                self.detach(background, op).await;
+
+                return result;
+            }
+        }
+
+        let check = self.read_config.compare.map(|compare| ConsistencyCheck {
+            compare,
+            on_mismatch: self.hooks.on_mismatch.as_ref(),
+            flag_key: &flag_key,
+            stage,
+        });
 
         let result = match stage {
             Stage::Off => old.run().await,
             Stage::DualWrite => old.run().await,
             Stage::Shadow => {
-                read_both(
-                    old,
-                    new,
-                    self.read_config.compare,
-                    self.read_execution_order,
-                    tracker.clone(),
-                    self.sampler.as_mut(),
-                )
-                .await
+                read_both(old, new, check, self.read_execution_order, tracker.clone()).await
             }
             Stage::Live => {
-                read_both(
-                    new,
-                    old,
-                    self.read_config.compare,
-                    self.read_execution_order,
-                    tracker.clone(),
-                    self.sampler.as_mut(),
-                )
-                .await
+                read_both(new, old, check, self.read_execution_order, tracker.clone()).await
             }
             Stage::Rampdown => new.run().await,
             Stage::Complete => new.run().await,
@@ -294,12 +943,12 @@ where
 
     /// Uses the provided flag key and context to execute a migration-backed write operation.
     pub async fn write(
-        &mut self,
+        &self,
         context: &Context,
         flag_key: String,
         default_stage: Stage,
-        payload: P,
-    ) -> MigrationWriteResult<T> {
+        payload: WP,
+    ) -> MigrationWriteResult<WT> {
         let (stage, tracker) = self
             .client
             .migration_variation(context, &flag_key, default_stage);
@@ -310,22 +959,41 @@ where
             error!("Failed to acquire tracker lock. Cannot track migration write.");
         }
 
-        let mut old = Executor {
-            origin: Origin::Old,
-            function: &self.write_config.old,
-            tracker: tracker.clone(),
-            measure_latency: self.measure_latency,
-            measure_errors: self.measure_errors,
-            payload: &payload,
-        };
-        let mut new = Executor {
-            origin: Origin::New,
-            function: &self.write_config.new,
-            tracker: tracker.clone(),
-            measure_latency: self.measure_latency,
-            measure_errors: self.measure_errors,
-            payload: &payload,
-        };
+        let payload = Arc::new(payload);
+        let mut old = self.executor(Origin::Old, &self.write_config.old, &tracker, &payload);
+        let mut new = self.executor(Origin::New, &self.write_config.new, &tracker, &payload);
+
+        if let Some(background) = self.hooks.background {
+            let detached = match stage {
+                Stage::DualWrite | Stage::Shadow => Some((old.run().await, Origin::New)),
+                Stage::Live | Stage::Rampdown => Some((new.run().await, Origin::Old)),
+                _ => None,
+            };
+
+            if let Some((result, origin)) = detached {
+                // As when writing in the foreground, the non-authoritative origin is only written
+                // to if the authoritative write succeeded.
+                if result.result.is_ok() {
+                    let op = DetachedOp {
+                        origin,
+                        flag_key,
+                        stage,
+                        tracker,
+                        work: DetachedWork::Write {
+                            payload: payload.clone(),
+                        },
+                    };
+                    self.detach(background, op).await;
+                } else {
+                    self.client.track_migration_op(tracker);
+                }
+
+                return MigrationWriteResult {
+                    authoritative: result,
+                    nonauthoritative: None,
+                };
+            }
+        }
 
         let result = match stage {
             Stage::Off => MigrationWriteResult {
@@ -343,18 +1011,234 @@ where
         };
 
         self.client.track_migration_op(tracker);
+        if let Some(MigrationOriginResult {
+            origin,
+            result: Err(error),
+            ..
+        }) = &result.nonauthoritative
+        {
+            self.write_failed(PendingWrite {
+                origin: *origin,
+                flag_key,
+                stage,
+                payload: payload.clone(),
+                error: error.clone(),
+                attempts: 1,
+            });
+        }
 
         result
     }
+
+    fn executor<'a, X, R, F>(
+        &self,
+        origin: Origin,
+        function: &'a F,
+        tracker: &Arc<Mutex<MigrationOpTracker>>,
+        payload: &'a X,
+    ) -> Executor<'a, X, R, F>
+    where
+        X: Send + Sync,
+        R: Send + Sync,
+        F: Fn(&X) -> BoxFuture<MigrationResult<R>> + Sync + Send,
+    {
+        Executor {
+            origin,
+            function,
+            tracker: tracker.clone(),
+            settings: self.settings,
+            payload,
+        }
+    }
+
+    async fn detach(
+        &self,
+        background: Background<P, T, FRO, FRN, FWO, FWN, WP, WT>,
+        op: DetachedOp<P, T, WP>,
+    ) {
+        if let Err(task) = (background.spawn)(self, op) {
+            warn!("No runtime is available to run non-authoritative migration work in the background; running it in the foreground instead.");
+            task.await;
+        }
+    }
+
+    async fn read_origin(
+        &self,
+        origin: Origin,
+        tracker: &Arc<Mutex<MigrationOpTracker>>,
+        payload: &P,
+    ) -> MigrationOriginResult<T> {
+        match origin {
+            Origin::Old => {
+                self.executor(origin, &self.read_config.old, tracker, payload)
+                    .run()
+                    .await
+            }
+            Origin::New => {
+                self.executor(origin, &self.read_config.new, tracker, payload)
+                    .run()
+                    .await
+            }
+        }
+    }
+
+    async fn write_origin(
+        &self,
+        origin: Origin,
+        tracker: &Arc<Mutex<MigrationOpTracker>>,
+        payload: &WP,
+    ) -> MigrationOriginResult<WT> {
+        match origin {
+            Origin::Old => {
+                self.executor(origin, &self.write_config.old, tracker, payload)
+                    .run()
+                    .await
+            }
+            Origin::New => {
+                self.executor(origin, &self.write_config.new, tracker, payload)
+                    .run()
+                    .await
+            }
+        }
+    }
+
+    async fn finish_detached(&self, op: DetachedOp<P, T, WP>) {
+        match op.work {
+            DetachedWork::Read {
+                payload,
+                authoritative,
+            } => {
+                let result = self.read_origin(op.origin, &op.tracker, &payload).await;
+
+                if let (Some(authoritative), Some(compare)) =
+                    (&authoritative, self.read_config.compare)
+                {
+                    let check = ConsistencyCheck {
+                        compare,
+                        on_mismatch: self.hooks.on_mismatch.as_ref(),
+                        flag_key: &op.flag_key,
+                        stage: op.stage,
+                    };
+                    check_consistency(check, &payload, authoritative, &result, &op.tracker);
+                }
+                self.client.track_migration_op(op.tracker);
+            }
+            DetachedWork::Write { payload } => {
+                let result = self.write_origin(op.origin, &op.tracker, &payload).await;
+                self.client.track_migration_op(op.tracker);
+
+                if let Err(error) = result.result {
+                    self.write_failed(PendingWrite {
+                        origin: op.origin,
+                        flag_key: op.flag_key,
+                        stage: op.stage,
+                        payload,
+                        error,
+                        attempts: 1,
+                    });
+                }
+            }
+        }
+    }
+
+    // Queues a failed non-authoritative write for retry, or gives up on it if it cannot be
+    // retried. The operation has already been tracked with the outcome of the original attempt.
+    fn write_failed(&self, write: PendingWrite<WP>) {
+        let write = match &self.hooks.retries {
+            Some(retries) => match (retries.spawn)(self, write) {
+                Ok(()) => return,
+                Err(write) => write,
+            },
+            None => write,
+        };
+
+        self.give_up_on_write(write);
+    }
+
+    async fn retry_write(&self, mut write: PendingWrite<WP>) {
+        let policy = match &self.hooks.retries {
+            Some(retries) => retries.policy,
+            None => return,
+        };
+
+        let mut backoff = policy.initial_backoff;
+        let mut succeeded = false;
+        while write.attempts <= policy.max_retries {
+            tokio::time::sleep(backoff).await;
+            backoff = (backoff * 2).min(policy.max_backoff);
+            write.attempts += 1;
+
+            // Retries are not part of the tracked operation, which describes the original attempt.
+            let result = match write.origin {
+                Origin::Old => {
+                    call(
+                        write.origin,
+                        self.settings,
+                        &self.write_config.old,
+                        &*write.payload,
+                    )
+                    .await
+                }
+                Origin::New => {
+                    call(
+                        write.origin,
+                        self.settings,
+                        &self.write_config.new,
+                        &*write.payload,
+                    )
+                    .await
+                }
+            };
+            match result {
+                Ok(_) => {
+                    succeeded = true;
+                    break;
+                }
+                Err(error) => write.error = error,
+            }
+        }
+
+        if let Some(retries) = &self.hooks.retries {
+            retries.pending.fetch_sub(1, Ordering::SeqCst);
+        }
+
+        if succeeded {
+            debug!(
+                "Migration write to the {:?} origin for {} succeeded after {} attempts",
+                write.origin, write.flag_key, write.attempts
+            );
+        } else {
+            self.give_up_on_write(write);
+        }
+    }
+
+    fn give_up_on_write(&self, write: PendingWrite<WP>) {
+        if let Some(on_write_failure) = &self.hooks.on_write_failure {
+            on_write_failure(&FailedWrite {
+                flag_key: &write.flag_key,
+                stage: write.stage,
+                origin: write.origin,
+                payload: &write.payload,
+                error: &write.error,
+                attempts: write.attempts,
+            });
+        }
+    }
+}
+
+pub(super) struct ConsistencyCheck<'a, P, T> {
+    pub(super) compare: MigrationComparisonFn<T>,
+    pub(super) on_mismatch: Option<&'a MigrationMismatchFn<P, T>>,
+    pub(super) flag_key: &'a str,
+    pub(super) stage: Stage,
 }
 
 async fn read_both<P, T, FA, FB>(
     mut authoritative: Executor<'_, P, T, FA>,
     mut nonauthoritative: Executor<'_, P, T, FB>,
-    compare: Option<MigrationComparisonFn<T>>,
+    check: Option<ConsistencyCheck<'_, P, T>>,
     execution_order: ExecutionOrder,
     tracker: Arc<Mutex<MigrationOpTracker>>,
-    sampler: &mut dyn Sampler,
 ) -> MigrationOriginResult<T>
 where
     P: Send + Sync,
@@ -378,14 +1262,16 @@ where
             nonauthoritative_result = results.pop().unwrap_or_else(|| MigrationOriginResult {
                 origin: nonauthoritative.origin,
                 result: Err("Failed to execute non-authoritative read".into()),
+                fallback: false,
             });
 
             authoritative_result = results.pop().unwrap_or_else(|| MigrationOriginResult {
                 origin: authoritative.origin,
                 result: Err("Failed to execute authoritative read".into()),
+                fallback: false,
             });
         }
-        ExecutionOrder::Random if sampler.sample(2) => {
+        ExecutionOrder::Random if ThreadRngSampler::new(rng()).sample(2) => {
             nonauthoritative_result = nonauthoritative.run().await;
             authoritative_result = authoritative.run().await;
         }
@@ -395,20 +1281,85 @@ where
         }
     };
 
-    if let Some(compare) = compare {
-        if let (Ok(authoritative), Ok(nonauthoritative)) = (
-            &authoritative_result.result,
-            &nonauthoritative_result.result,
-        ) {
-            if let Ok(mut tracker) = tracker.lock() {
-                tracker.consistent(|| compare(authoritative, nonauthoritative));
-            } else {
-                error!("Failed to acquire tracker lock. Cannot track consistency.");
-            }
+    if let Some(check) = check {
+        check_consistency(
+            check,
+            authoritative.payload,
+            &authoritative_result,
+            &nonauthoritative_result,
+            &tracker,
+        );
+    }
+
+    match authoritative.settings.read_fallback {
+        ReadFallback::NonAuthoritative => {
+            fall_back(authoritative_result, nonauthoritative_result, &tracker)
         }
+        ReadFallback::Disabled => authoritative_result,
+    }
+}
+
+// Returns the non-authoritative result in place of a failed authoritative read, provided that it
+// succeeded. The authoritative origin's error is tracked whenever this happens.
+pub(super) fn fall_back<T>(
+    authoritative_result: MigrationOriginResult<T>,
+    nonauthoritative_result: MigrationOriginResult<T>,
+    tracker: &Arc<Mutex<MigrationOpTracker>>,
+) -> MigrationOriginResult<T> {
+    if authoritative_result.result.is_ok() || nonauthoritative_result.result.is_err() {
+        return authoritative_result;
+    }
+
+    if let Ok(mut tracker) = tracker.lock() {
+        tracker.error(authoritative_result.origin);
+    } else {
+        error!("Failed to acquire tracker lock. Cannot track migration error.");
     }
 
-    authoritative_result
+    warn!(
+        "Authoritative {:?} read failed; falling back to the {:?} origin.",
+        authoritative_result.origin, nonauthoritative_result.origin
+    );
+
+    MigrationOriginResult {
+        fallback: true,
+        ..nonauthoritative_result
+    }
+}
+
+pub(super) fn check_consistency<P, T>(
+    check: ConsistencyCheck<'_, P, T>,
+    payload: &P,
+    authoritative_result: &MigrationOriginResult<T>,
+    nonauthoritative_result: &MigrationOriginResult<T>,
+    tracker: &Arc<Mutex<MigrationOpTracker>>,
+) {
+    if let (Ok(authoritative_value), Ok(nonauthoritative_value)) = (
+        &authoritative_result.result,
+        &nonauthoritative_result.result,
+    ) {
+        let consistent = if let Ok(mut tracker) = tracker.lock() {
+            tracker
+                .check_consistency(|| (check.compare)(authoritative_value, nonauthoritative_value))
+        } else {
+            error!("Failed to acquire tracker lock. Cannot track consistency.");
+            None
+        };
+
+        if let (Some(false), Some(on_mismatch)) = (consistent, check.on_mismatch) {
+            let (old, new) = match authoritative_result.origin {
+                Origin::Old => (authoritative_value, nonauthoritative_value),
+                Origin::New => (nonauthoritative_value, authoritative_value),
+            };
+            on_mismatch(&MigrationMismatch {
+                flag_key: check.flag_key,
+                stage: check.stage,
+                payload,
+                old,
+                new,
+            });
+        }
+    }
 }
 
 async fn write_both<P, T, FA, FB>(
@@ -438,6 +1389,28 @@ where
     }
 }
 
+// Calls an origin's function, limited by the origin's timeout.
+async fn call<P, T, F>(
+    origin: Origin,
+    settings: ExecutionSettings,
+    function: &F,
+    payload: &P,
+) -> MigrationResult<T>
+where
+    F: Fn(&P) -> BoxFuture<MigrationResult<T>> + Sync + Send,
+{
+    let timeout = settings
+        .timeout(origin)
+        .filter(|_| tokio::runtime::Handle::try_current().is_ok());
+    match timeout {
+        // Dropping the operation's future when the timeout elapses cancels it.
+        Some(timeout) => tokio::time::timeout(timeout, function(payload))
+            .await
+            .unwrap_or_else(|_| Err(format!("{:?} origin timed out after {:?}", origin, timeout))),
+        None => function(payload).await,
+    }
+}
+
 struct Executor<'a, P, T, F>
 where
     P: Send + Sync,
@@ -447,8 +1420,7 @@ where
     origin: Origin,
     function: &'a F,
     tracker: Arc<Mutex<MigrationOpTracker>>,
-    measure_latency: bool,
-    measure_errors: bool,
+    settings: ExecutionSettings,
     payload: &'a P,
 }
 
@@ -460,16 +1432,16 @@ where
 {
     async fn run(&mut self) -> MigrationOriginResult<T> {
         let start = Instant::now();
-        let result = (self.function)(self.payload).await;
+        let result = call(self.origin, self.settings, self.function, self.payload).await;
         let elapsed = start.elapsed();
 
         let result = match self.tracker.lock() {
             Ok(mut tracker) => {
-                if self.measure_latency {
+                if self.settings.measure_latency {
                     tracker.latency(self.origin, elapsed);
                 }
 
-                if self.measure_errors && result.is_err() {
+                if self.settings.measure_errors && result.is_err() {
                     tracker.error(self.origin);
                 }
 
@@ -483,6 +1455,7 @@ where
         MigrationOriginResult {
             origin: self.origin,
             result,
+            fallback: false,
         }
     }
 }
@@ -495,7 +1468,10 @@ mod tests {
     };
 
     use crate::{
-        migrations::migrator::MigratorBuilder, Client, ConfigBuilder, ExecutionOrder, Stage,
+        migrations::migrator::{
+            DynMigrator, DynMigratorWithTypes, MigrationFn, MigratorBuildError, MigratorBuilder,
+        },
+        Client, ConfigBuilder, ExecutionOrder, Origin, Stage,
     };
     use futures::future::FutureExt;
     use launchdarkly_server_sdk_evaluation::ContextBuilder;
@@ -539,11 +1515,11 @@ mod tests {
         let (sender, receiver) = mpsc::channel();
         let old_sender = sender.clone();
         let new_sender = sender.clone();
-        let mut migrator = MigratorBuilder::new(client)
+        let migrator = MigratorBuilder::new(client)
             .track_latency(false)
             .track_errors(false)
             .write(
-                |_| async move { Ok(0) }.boxed(),
+                |_: &i32| async move { Ok(0) }.boxed(),
                 |_| async move { Ok(0) }.boxed(),
             )
             .read_execution_order(ExecutionOrder::Serial)
@@ -600,11 +1576,11 @@ mod tests {
         let (sender, receiver) = mpsc::channel();
         let old_sender = sender.clone();
         let new_sender = sender.clone();
-        let mut migrator = MigratorBuilder::new(client)
+        let migrator = MigratorBuilder::new(client)
             .track_latency(false)
             .track_errors(false)
             .read(
-                |_| async move { Ok(0) }.boxed(),
+                |_: &i32| async move { Ok(0) }.boxed(),
                 |_| async move { Ok(0) }.boxed(),
                 Some(|_, _| true),
             )
@@ -673,11 +1649,11 @@ mod tests {
         let (sender, receiver) = mpsc::channel();
         let old_sender = sender.clone();
         let new_sender = sender.clone();
-        let mut migrator = MigratorBuilder::new(client)
+        let migrator = MigratorBuilder::new(client)
             .track_latency(false)
             .track_errors(false)
             .write(
-                |_| async move { Ok("write") }.boxed(),
+                |_: &&str| async move { Ok("write") }.boxed(),
                 |_| async move { Ok("write") }.boxed(),
             )
             .read_execution_order(ExecutionOrder::Serial)
@@ -739,11 +1715,11 @@ mod tests {
         let client = Arc::new(Client::build(config).expect("client failed to build"));
         client.start_with_default_executor();
 
-        let mut migrator = MigratorBuilder::new(client)
+        let migrator = MigratorBuilder::new(client)
             .track_latency(false)
             .track_errors(false)
             .write(
-                |_| async move { Ok(()) }.boxed(),
+                |_: &()| async move { Ok(()) }.boxed(),
                 |_| async move { Ok(()) }.boxed(),
             )
             .read_execution_order(ExecutionOrder::Concurrent)
@@ -797,11 +1773,11 @@ mod tests {
         let client = Arc::new(Client::build(config).expect("client failed to build"));
         client.start_with_default_executor();
 
-        let mut migrator = MigratorBuilder::new(client)
+        let migrator = MigratorBuilder::new(client)
             .track_latency(false)
             .track_errors(false)
             .write(
-                |_| async move { Ok(()) }.boxed(),
+                |_: &()| async move { Ok(()) }.boxed(),
                 |_| async move { Ok(()) }.boxed(),
             )
             .read_execution_order(execution_order)
@@ -866,11 +1842,11 @@ mod tests {
         let (sender, receiver) = mpsc::channel();
         let old_sender = sender.clone();
         let new_sender = sender.clone();
-        let mut migrator = MigratorBuilder::new(client)
+        let migrator = MigratorBuilder::new(client)
             .track_latency(false)
             .track_errors(false)
             .read(
-                |_| async move { Ok(()) }.boxed(),
+                |_: &()| async move { Ok(()) }.boxed(),
                 |_| async move { Ok(()) }.boxed(),
                 Some(|_, _| true),
             )
@@ -951,11 +1927,11 @@ mod tests {
         let (sender, receiver) = mpsc::channel();
         let old_sender = sender.clone();
         let new_sender = sender.clone();
-        let mut migrator = MigratorBuilder::new(client)
+        let migrator = MigratorBuilder::new(client)
             .track_latency(false)
             .track_errors(false)
             .read(
-                |_| async move { Ok(()) }.boxed(),
+                |_: &()| async move { Ok(()) }.boxed(),
                 |_| async move { Ok(()) }.boxed(),
                 Some(|_, _| true),
             )
@@ -964,7 +1940,7 @@ mod tests {
                     let old_sender = old_sender.clone();
                     async move {
                         old_sender.send("old").unwrap();
-                        Err("error".into())
+                        Err::<(), _>("error".into())
                     }
                     .boxed()
                 },
@@ -1033,4 +2009,228 @@ mod tests {
 
         assert!(migrator.is_ok());
     }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn clones_can_be_used_concurrently() {
+        let config = ConfigBuilder::new("sdk-key")
+            .offline(true)
+            .build()
+            .expect("config failed to build");
+
+        let client = Arc::new(Client::build(config).expect("client failed to build"));
+        client.start_with_default_executor();
+
+        let migrator = MigratorBuilder::new(client)
+            .track_latency(false)
+            .track_errors(false)
+            .read(
+                |&payload: &u32| async move { Ok(payload) }.boxed(),
+                |&payload: &u32| async move { Ok(payload) }.boxed(),
+                Some(|a, b| a == b),
+            )
+            .write(
+                |&payload: &u32| async move { Ok(payload) }.boxed(),
+                |&payload: &u32| async move { Ok(payload) }.boxed(),
+            )
+            .build()
+            .expect("migrator failed to build");
+
+        let handles = (0..10)
+            .map(|payload| {
+                let migrator = migrator.clone();
+                tokio::spawn(async move {
+                    let context = ContextBuilder::new("user-key")
+                        .build()
+                        .expect("context failed to build");
+                    let read = migrator
+                        .read(&context, "migration-key".into(), Stage::Shadow, payload)
+                        .await;
+                    let write = migrator
+                        .write(&context, "migration-key".into(), Stage::Live, payload)
+                        .await;
+                    (read.result, write.authoritative.result)
+                })
+            })
+            .collect::<Vec<_>>();
+
+        for (payload, handle) in handles.into_iter().enumerate() {
+            let payload = payload as u32;
+            assert_eq!((Ok(payload), Ok(payload)), handle.await.unwrap());
+        }
+    }
+
+    #[tokio::test]
+    async fn dyn_migrator_can_be_stored_by_payload_and_result_types() {
+        struct AppState {
+            migrator: DynMigrator<String, usize>,
+        }
+
+        let config = ConfigBuilder::new("sdk-key")
+            .offline(true)
+            .build()
+            .expect("config failed to build");
+
+        let client = Arc::new(Client::build(config).expect("client failed to build"));
+        client.start_with_default_executor();
+
+        let state = AppState {
+            migrator: MigratorBuilder::new(client)
+                .track_latency(false)
+                .track_errors(false)
+                .read(
+                    |payload: &String| async move { Ok(payload.len()) }.boxed(),
+                    |payload: &String| async move { Ok(payload.len() * 2) }.boxed(),
+                    None,
+                )
+                .write(
+                    |_: &String| async move { Ok(0) }.boxed(),
+                    |_: &String| async move { Ok(0) }.boxed(),
+                )
+                .build_dyn()
+                .expect("migrator failed to build"),
+        };
+
+        let result = state
+            .migrator
+            .read(
+                &ContextBuilder::new("user-key")
+                    .build()
+                    .expect("context failed to build"),
+                "migration-key".into(),
+                Stage::Complete,
+                "payload".to_string(),
+            )
+            .await;
+
+        assert_eq!(Origin::New, result.origin);
+        assert_eq!(Ok(14), result.result);
+    }
+
+    #[test]
+    fn build_reports_missing_configuration() {
+        let config = ConfigBuilder::new("sdk-key")
+            .offline(true)
+            .build()
+            .expect("config failed to build");
+
+        let client = Arc::new(Client::build(config).expect("client failed to build"));
+        type OriginFn = MigrationFn<u32, ()>;
+        let result = MigratorBuilder::<_, _, OriginFn, OriginFn, _, _, _, _>::new(client.clone())
+            .write(
+                |_: &u32| async move { Ok(()) }.boxed(),
+                |_: &u32| async move { Ok(()) }.boxed(),
+            )
+            .build_dyn();
+        assert_eq!(Some(MigratorBuildError::MissingReadConfig), result.err());
+
+        let result = MigratorBuilder::<_, _, _, _, OriginFn, OriginFn, u32, ()>::new(client)
+            .read(
+                |_: &u32| async move { Ok(()) }.boxed(),
+                |_: &u32| async move { Ok(()) }.boxed(),
+                None,
+            )
+            .build_dyn();
+        assert_eq!(Some(MigratorBuildError::MissingWriteConfig), result.err());
+    }
+
+    #[test]
+    fn build_requires_a_runtime_for_timeouts() {
+        let config = ConfigBuilder::new("sdk-key")
+            .offline(true)
+            .build()
+            .expect("config failed to build");
+
+        let client = Arc::new(Client::build(config).expect("client failed to build"));
+        let builder = || {
+            MigratorBuilder::new(client.clone())
+                .timeout(Origin::New, std::time::Duration::from_millis(10))
+                .read(
+                    |_: &u32| async move { Ok(()) }.boxed(),
+                    |_: &u32| async move { Ok(()) }.boxed(),
+                    None,
+                )
+                .write(
+                    |_: &u32| async move { Ok(()) }.boxed(),
+                    |_: &u32| async move { Ok(()) }.boxed(),
+                )
+        };
+        assert_eq!(
+            Some(MigratorBuildError::TimeoutRequiresRuntime),
+            builder().build().err()
+        );
+
+        let runtime = tokio::runtime::Runtime::new().expect("runtime failed to build");
+        let _guard = runtime.enter();
+        assert!(builder().build().is_ok());
+    }
+
+    #[tokio::test]
+    async fn reads_and_writes_can_use_distinct_types() {
+        let config = ConfigBuilder::new("sdk-key")
+            .offline(true)
+            .build()
+            .expect("config failed to build");
+
+        let client = Arc::new(Client::build(config).expect("client failed to build"));
+        client.start_with_default_executor();
+
+        let (sender, receiver) = mpsc::channel();
+        let old_sender = sender.clone();
+        let new_sender = sender.clone();
+
+        // Reads look records up by ID, while writes store a record and return its ID.
+        let migrator: DynMigratorWithTypes<u64, String, String, u64> = MigratorBuilder::new(client)
+            .track_latency(false)
+            .track_errors(false)
+            .read(
+                |&id: &u64| async move { Ok(format!("record-{id}")) }.boxed(),
+                |&id: &u64| async move { Ok(format!("record-{id}")) }.boxed(),
+                Some(|a: &String, b: &String| a == b),
+            )
+            .write_with_types(
+                move |record: &String| {
+                    old_sender.send((Origin::Old, record.clone())).unwrap();
+                    async move { Ok(1) }.boxed()
+                },
+                move |record: &String| {
+                    new_sender.send((Origin::New, record.clone())).unwrap();
+                    async move { Ok(2) }.boxed()
+                },
+            )
+            .build_dyn()
+            .expect("migrator failed to build");
+
+        let context = ContextBuilder::new("user-key")
+            .build()
+            .expect("context failed to build");
+
+        let read = migrator
+            .read(&context, "migration-key".into(), Stage::Live, 7)
+            .await;
+        assert_eq!(Origin::New, read.origin);
+        assert_eq!(Ok("record-7".to_string()), read.result);
+
+        let write = migrator
+            .write(
+                &context,
+                "migration-key".into(),
+                Stage::Live,
+                "record-7".to_string(),
+            )
+            .await;
+        assert_eq!(Ok(2), write.authoritative.result);
+        assert_eq!(
+            Some(Ok(1)),
+            write.nonauthoritative.map(|result| result.result)
+        );
+
+        let writes = receiver.try_iter().collect::<Vec<_>>();
+        assert_eq!(
+            vec![
+                (Origin::New, "record-7".to_string()),
+                (Origin::Old, "record-7".to_string())
+            ],
+            writes
+        );
+    }
 }
@@ -103,9 +103,33 @@ pub enum ExecutionOrder {
     Concurrent,
 }
 
+#[non_exhaustive]
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+/// ReadFallback determines what is returned from a read executed against both origins when the
+/// authoritative origin fails.
+pub enum ReadFallback {
+    /// Disabled returns the authoritative origin's error, regardless of the non-authoritative
+    /// result.
+    #[default]
+    Disabled,
+    /// NonAuthoritative returns the non-authoritative origin's result instead of the
+    /// authoritative origin's error, provided that the non-authoritative read succeeded.
+    NonAuthoritative,
+}
+
+pub use blocking::BlockingMigrationFn;
+pub use blocking::BlockingMigrator;
+pub use blocking::BlockingMigratorBuilder;
+pub use migrator::FailedWrite;
+pub use migrator::MigrationFn;
+pub use migrator::MigrationMismatch;
 pub use migrator::Migrator;
+pub use migrator::MigratorBuildError;
 pub use migrator::MigratorBuilder;
+pub use migrator::WriteRetryPolicy;
+pub use migrator::{DynMigrator, DynMigratorWithTypes};
 pub use tracker::MigrationOpTracker;
 
+mod blocking;
 mod migrator;
 mod tracker;
@@ -78,9 +78,17 @@ impl MigrationOpTracker {
     /// A callable is provided in case sampling rules do not require consistency checking to run.
     /// In this case, we can avoid the overhead of a function by not using the callable.
     pub fn consistent(&mut self, is_consistent: impl Fn() -> bool) {
+        self.check_consistency(is_consistent);
+    }
+
+    // Records the consistency check as [MigrationOpTracker::consistent] does, returning its
+    // result, or None if sampling rules skipped it.
+    pub(crate) fn check_consistency(&mut self, is_consistent: impl Fn() -> bool) -> Option<bool> {
         if ThreadRngSampler::new(rng()).sample(self.consistent_ratio.unwrap_or(1)) {
             self.consistent = Some(is_consistent());
+            return self.consistent;
         }
+        None
     }
 
     /// Allows recording which origins were called during a migration.
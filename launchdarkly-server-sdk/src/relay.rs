@@ -0,0 +1,691 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, HashMap};
+use std::convert::Infallible;
+use std::future::Future;
+use std::hash::{Hash, Hasher};
+use std::net::TcpListener;
+use std::sync::Arc;
+use std::time::Duration;
+
+use hyper::body::Bytes;
+use hyper::header::{AUTHORIZATION, CACHE_CONTROL, CONTENT_TYPE, ETAG, IF_NONE_MATCH};
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Method, Request, Response, Server, StatusCode};
+use launchdarkly_server_sdk_evaluation::{Flag, Segment, Versioned};
+use parking_lot::{Mutex, RwLock};
+use serde::Serialize;
+use serde_json::Value;
+use thiserror::Error;
+use tokio::sync::broadcast;
+
+use crate::client::InitializedHandle;
+use crate::stores::store::DataStore;
+use crate::stores::store_types::{AllData, StorageItem};
+use crate::Client;
+
+const DEFAULT_UPDATE_INTERVAL: Duration = Duration::from_secs(1);
+// LaunchDarkly sends keepalives every 3 minutes, and the SDK's stream times out after 5.
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(180);
+const UPDATE_CAPACITY: usize = 1000;
+
+type Snapshot = AllData<StorageItem<Flag>, StorageItem<Segment>>;
+
+/// Error type used to represent failures when running a [RelayServer].
+#[non_exhaustive]
+#[derive(Debug, Error)]
+pub enum RelayServerError {
+    /// Error used when the client's data store does not hold its data in memory, such as a
+    /// persistent store, and so cannot be re-served.
+    #[error("the client's data store does not support re-serving its data")]
+    UnsupportedStore,
+    /// Error used when the listener cannot be used by the server.
+    #[error("failed to listen: {0}")]
+    Io(#[from] std::io::Error),
+    /// Error used when the server fails while running.
+    #[error("server failed: {0}")]
+    Server(#[from] hyper::Error),
+}
+
+/// Contains methods for configuring a [RelayServer].
+///
+/// # Examples
+///
+/// ```no_run
+/// # use launchdarkly_server_sdk::{Client, ConfigBuilder, RelayServerBuilder};
+/// # use std::net::TcpListener;
+/// # #[tokio::main]
+/// # async fn main() -> Result<(), Box<dyn std::error::Error>> {
+///     let client = Client::build(ConfigBuilder::new("sdk-key").build()?)?;
+///     client.start_with_default_executor();
+///
+///     let server = RelayServerBuilder::new().build(&client);
+///     let listener = TcpListener::bind("0.0.0.0:8030")?;
+///     server.serve(listener, std::future::pending()).await?;
+/// #   Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct RelayServerBuilder {
+    sdk_key: Option<String>,
+    update_interval: Duration,
+}
+
+impl RelayServerBuilder {
+    /// Create a new instance of the [RelayServerBuilder] with default values.
+    pub fn new() -> Self {
+        Self {
+            sdk_key: None,
+            update_interval: DEFAULT_UPDATE_INTERVAL,
+        }
+    }
+
+    /// Sets the SDK key which other SDKs must send in their `Authorization` header to be served.
+    ///
+    /// The default is the SDK key of the client whose data is served.
+    pub fn sdk_key(&mut self, sdk_key: &str) -> &mut Self {
+        self.sdk_key = Some(sdk_key.to_string());
+        self
+    }
+
+    /// Sets how often the client's store is checked for changes to stream to connected SDKs.
+    ///
+    /// The default is one second.
+    pub fn update_interval(&mut self, update_interval: Duration) -> &mut Self {
+        self.update_interval = update_interval;
+        self
+    }
+
+    /// Create a server which re-serves the flag data held by the client.
+    pub fn build(&self, client: &Client) -> RelayServer {
+        RelayServer {
+            store: client.data_store.clone(),
+            initialized: client.initialized_handle(),
+            sdk_key: self
+                .sdk_key
+                .clone()
+                .unwrap_or_else(|| client.sdk_key.clone()),
+            update_interval: self.update_interval,
+        }
+    }
+}
+
+impl Default for RelayServerBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// An HTTP server which re-serves a client's flag data to other SDK instances, so that only the
+/// client needs a connection to LaunchDarkly.
+///
+/// The server exposes the same endpoints as LaunchDarkly's Relay Proxy:
+///
+/// - `GET /sdk/latest-all`, used by the polling data source. Responses carry an `ETag`, and
+///   requests with a matching `If-None-Match` header receive `304 Not Modified`.
+/// - `GET /all`, used by the streaming data source. Each connection receives a `put` event with
+///   every flag and segment, followed by `patch` and `delete` events as the client's store
+///   changes.
+///
+/// Other SDK instances can use the server by passing its URL to
+/// [crate::ServiceEndpointsBuilder::relay_proxy].
+///
+/// Requests must carry the SDK key set with [RelayServerBuilder::sdk_key] in their
+/// `Authorization` header, as SDKs do when connecting to LaunchDarkly, or they receive
+/// `401 Unauthorized`.
+///
+/// Both endpoints respond with `503 Service Unavailable` until the client has initialized, so
+/// that SDKs do not initialize from an empty store.
+///
+/// The server requires the client to use the default in-memory data store.
+pub struct RelayServer {
+    store: Arc<RwLock<dyn DataStore>>,
+    initialized: InitializedHandle,
+    sdk_key: String,
+    update_interval: Duration,
+}
+
+impl RelayServer {
+    /// Serves requests on the listener until `shutdown` completes, which also closes every open
+    /// stream. This must be called from within a tokio runtime.
+    pub async fn serve(
+        &self,
+        listener: TcpListener,
+        shutdown: impl Future<Output = ()>,
+    ) -> Result<(), RelayServerError> {
+        if self.store.read().all_data().is_none() {
+            return Err(RelayServerError::UnsupportedStore);
+        }
+        listener.set_nonblocking(true)?;
+
+        let (updates, _) = broadcast::channel(UPDATE_CAPACITY);
+        let state = Arc::new(State {
+            store: self.store.clone(),
+            initialized: self.initialized.clone(),
+            sdk_key: self.sdk_key.clone(),
+            updates: RwLock::new(Some(updates)),
+            payload: Mutex::new(None),
+        });
+        let watcher = tokio::spawn(watch_store(state.clone(), self.update_interval));
+
+        let service_state = state.clone();
+        let make_service = make_service_fn(move |_| {
+            let state = service_state.clone();
+            async move {
+                Ok::<_, Infallible>(service_fn(move |request| {
+                    let state = state.clone();
+                    async move { Ok::<_, Infallible>(state.handle(&request)) }
+                }))
+            }
+        });
+
+        let result = Server::from_tcp(listener)?
+            .serve(make_service)
+            .with_graceful_shutdown(async {
+                shutdown.await;
+                // Dropping the only sender ends every open stream, so that shutdown can complete.
+                state.updates.write().take();
+            })
+            .await;
+        watcher.abort();
+
+        Ok(result?)
+    }
+}
+
+struct State {
+    store: Arc<RwLock<dyn DataStore>>,
+    initialized: InitializedHandle,
+    sdk_key: String,
+    updates: RwLock<Option<broadcast::Sender<Arc<str>>>>,
+    // The store's live data serialized at the store version it was read from.
+    payload: Mutex<Option<(u64, Arc<Payload>)>>,
+}
+
+struct Payload {
+    body: Bytes,
+    etag: String,
+}
+
+impl State {
+    // Until the client has initialized, its store may be empty or partially populated.
+    fn snapshot(&self) -> Option<Snapshot> {
+        if !self.initialized.initialized() {
+            return None;
+        }
+        self.store.read().all_data()
+    }
+
+    fn version(&self) -> Option<u64> {
+        self.store.read().version()
+    }
+
+    // Serializing the store is only repeated once it has changed.
+    fn payload(&self) -> Option<Arc<Payload>> {
+        if !self.initialized.initialized() {
+            return None;
+        }
+
+        let (version, snapshot) = {
+            let store = self.store.read();
+            let version = store.version();
+            if let (Some(version), Some((cached, payload))) = (version, &*self.payload.lock()) {
+                if version == *cached {
+                    return Some(payload.clone());
+                }
+            }
+            (version, store.all_data()?)
+        };
+
+        let body = live_data(&snapshot).to_string();
+        let payload = Arc::new(Payload {
+            etag: etag(&body),
+            body: Bytes::from(body),
+        });
+        if let Some(version) = version {
+            *self.payload.lock() = Some((version, payload.clone()));
+        }
+        Some(payload)
+    }
+
+    fn handle(&self, request: &Request<Body>) -> Response<Body> {
+        let authorized = request
+            .headers()
+            .get(AUTHORIZATION)
+            .is_some_and(|key| key.as_bytes() == self.sdk_key.as_bytes());
+
+        match (request.method(), request.uri().path()) {
+            (&Method::GET, "/sdk/latest-all" | "/all") if !authorized => {
+                status(StatusCode::UNAUTHORIZED)
+            }
+            (&Method::GET, "/sdk/latest-all") => self.poll(request),
+            (&Method::GET, "/all") => self.stream(),
+            _ => status(StatusCode::NOT_FOUND),
+        }
+    }
+
+    fn poll(&self, request: &Request<Body>) -> Response<Body> {
+        let payload = match self.payload() {
+            Some(payload) => payload,
+            None => return status(StatusCode::SERVICE_UNAVAILABLE),
+        };
+
+        let not_modified = request
+            .headers()
+            .get(IF_NONE_MATCH)
+            .is_some_and(|tag| tag.as_bytes() == payload.etag.as_bytes());
+
+        let response = Response::builder().header(ETAG, payload.etag.as_str());
+        if not_modified {
+            response
+                .status(StatusCode::NOT_MODIFIED)
+                .body(Body::empty())
+        } else {
+            response
+                .header(CONTENT_TYPE, "application/json")
+                .body(Body::from(payload.body.clone()))
+        }
+        .expect("response headers are valid")
+    }
+
+    fn stream(&self) -> Response<Body> {
+        // Subscribe before taking the snapshot so that no change is missed; a change seen in both
+        // is harmless, as SDKs ignore updates which are not newer than what they hold.
+        let mut updates = match &*self.updates.read() {
+            Some(updates) => updates.subscribe(),
+            None => return status(StatusCode::SERVICE_UNAVAILABLE),
+        };
+        let payload = match self.payload() {
+            Some(payload) => payload,
+            None => return status(StatusCode::SERVICE_UNAVAILABLE),
+        };
+
+        let (mut sender, body) = Body::channel();
+        tokio::spawn(async move {
+            let put = [
+                &b"event: put\ndata: {\"path\":\"/\",\"data\":"[..],
+                &payload.body,
+                b"}\n\n",
+            ]
+            .concat();
+            if sender.send_data(Bytes::from(put)).await.is_err() {
+                return;
+            }
+
+            loop {
+                let data = match tokio::time::timeout(HEARTBEAT_INTERVAL, updates.recv()).await {
+                    Err(_) => Bytes::from_static(b":\n\n"),
+                    Ok(Ok(event)) => Bytes::copy_from_slice(event.as_bytes()),
+                    // Streams which fall behind or are shut down are closed. SDKs reconnect and
+                    // receive a new put when they fall behind.
+                    Ok(Err(_)) => return,
+                };
+                if sender.send_data(data).await.is_err() {
+                    return;
+                }
+            }
+        });
+
+        Response::builder()
+            .header(CONTENT_TYPE, "text/event-stream")
+            .header(CACHE_CONTROL, "no-cache")
+            .body(body)
+            .expect("response headers are valid")
+    }
+}
+
+// Compares the store against its previous contents at each interval in which it has changed,
+// broadcasting the changes to open streams.
+async fn watch_store(state: Arc<State>, update_interval: Duration) {
+    let mut previous_version = state.version();
+    let mut previous = state.snapshot();
+    loop {
+        tokio::time::sleep(update_interval).await;
+
+        let updates = match &*state.updates.read() {
+            Some(updates) => updates.clone(),
+            None => return,
+        };
+        // A snapshot taken while there are no streams predates every stream opened later, so it
+        // can be kept until there are streams to compare for.
+        if previous.is_some() && updates.receiver_count() == 0 {
+            continue;
+        }
+        let version = state.version();
+        if previous.is_some() && version.is_some() && version == previous_version {
+            continue;
+        }
+
+        let current = state.snapshot();
+        let events = match (&previous, &current) {
+            (Some(previous), Some(current)) => {
+                let mut events = diff("flags", &previous.flags, &current.flags);
+                events.extend(diff("segments", &previous.segments, &current.segments));
+                events
+            }
+            // Streams which opened before the first snapshot may have missed changes since their
+            // own, so they receive every item.
+            (None, Some(current)) if updates.receiver_count() > 0 => {
+                let mut events = diff("flags", &HashMap::new(), &current.flags);
+                events.extend(diff("segments", &HashMap::new(), &current.segments));
+                events
+            }
+            _ => Vec::new(),
+        };
+        previous_version = version;
+        previous = current;
+
+        for event in events {
+            // Sending only fails when every stream has closed since they were counted.
+            let _ = updates.send(Arc::from(event));
+        }
+    }
+}
+
+fn diff<T: Serialize + Versioned>(
+    kind: &str,
+    previous: &HashMap<String, StorageItem<T>>,
+    current: &HashMap<String, StorageItem<T>>,
+) -> Vec<String> {
+    let mut events = Vec::new();
+    for (key, item) in current {
+        if previous
+            .get(key)
+            .is_some_and(|previous| previous.version() == item.version())
+        {
+            continue;
+        }
+        let path = format!("/{kind}/{key}");
+        events.push(match item {
+            StorageItem::Item(data) => {
+                sse_event("patch", &serde_json::json!({ "path": path, "data": data }))
+            }
+            StorageItem::Tombstone(version) => sse_event(
+                "delete",
+                &serde_json::json!({ "path": path, "version": version }),
+            ),
+        });
+    }
+
+    // Items can also disappear without leaving a placeholder, such as when the store is
+    // reinitialized, so these are deleted at the next version.
+    for (key, item) in previous {
+        if let (false, StorageItem::Item(_)) = (current.contains_key(key), item) {
+            events.push(sse_event(
+                "delete",
+                &serde_json::json!({ "path": format!("/{kind}/{key}"), "version": item.version() + 1 }),
+            ));
+        }
+    }
+
+    events
+}
+
+// Items are ordered by key so that identical data always produces the same ETag.
+fn live_data(snapshot: &Snapshot) -> Value {
+    fn live<T: Serialize>(items: &HashMap<String, StorageItem<T>>) -> BTreeMap<&str, &T> {
+        items
+            .iter()
+            .filter_map(|(key, item)| match item {
+                StorageItem::Item(data) => Some((key.as_str(), data)),
+                StorageItem::Tombstone(_) => None,
+            })
+            .collect()
+    }
+
+    serde_json::json!({
+        "flags": live(&snapshot.flags),
+        "segments": live(&snapshot.segments),
+    })
+}
+
+fn etag(body: &str) -> String {
+    let mut hasher = DefaultHasher::new();
+    body.hash(&mut hasher);
+    format!("\"{:016x}\"", hasher.finish())
+}
+
+fn sse_event(event: &str, data: &Value) -> String {
+    format!("event: {event}\ndata: {data}\n\n")
+}
+
+fn status(status: StatusCode) -> Response<Body> {
+    Response::builder()
+        .status(status)
+        .body(Body::empty())
+        .expect("response headers are valid")
+}
+
+#[cfg(test)]
+mod tests {
+    use hyper::client::HttpConnector;
+    use launchdarkly_server_sdk_evaluation::{self as eval, ContextBuilder, FlagValue, Reason};
+    use test_case::test_case;
+    use tokio::sync::oneshot;
+
+    use super::*;
+    use crate::data_source::MockDataSource;
+    use crate::data_source_builders::MockDataSourceBuilder;
+    use crate::stores::store_types::PatchTarget;
+    use crate::test_common::basic_flag;
+    use crate::{
+        ConfigBuilder, NullEventProcessorBuilder, ServiceEndpointsBuilder,
+        StreamingDataSourceBuilder,
+    };
+
+    fn upstream_client() -> Client {
+        let updates = Arc::new(MockDataSource::new_with_init_delay(0));
+        let config = ConfigBuilder::new("sdk-key")
+            .data_source(MockDataSourceBuilder::new().data_source(updates))
+            .event_processor(&NullEventProcessorBuilder::new())
+            .build()
+            .expect("config should build");
+        let client = Client::build(config).expect("client should build");
+        client.start_with_default_executor();
+        upsert(&client, "myFlag", StorageItem::Item(basic_flag("myFlag")));
+        client
+    }
+
+    fn upsert(client: &Client, key: &str, item: StorageItem<Flag>) {
+        client
+            .data_store
+            .write()
+            .upsert(key, PatchTarget::Flag(item))
+            .expect("patch should apply");
+    }
+
+    fn start(client: &Client) -> (String, oneshot::Sender<()>) {
+        let mut builder = RelayServerBuilder::new();
+        builder.update_interval(Duration::from_millis(10));
+        serve(builder.build(client))
+    }
+
+    fn serve(server: RelayServer) -> (String, oneshot::Sender<()>) {
+        let listener = TcpListener::bind("127.0.0.1:0").expect("listener should bind");
+        let url = format!("http://{}", listener.local_addr().expect("address"));
+        let (shutdown_tx, shutdown_rx) = oneshot::channel::<()>();
+
+        tokio::spawn(async move {
+            server
+                .serve(listener, async {
+                    let _ = shutdown_rx.await;
+                })
+                .await
+                .expect("server should run");
+        });
+
+        (url, shutdown_tx)
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn polling_endpoint_serves_the_store_with_etags() {
+        let client = upstream_client();
+        let (url, shutdown) = start(&client);
+        let http = reqwest::Client::new();
+
+        let response = http
+            .get(format!("{url}/sdk/latest-all"))
+            .header("Authorization", "sdk-key")
+            .send()
+            .await
+            .expect("request should succeed");
+        assert_eq!(200, response.status().as_u16());
+        let etag = response.headers()["etag"].clone();
+        let body: Value = response.json().await.expect("body should be JSON");
+        assert_eq!("myFlag", body["flags"]["myFlag"]["key"]);
+        assert_eq!(serde_json::json!({}), body["segments"]);
+
+        let response = http
+            .get(format!("{url}/sdk/latest-all"))
+            .header("Authorization", "sdk-key")
+            .header("If-None-Match", etag.clone())
+            .send()
+            .await
+            .expect("request should succeed");
+        assert_eq!(304, response.status().as_u16());
+
+        let mut flag = basic_flag("myFlag");
+        flag.version += 1;
+        upsert(&client, "myFlag", StorageItem::Item(flag));
+        let response = http
+            .get(format!("{url}/sdk/latest-all"))
+            .header("Authorization", "sdk-key")
+            .header("If-None-Match", etag)
+            .send()
+            .await
+            .expect("request should succeed");
+        assert_eq!(200, response.status().as_u16());
+
+        let response = http
+            .get(format!("{url}/unknown"))
+            .send()
+            .await
+            .expect("request should succeed");
+        assert_eq!(404, response.status().as_u16());
+
+        let _ = shutdown.send(());
+    }
+
+    #[test_case(None, None, 401)]
+    #[test_case(None, Some("wrong-key"), 401)]
+    #[test_case(None, Some("sdk-key"), 200)]
+    #[test_case(Some("relay-key"), Some("sdk-key"), 401)]
+    #[test_case(Some("relay-key"), Some("relay-key"), 200)]
+    #[tokio::test(flavor = "multi_thread")]
+    async fn endpoints_require_the_sdk_key(
+        configured: Option<&str>,
+        sent: Option<&str>,
+        expected: u16,
+    ) {
+        let client = upstream_client();
+        let mut builder = RelayServerBuilder::new();
+        if let Some(sdk_key) = configured {
+            builder.sdk_key(sdk_key);
+        }
+        let (url, shutdown) = serve(builder.build(&client));
+        let http = reqwest::Client::new();
+
+        for path in ["/sdk/latest-all", "/all"] {
+            let mut request = http.get(format!("{url}{path}"));
+            if let Some(sdk_key) = sent {
+                request = request.header("Authorization", sdk_key);
+            }
+            let response = request.send().await.expect("request should succeed");
+            assert_eq!(expected, response.status().as_u16(), "{path}");
+        }
+
+        let _ = shutdown.send(());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn endpoints_are_unavailable_until_the_client_initializes() {
+        let updates = Arc::new(MockDataSource::new_with_init_delay(0));
+        let config = ConfigBuilder::new("sdk-key")
+            .data_source(MockDataSourceBuilder::new().data_source(updates))
+            .event_processor(&NullEventProcessorBuilder::new())
+            .build()
+            .expect("config should build");
+        let client = Client::build(config).expect("client should build");
+        upsert(&client, "myFlag", StorageItem::Item(basic_flag("myFlag")));
+        let (url, shutdown) = start(&client);
+        let http = reqwest::Client::new();
+
+        for path in ["/sdk/latest-all", "/all"] {
+            let response = http
+                .get(format!("{url}{path}"))
+                .header("Authorization", "sdk-key")
+                .send()
+                .await
+                .expect("request should succeed");
+            assert_eq!(503, response.status().as_u16(), "{path}");
+        }
+
+        client.start_with_default_executor();
+        assert_eq!(
+            Some(true),
+            client.wait_for_initialization(Duration::from_secs(5)).await
+        );
+        let response = http
+            .get(format!("{url}/sdk/latest-all"))
+            .header("Authorization", "sdk-key")
+            .send()
+            .await
+            .expect("request should succeed");
+        assert_eq!(200, response.status().as_u16());
+
+        let _ = shutdown.send(());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn streaming_endpoint_feeds_other_sdks() {
+        let upstream = upstream_client();
+        let (url, shutdown) = start(&upstream);
+
+        let mut data_source = StreamingDataSourceBuilder::<HttpConnector>::new();
+        data_source.https_connector(HttpConnector::new());
+        let config = ConfigBuilder::new("sdk-key")
+            .service_endpoints(ServiceEndpointsBuilder::new().relay_proxy(&url))
+            .data_source(&data_source)
+            .event_processor(&NullEventProcessorBuilder::new())
+            .build()
+            .expect("config should build");
+        let downstream = Client::build(config).expect("client should build");
+        downstream.start_with_default_executor();
+        assert_eq!(
+            Some(true),
+            downstream
+                .wait_for_initialization(Duration::from_secs(5))
+                .await
+        );
+
+        let context = ContextBuilder::new("bob")
+            .build()
+            .expect("Failed to create context");
+        assert!(downstream.bool_variation(&context, "myFlag", false));
+
+        upsert(
+            &upstream,
+            "newFlag",
+            StorageItem::Item(basic_flag("newFlag")),
+        );
+        upsert(&upstream, "myFlag", StorageItem::Tombstone(100));
+
+        let mut updated = false;
+        for _ in 0..200 {
+            let deleted = downstream.variation_detail(&context, "myFlag", FlagValue::Bool(false));
+            if downstream.bool_variation(&context, "newFlag", false)
+                && deleted.reason
+                    == (Reason::Error {
+                        error: eval::Error::FlagNotFound,
+                    })
+            {
+                updated = true;
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert!(updated, "downstream client should receive the changes");
+
+        downstream.close();
+        let _ = shutdown.send(());
+    }
+}
@@ -1,7 +1,14 @@
 pub mod persistent_store;
 pub mod persistent_store_builders;
 pub mod persistent_store_cache;
+#[cfg(feature = "store-testing")]
+pub mod persistent_store_conformance;
+pub mod persistent_store_encryption;
 pub mod persistent_store_wrapper;
+mod single_flight;
+#[cfg(feature = "sqlite")]
+pub mod sqlite_store;
 pub mod store;
 pub mod store_builders;
 pub mod store_types;
+mod tombstones;
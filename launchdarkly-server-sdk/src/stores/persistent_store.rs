@@ -1,6 +1,9 @@
 use core::fmt;
 use std::collections::HashMap;
 
+use futures::future::{self, BoxFuture, FutureExt};
+use parking_lot::RwLock;
+
 use super::store_types::{AllData, DataKind, SerializedItem};
 
 /// Error type used to represent failures when interacting with the underlying persistent stores.
@@ -91,15 +94,183 @@ pub trait PersistentDataStore: Send + Sync {
     /// In a shared data store, it should be able to detect this even if [PersistentDataStore::init] was called in a
     /// different process: that is, the test should be based on looking at what is in the data store.
     fn is_initialized(&self) -> bool;
+
+    /// Permanently removes the placeholder for a deleted item, but only if the store still holds
+    /// a placeholder with exactly the given version. Returns true if the placeholder was removed.
+    ///
+    /// The SDK calls this once a placeholder it wrote has outlived the tombstone retention
+    /// configured with [crate::PersistentDataStoreBuilder::tombstone_retention]. The check and the
+    /// removal should be done atomically, so that an item recreated or deleted again by another
+    /// process in the meantime is never removed.
+    ///
+    /// Implementing this method is optional. The default implementation keeps placeholders
+    /// forever and returns Ok(false).
+    fn remove_tombstone(
+        &mut self,
+        _kind: DataKind,
+        _key: &str,
+        _version: u64,
+    ) -> Result<bool, PersistentStoreError> {
+        Ok(false)
+    }
+}
+
+/// AsyncPersistentDataStore is the asynchronous counterpart of [PersistentDataStore].
+///
+/// Implement this interface for data stores whose clients perform network I/O, such as most
+/// database integrations. The semantics of every method are identical to the corresponding
+/// [PersistentDataStore] method; the only difference is that results are delivered through a
+/// future, so the SDK can await store reads (see [crate::Client::variation_async]) instead of
+/// blocking a runtime worker thread.
+///
+/// Unlike [PersistentDataStore], all methods take `&self`. Implementations are expected to
+/// handle concurrent calls, typically through a connection pool.
+pub trait AsyncPersistentDataStore: Send + Sync {
+    /// Overwrites the store's contents with a set of items for each collection.
+    ///
+    /// See [PersistentDataStore::init].
+    fn init(
+        &self,
+        all_data: AllData<SerializedItem, SerializedItem>,
+    ) -> BoxFuture<'_, Result<(), PersistentStoreError>>;
+
+    /// Retrieves a flag item from the specified collection, if available.
+    ///
+    /// See [PersistentDataStore::flag].
+    fn flag<'a>(
+        &'a self,
+        key: &'a str,
+    ) -> BoxFuture<'a, Result<Option<SerializedItem>, PersistentStoreError>>;
+
+    /// Retrieves a segment item from the specified collection, if available.
+    ///
+    /// See [PersistentDataStore::segment].
+    fn segment<'a>(
+        &'a self,
+        key: &'a str,
+    ) -> BoxFuture<'a, Result<Option<SerializedItem>, PersistentStoreError>>;
+
+    /// Retrieves all flag items from the specified collection.
+    ///
+    /// See [PersistentDataStore::all_flags].
+    fn all_flags(
+        &self,
+    ) -> BoxFuture<'_, Result<HashMap<String, SerializedItem>, PersistentStoreError>>;
+
+    /// Updates or inserts an item in the specified collection if its version is newer than the
+    /// existing version.
+    ///
+    /// See [PersistentDataStore::upsert].
+    fn upsert<'a>(
+        &'a self,
+        kind: DataKind,
+        key: &'a str,
+        serialized_item: SerializedItem,
+    ) -> BoxFuture<'a, Result<bool, PersistentStoreError>>;
+
+    /// Returns true if the data store contains a data set.
+    ///
+    /// See [PersistentDataStore::is_initialized].
+    fn is_initialized(&self) -> BoxFuture<'_, bool>;
+
+    /// Permanently removes the placeholder for a deleted item, if it is still at the given
+    /// version.
+    ///
+    /// See [PersistentDataStore::remove_tombstone]. The default implementation keeps placeholders
+    /// forever.
+    fn remove_tombstone<'a>(
+        &'a self,
+        _kind: DataKind,
+        _key: &'a str,
+        _version: u64,
+    ) -> BoxFuture<'a, Result<bool, PersistentStoreError>> {
+        future::ready(Ok(false)).boxed()
+    }
+}
+
+/// Adapts a synchronous [PersistentDataStore] to the [AsyncPersistentDataStore] interface.
+///
+/// Each operation runs to completion on the calling thread and returns an already resolved
+/// future, so the behavior of the wrapped store is unchanged.
+pub struct PersistentDataStoreAdapter {
+    store: RwLock<Box<dyn PersistentDataStore>>,
+}
+
+impl PersistentDataStoreAdapter {
+    /// Create a new adapter around the provided [PersistentDataStore].
+    pub fn new(store: Box<dyn PersistentDataStore>) -> Self {
+        Self {
+            store: RwLock::new(store),
+        }
+    }
+}
+
+impl AsyncPersistentDataStore for PersistentDataStoreAdapter {
+    fn init(
+        &self,
+        all_data: AllData<SerializedItem, SerializedItem>,
+    ) -> BoxFuture<'_, Result<(), PersistentStoreError>> {
+        future::ready(self.store.write().init(all_data)).boxed()
+    }
+
+    fn flag<'a>(
+        &'a self,
+        key: &'a str,
+    ) -> BoxFuture<'a, Result<Option<SerializedItem>, PersistentStoreError>> {
+        future::ready(self.store.read().flag(key)).boxed()
+    }
+
+    fn segment<'a>(
+        &'a self,
+        key: &'a str,
+    ) -> BoxFuture<'a, Result<Option<SerializedItem>, PersistentStoreError>> {
+        future::ready(self.store.read().segment(key)).boxed()
+    }
+
+    fn all_flags(
+        &self,
+    ) -> BoxFuture<'_, Result<HashMap<String, SerializedItem>, PersistentStoreError>> {
+        future::ready(self.store.read().all_flags()).boxed()
+    }
+
+    fn upsert<'a>(
+        &'a self,
+        kind: DataKind,
+        key: &'a str,
+        serialized_item: SerializedItem,
+    ) -> BoxFuture<'a, Result<bool, PersistentStoreError>> {
+        future::ready(self.store.write().upsert(kind, key, serialized_item)).boxed()
+    }
+
+    fn is_initialized(&self) -> BoxFuture<'_, bool> {
+        future::ready(self.store.read().is_initialized()).boxed()
+    }
+
+    fn remove_tombstone<'a>(
+        &'a self,
+        kind: DataKind,
+        key: &'a str,
+        version: u64,
+    ) -> BoxFuture<'a, Result<bool, PersistentStoreError>> {
+        future::ready(self.store.write().remove_tombstone(kind, key, version)).boxed()
+    }
 }
 
 #[cfg(test)]
 pub(crate) mod tests {
     use crate::stores::persistent_store::PersistentDataStore;
     use crate::stores::store_types::{AllData, DataKind, SerializedItem};
+    use futures::future::{BoxFuture, FutureExt};
     use std::collections::HashMap;
+    use std::future::Future;
+    use std::pin::Pin;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::task::{Context, Poll};
+
+    use parking_lot::Mutex;
 
-    use super::PersistentStoreError;
+    use super::{AsyncPersistentDataStore, PersistentDataStoreAdapter, PersistentStoreError};
 
     pub struct NullPersistentDataStore {
         pub(crate) initialized: bool,
@@ -184,5 +355,224 @@ pub(crate) mod tests {
         fn is_initialized(&self) -> bool {
             self.initialized
         }
+
+        fn remove_tombstone(
+            &mut self,
+            kind: DataKind,
+            key: &str,
+            version: u64,
+        ) -> Result<bool, PersistentStoreError> {
+            let items = match kind {
+                DataKind::Flag => &mut self.data.flags,
+                DataKind::Segment => &mut self.data.segments,
+            };
+            match items.get(key) {
+                Some(item) if item.deleted && item.version == version => {
+                    Ok(items.remove(key).is_some())
+                }
+                _ => Ok(false),
+            }
+        }
+    }
+
+    /// A [PersistentDataStore] whose clones share their data, so tests can change what the store
+    /// holds behind the back of the SDK, and which counts how many reads reach it.
+    #[derive(Clone)]
+    pub struct SharedPersistentDataStore {
+        store: Arc<Mutex<InMemoryPersistentDataStore>>,
+        reads: Arc<AtomicUsize>,
+    }
+
+    impl SharedPersistentDataStore {
+        pub fn new(data: AllData<SerializedItem, SerializedItem>) -> Self {
+            Self {
+                store: Arc::new(Mutex::new(InMemoryPersistentDataStore {
+                    data,
+                    initialized: true,
+                })),
+                reads: Arc::new(AtomicUsize::new(0)),
+            }
+        }
+
+        pub fn reads(&self) -> usize {
+            self.reads.load(Ordering::SeqCst)
+        }
+    }
+
+    impl PersistentDataStore for SharedPersistentDataStore {
+        fn init(
+            &mut self,
+            all_data: AllData<SerializedItem, SerializedItem>,
+        ) -> Result<(), PersistentStoreError> {
+            self.store.lock().init(all_data)
+        }
+
+        fn flag(&self, key: &str) -> Result<Option<SerializedItem>, PersistentStoreError> {
+            self.reads.fetch_add(1, Ordering::SeqCst);
+            self.store.lock().flag(key)
+        }
+
+        fn segment(&self, key: &str) -> Result<Option<SerializedItem>, PersistentStoreError> {
+            self.reads.fetch_add(1, Ordering::SeqCst);
+            self.store.lock().segment(key)
+        }
+
+        fn all_flags(&self) -> Result<HashMap<String, SerializedItem>, PersistentStoreError> {
+            self.reads.fetch_add(1, Ordering::SeqCst);
+            self.store.lock().all_flags()
+        }
+
+        fn upsert(
+            &mut self,
+            kind: DataKind,
+            key: &str,
+            serialized_item: SerializedItem,
+        ) -> Result<bool, PersistentStoreError> {
+            self.store.lock().upsert(kind, key, serialized_item)
+        }
+
+        fn is_initialized(&self) -> bool {
+            self.store.lock().is_initialized()
+        }
+
+        fn remove_tombstone(
+            &mut self,
+            kind: DataKind,
+            key: &str,
+            version: u64,
+        ) -> Result<bool, PersistentStoreError> {
+            self.store.lock().remove_tombstone(kind, key, version)
+        }
+    }
+
+    /// A future which returns pending once before completing, forcing callers through their
+    /// asynchronous code paths.
+    struct YieldOnce(bool);
+
+    impl Future for YieldOnce {
+        type Output = ();
+
+        fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+            if self.0 {
+                return Poll::Ready(());
+            }
+            self.0 = true;
+            cx.waker().wake_by_ref();
+            Poll::Pending
+        }
+    }
+
+    /// An [AsyncPersistentDataStore] which yields before every operation, so that none of its
+    /// futures complete on the first poll.
+    pub struct YieldingPersistentDataStore {
+        store: PersistentDataStoreAdapter,
+        sleep: Option<std::time::Duration>,
+    }
+
+    impl YieldingPersistentDataStore {
+        pub fn new(store: impl PersistentDataStore + 'static) -> Self {
+            Self {
+                store: PersistentDataStoreAdapter::new(Box::new(store)),
+                sleep: None,
+            }
+        }
+
+        /// Creates a store which waits on a tokio timer before every operation, so that its
+        /// futures need a runtime with a running time driver to complete.
+        pub fn sleeping(
+            store: impl PersistentDataStore + 'static,
+            sleep: std::time::Duration,
+        ) -> Self {
+            Self {
+                store: PersistentDataStoreAdapter::new(Box::new(store)),
+                sleep: Some(sleep),
+            }
+        }
+
+        async fn pause(&self) {
+            match self.sleep {
+                Some(sleep) => tokio::time::sleep(sleep).await,
+                None => YieldOnce(false).await,
+            }
+        }
+    }
+
+    impl AsyncPersistentDataStore for YieldingPersistentDataStore {
+        fn init(
+            &self,
+            all_data: AllData<SerializedItem, SerializedItem>,
+        ) -> BoxFuture<'_, Result<(), PersistentStoreError>> {
+            async move {
+                self.pause().await;
+                self.store.init(all_data).await
+            }
+            .boxed()
+        }
+
+        fn flag<'a>(
+            &'a self,
+            key: &'a str,
+        ) -> BoxFuture<'a, Result<Option<SerializedItem>, PersistentStoreError>> {
+            async move {
+                self.pause().await;
+                self.store.flag(key).await
+            }
+            .boxed()
+        }
+
+        fn segment<'a>(
+            &'a self,
+            key: &'a str,
+        ) -> BoxFuture<'a, Result<Option<SerializedItem>, PersistentStoreError>> {
+            async move {
+                self.pause().await;
+                self.store.segment(key).await
+            }
+            .boxed()
+        }
+
+        fn all_flags(
+            &self,
+        ) -> BoxFuture<'_, Result<HashMap<String, SerializedItem>, PersistentStoreError>> {
+            async move {
+                self.pause().await;
+                self.store.all_flags().await
+            }
+            .boxed()
+        }
+
+        fn upsert<'a>(
+            &'a self,
+            kind: DataKind,
+            key: &'a str,
+            serialized_item: SerializedItem,
+        ) -> BoxFuture<'a, Result<bool, PersistentStoreError>> {
+            async move {
+                self.pause().await;
+                self.store.upsert(kind, key, serialized_item).await
+            }
+            .boxed()
+        }
+
+        fn is_initialized(&self) -> BoxFuture<'_, bool> {
+            async move {
+                self.pause().await;
+                self.store.is_initialized().await
+            }
+            .boxed()
+        }
+
+        fn remove_tombstone<'a>(
+            &'a self,
+            kind: DataKind,
+            key: &'a str,
+            version: u64,
+        ) -> BoxFuture<'a, Result<bool, PersistentStoreError>> {
+            async move {
+                self.pause().await;
+                self.store.remove_tombstone(kind, key, version).await
+            }
+            .boxed()
+        }
     }
 }
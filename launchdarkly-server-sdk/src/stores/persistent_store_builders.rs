@@ -1,7 +1,10 @@
 use parking_lot::RwLock;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::Arc;
 use std::time::Duration;
 
+use super::persistent_store::{AsyncPersistentDataStore, PersistentDataStoreAdapter};
+use super::persistent_store_encryption::{EncryptedPersistentDataStore, PersistentStoreEncryption};
 use super::persistent_store_wrapper::PersistentDataStoreWrapper;
 use super::store_builders::{BuildError, DataStoreFactory};
 use super::{persistent_store::PersistentDataStore, store::DataStore};
@@ -18,11 +21,65 @@ pub trait PersistentDataStoreFactory {
     fn create_persistent_data_store(&self) -> Result<Box<dyn PersistentDataStore>, std::io::Error>;
 }
 
-/// Used to create a PersistentDataStoreWrapper instance, which wraps a [PersistentDataStore].
+/// AsyncPersistentDataStoreFactory is an interface for a factory that creates some implementation
+/// of an [AsyncPersistentDataStore].
+///
+/// This interface is implemented by database integrations with an asynchronous client. Usage is
+/// described in [AsyncPersistentDataStore].
+pub trait AsyncPersistentDataStoreFactory {
+    /// This is called by the SDK to create the implementation instance.
+    fn create_async_persistent_data_store(
+        &self,
+    ) -> Result<Box<dyn AsyncPersistentDataStore>, std::io::Error>;
+}
+
+/// Counters describing the SDK's cache in front of a persistent data store.
+///
+/// Obtained from [PersistentDataStoreBuilder::stats]. The counters are shared by every store
+/// built from that builder, and by all clones of this handle.
+#[derive(Clone, Default)]
+pub struct PersistentDataStoreStats {
+    pub(super) coalesced_flag_reads: Arc<AtomicU64>,
+    pub(super) coalesced_segment_reads: Arc<AtomicU64>,
+    pub(super) coalesced_all_flags_reads: Arc<AtomicU64>,
+}
+
+impl PersistentDataStoreStats {
+    /// The number of flag lookups which missed the cache while a read of the same flag was
+    /// already in progress, and so waited for that read instead of querying the store.
+    pub fn coalesced_flag_reads(&self) -> u64 {
+        self.coalesced_flag_reads.load(Ordering::Relaxed)
+    }
+
+    /// The number of segment lookups which missed the cache while a read of the same segment was
+    /// already in progress, and so waited for that read instead of querying the store.
+    pub fn coalesced_segment_reads(&self) -> u64 {
+        self.coalesced_segment_reads.load(Ordering::Relaxed)
+    }
+
+    /// The number of requests for all flags which missed the cache while a read of all flags was
+    /// already in progress, and so waited for that read instead of querying the store.
+    pub fn coalesced_all_flags_reads(&self) -> u64 {
+        self.coalesced_all_flags_reads.load(Ordering::Relaxed)
+    }
+}
+
+#[derive(Clone)]
+enum StoreFactory {
+    Sync(Arc<dyn PersistentDataStoreFactory>),
+    Async(Arc<dyn AsyncPersistentDataStoreFactory>),
+}
+
+/// Used to create a PersistentDataStoreWrapper instance, which wraps a [PersistentDataStore] or an
+/// [AsyncPersistentDataStore].
 #[derive(Clone)]
 pub struct PersistentDataStoreBuilder {
     cache_ttl: Option<Duration>,
-    factory: Arc<dyn PersistentDataStoreFactory>,
+    stale_while_revalidate: bool,
+    tombstone_retention: Option<Duration>,
+    encryption: Option<PersistentStoreEncryption>,
+    factory: StoreFactory,
+    stats: PersistentDataStoreStats,
 }
 
 impl PersistentDataStoreBuilder {
@@ -31,7 +88,28 @@ impl PersistentDataStoreBuilder {
     pub fn new(factory: Arc<dyn PersistentDataStoreFactory>) -> Self {
         Self {
             cache_ttl: Some(DEFAULT_CACHE_TIME),
-            factory,
+            stale_while_revalidate: false,
+            tombstone_retention: None,
+            encryption: None,
+            stats: PersistentDataStoreStats::default(),
+            factory: StoreFactory::Sync(factory),
+        }
+    }
+
+    /// Create a new [PersistentDataStoreBuilder] configured with the provided
+    /// [AsyncPersistentDataStoreFactory] and a default cache lifetime.
+    ///
+    /// Reads made through the asynchronous evaluation methods, such as
+    /// [crate::Client::variation_async], await the store. The synchronous evaluation methods
+    /// block the calling thread until the store responds.
+    pub fn new_async(factory: Arc<dyn AsyncPersistentDataStoreFactory>) -> Self {
+        Self {
+            cache_ttl: Some(DEFAULT_CACHE_TIME),
+            stale_while_revalidate: false,
+            tombstone_retention: None,
+            encryption: None,
+            stats: PersistentDataStoreStats::default(),
+            factory: StoreFactory::Async(factory),
         }
     }
 
@@ -69,18 +147,93 @@ impl PersistentDataStoreBuilder {
         self.cache_ttl = Some(Duration::from_secs(0));
         self
     }
+
+    /// Specifies whether cached items which have outlived the cache TTL should continue to be
+    /// served while they are refreshed from the persistent store in the background. The cache of
+    /// all flags is also populated from the persistent store when the SDK starts.
+    ///
+    /// In this mode, once the cache has been populated, evaluations never wait for the persistent
+    /// store. The trade-off is that an evaluation may see data up to one store round-trip older
+    /// than the cache TTL; if the store cannot be reached, the last values read are served until
+    /// a refresh succeeds.
+    ///
+    /// This has no effect if caching is disabled or the cache never expires. The default is
+    /// false.
+    pub fn stale_while_revalidate(&mut self, enabled: bool) -> &mut Self {
+        self.stale_while_revalidate = enabled;
+        self
+    }
+
+    /// Specifies how long to keep the placeholders which record that a flag or segment was
+    /// deleted. By default, placeholders are kept until the next full data set replaces the
+    /// store's contents.
+    ///
+    /// Once a placeholder this SDK instance wrote is older than the retention, it is removed
+    /// through [PersistentDataStore::remove_tombstone]; stores which do not implement that method
+    /// keep their placeholders. Placeholders are purged as later updates arrive.
+    ///
+    /// A placeholder stops an update which was delayed in transit from resurrecting an item that
+    /// has since been deleted, so the retention should comfortably exceed the longest delay you
+    /// expect between updates being sent and received.
+    pub fn tombstone_retention(&mut self, retention: Duration) -> &mut Self {
+        self.tombstone_retention = Some(retention);
+        self
+    }
+
+    /// Specifies that items should be encrypted before they are written to the persistent store,
+    /// and decrypted as they are read back. See [PersistentStoreEncryption] for details.
+    ///
+    /// By default, items are written to the store unencrypted.
+    pub fn encryption(&mut self, encryption: PersistentStoreEncryption) -> &mut Self {
+        self.encryption = Some(encryption);
+        self
+    }
+
+    /// Returns counters describing the cache of every store built by this builder.
+    pub fn stats(&self) -> PersistentDataStoreStats {
+        self.stats.clone()
+    }
 }
 
 impl DataStoreFactory for PersistentDataStoreBuilder {
     fn build(&self) -> Result<Arc<RwLock<dyn DataStore>>, BuildError> {
-        let store = self
-            .factory
-            .create_persistent_data_store()
-            .map_err(|e| BuildError::InvalidConfig(e.to_string()))?;
-        Ok(Arc::new(RwLock::new(PersistentDataStoreWrapper::new(
-            store,
-            self.cache_ttl,
-        ))))
+        let store: Arc<dyn AsyncPersistentDataStore> = match &self.factory {
+            StoreFactory::Sync(factory) => {
+                let store = factory
+                    .create_persistent_data_store()
+                    .map_err(|e| BuildError::InvalidConfig(e.to_string()))?;
+                Arc::new(PersistentDataStoreAdapter::new(store))
+            }
+            StoreFactory::Async(factory) => factory
+                .create_async_persistent_data_store()
+                .map_err(|e| BuildError::InvalidConfig(e.to_string()))?
+                .into(),
+        };
+        let store: Arc<dyn AsyncPersistentDataStore> = match &self.encryption {
+            Some(encryption) => {
+                Arc::new(EncryptedPersistentDataStore::new(store, encryption.clone()))
+            }
+            None => store,
+        };
+
+        let wrapper = PersistentDataStoreWrapper::new_async(store, self.cache_ttl);
+        let wrapper = match &self.factory {
+            StoreFactory::Sync(_) => wrapper.synchronous(),
+            StoreFactory::Async(_) => wrapper,
+        };
+
+        let wrapper = wrapper
+            .stats(&self.stats)
+            .tombstone_retention(self.tombstone_retention);
+        let wrapper = if self.stale_while_revalidate {
+            let wrapper = wrapper.stale_while_revalidate(self.cache_ttl);
+            wrapper.prewarm();
+            wrapper
+        } else {
+            wrapper
+        };
+
+        Ok(Arc::new(RwLock::new(wrapper)))
     }
 
     fn to_owned(&self) -> Box<dyn DataStoreFactory> {
@@ -90,7 +243,11 @@ impl DataStoreFactory for PersistentDataStoreBuilder {
 
 #[cfg(test)]
 mod tests {
-    use crate::stores::persistent_store::tests::InMemoryPersistentDataStore;
+    use crate::stores::persistent_store::tests::{
+        InMemoryPersistentDataStore, SharedPersistentDataStore,
+    };
+    use crate::test_common::basic_flag;
+    use maplit::hashmap;
     use std::collections::HashMap;
 
     use crate::stores::store_types::AllData;
@@ -102,7 +259,7 @@ mod tests {
     impl PersistentDataStoreFactory for InMemoryPersistentDataStoreFactory {
         fn create_persistent_data_store(
             &self,
-        ) -> Result<Box<(dyn PersistentDataStore + 'static)>, std::io::Error> {
+        ) -> Result<Box<dyn PersistentDataStore + 'static>, std::io::Error> {
             Ok(Box::new(InMemoryPersistentDataStore {
                 data: AllData {
                     flags: HashMap::new(),
@@ -129,4 +286,46 @@ mod tests {
         builder.cache_forever();
         assert_eq!(builder.cache_ttl, None);
     }
+
+    #[test]
+    fn builder_can_enable_stale_while_revalidate() {
+        let factory = InMemoryPersistentDataStoreFactory {};
+        let mut builder = PersistentDataStoreBuilder::new(Arc::new(factory));
+        assert!(!builder.stale_while_revalidate);
+
+        builder.stale_while_revalidate(true);
+        assert!(builder.stale_while_revalidate);
+        assert!(builder.build().is_ok());
+    }
+
+    #[test]
+    fn builder_can_enable_encryption() {
+        struct SharedFactory(SharedPersistentDataStore);
+
+        impl PersistentDataStoreFactory for SharedFactory {
+            fn create_persistent_data_store(
+                &self,
+            ) -> Result<Box<dyn PersistentDataStore + 'static>, std::io::Error> {
+                Ok(Box::new(self.0.clone()))
+            }
+        }
+
+        let shared = SharedPersistentDataStore::new(AllData {
+            flags: HashMap::new(),
+            segments: HashMap::new(),
+        });
+        let mut builder = PersistentDataStoreBuilder::new(Arc::new(SharedFactory(shared.clone())));
+        builder.encryption(PersistentStoreEncryption::new("key", &[7; 32]).unwrap());
+
+        let store = builder.build().unwrap();
+        store.write().init(AllData {
+            flags: hashmap!["flag".into() => basic_flag("flag")],
+            segments: HashMap::new(),
+        });
+
+        let raw = shared.flag("flag").unwrap().unwrap();
+        assert!(raw.serialized_item.starts_with("$ldenc1:key:"));
+        let flag = store.read().flag("flag").expect("flag should be decrypted");
+        assert_eq!("flag", flag.key);
+    }
 }
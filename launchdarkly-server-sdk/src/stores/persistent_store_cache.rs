@@ -1,43 +1,88 @@
 use std::collections::HashMap;
 use std::hash::Hash;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
+use launchdarkly_server_sdk_evaluation::Versioned;
 use moka::sync::Cache;
 
 use super::store_types::StorageItem;
 
+#[derive(Clone)]
+struct Cached<V> {
+    value: V,
+    cached_at: Instant,
+}
+
+/// A value read from the cache, along with whether it has outlived the cache TTL and should be
+/// refreshed from the persistent store.
+pub(super) struct CacheHit<V> {
+    pub value: V,
+    pub stale: bool,
+}
+
+#[derive(Clone)]
 pub(super) struct CachePair<T> {
-    all: Cache<String, HashMap<String, StorageItem<T>>>,
-    single: Cache<String, StorageItem<T>>,
+    all: Cache<String, Cached<HashMap<String, StorageItem<T>>>>,
+    single: Cache<String, Cached<StorageItem<T>>>,
     cache_name: String,
+    // When set, entries are never evicted; instead they are reported as stale once they are older
+    // than this duration.
+    refresh_after: Option<Duration>,
 }
 
 impl<T: 'static + Sync + Send + Clone> CachePair<T> {
-    pub fn new(cache_name: String, cache_ttl: Option<Duration>) -> CachePair<T> {
+    pub fn new(
+        cache_name: String,
+        cache_ttl: Option<Duration>,
+        stale_while_revalidate: bool,
+    ) -> CachePair<T> {
+        let refresh_after = match cache_ttl {
+            Some(ttl) if stale_while_revalidate && !ttl.is_zero() => Some(ttl),
+            _ => None,
+        };
+
         match cache_ttl {
-            Some(ttl) => CachePair {
+            Some(ttl) if refresh_after.is_none() => CachePair {
                 all: Cache::builder().time_to_live(ttl).build(),
                 single: Cache::builder().time_to_live(ttl).build(),
                 cache_name,
+                refresh_after,
             },
-            None => CachePair {
+            _ => CachePair {
                 all: Cache::builder().build(),
                 single: Cache::builder().build(),
                 cache_name,
+                refresh_after,
             },
         }
     }
 
     pub fn cache_is_infinite(&self) -> bool {
-        self.all.policy().time_to_live().is_none()
+        self.all.policy().time_to_live().is_none() && self.refresh_after.is_none()
     }
 
-    pub fn get_all(&self) -> Option<HashMap<String, StorageItem<T>>> {
-        self.all.get(&self.all_key())
+    pub fn get_all(&self) -> Option<CacheHit<HashMap<String, StorageItem<T>>>> {
+        self.all
+            .get(&self.all_key())
+            .map(|cached| self.to_hit(cached))
     }
 
-    pub fn get_one(&self, key: &str) -> Option<StorageItem<T>> {
-        self.single.get(&self.single_key(key))
+    pub fn get_one(&self, key: &str) -> Option<CacheHit<StorageItem<T>>> {
+        self.single
+            .get(&self.single_key(key))
+            .map(|cached| self.to_hit(cached))
+    }
+
+    fn to_hit<V>(&self, cached: Cached<V>) -> CacheHit<V> {
+        let stale = match self.refresh_after {
+            Some(refresh_after) => cached.cached_at.elapsed() >= refresh_after,
+            None => false,
+        };
+
+        CacheHit {
+            value: cached.value,
+            stale,
+        }
     }
 
     pub fn insert_single(&self, item: StorageItem<T>, key: &str) {
@@ -48,14 +93,36 @@ impl<T: 'static + Sync + Send + Clone> CachePair<T> {
         self.insert_into_cache(&self.all, self.all_key(), data)
     }
 
-    fn insert_into_cache<K, V>(&self, cache: &Cache<K, V>, key: K, value: V)
+    /// Replaces a single item within the cached set of all items, if that set is cached and will
+    /// not expire on its own. The set keeps its original age, so a refresh is still due when it
+    /// would otherwise have been.
+    ///
+    /// Returns false if the set was not updated and should be invalidated instead.
+    pub fn update_in_all(&self, key: &str, item: StorageItem<T>) -> bool {
+        if !(self.cache_is_infinite() || self.refresh_after.is_some()) {
+            return false;
+        }
+
+        if let Some(mut cached) = self.all.get(&self.all_key()) {
+            cached.value.insert(key.to_string(), item);
+            self.all.insert(self.all_key(), cached);
+        }
+        true
+    }
+
+    fn insert_into_cache<K, V>(&self, cache: &Cache<K, Cached<V>>, key: K, value: V)
     where
         K: Hash + Eq + Send + Sync + 'static,
         V: Clone + Send + Sync + 'static,
     {
+        let cached = Cached {
+            value,
+            cached_at: Instant::now(),
+        };
+
         match cache.policy().time_to_live() {
-            None => cache.insert(key, value),
-            Some(duration) if !duration.is_zero() => cache.insert(key, value),
+            None => cache.insert(key, cached),
+            Some(duration) if !duration.is_zero() => cache.insert(key, cached),
             _ => (),
         }
     }
@@ -76,8 +143,35 @@ impl<T: 'static + Sync + Send + Clone> CachePair<T> {
         self.single.invalidate(&self.single_key(key));
     }
 
+    /// Removes an item from the cache entirely, including from the cached set of all items.
+    pub fn forget(&self, key: &str) {
+        self.invalidate_single(key);
+        if let Some(mut cached) = self.all.get(&self.all_key()) {
+            if cached.value.remove(key).is_some() {
+                self.all.insert(self.all_key(), cached);
+            }
+        }
+    }
+
     pub fn invalidate_everything(&self) {
         self.all.invalidate_all();
         self.single.invalidate_all();
     }
 }
+
+impl<T> CachePair<T>
+where
+    T: 'static + Sync + Send + Clone,
+    StorageItem<T>: Versioned,
+{
+    /// Caches an item read from the persistent store, unless the cache already holds a newer
+    /// version of it. This prevents a read which raced with an update from undoing that update.
+    pub fn insert_single_unless_newer(&self, item: StorageItem<T>, key: &str) {
+        if let Some(cached) = self.single.get(&self.single_key(key)) {
+            if cached.value.version() > item.version() {
+                return;
+            }
+        }
+        self.insert_single(item, key);
+    }
+}
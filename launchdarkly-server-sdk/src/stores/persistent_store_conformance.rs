@@ -0,0 +1,974 @@
+//! A reusable battery of behavioral tests for [PersistentDataStore] implementations.
+//!
+//! The semantics a persistent store must provide are spread across the doc comments of
+//! [PersistentDataStore]: version-guarded upserts, deleted item placeholders, `init` replacing all
+//! existing data, and `is_initialized` observing data written by other instances. This module
+//! checks all of them, and then round-trips data through the SDK's own caching layer using every
+//! caching mode the SDK supports.
+
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+use launchdarkly_server_sdk_evaluation::{Flag, Segment, Store};
+
+use super::persistent_store::PersistentDataStore;
+use super::persistent_store_wrapper::PersistentDataStoreWrapper;
+use super::store::DataStore;
+use super::store_types::{AllData, DataKind, PatchTarget, SerializedItem, StorageItem};
+
+/// Provides the store instances exercised by a [PersistentDataStoreTestSuite].
+///
+/// Every test case works within its own prefix, so that cases cannot observe each other's data.
+/// Stores created with the same prefix must share their underlying data, as they would if they
+/// were created by different processes pointed at the same database.
+pub trait PersistentDataStoreTestHarness {
+    /// Creates a new store instance which reads and writes the data belonging to `prefix`.
+    fn create_store(&self, prefix: &str) -> Result<Box<dyn PersistentDataStore>, std::io::Error>;
+
+    /// Removes all data belonging to `prefix`, including the record of whether it was
+    /// initialized.
+    fn clear_data(&self, prefix: &str) -> Result<(), std::io::Error>;
+}
+
+type TestCase<H> = fn(&PersistentDataStoreTestSuite<H>, &str) -> Result<(), String>;
+
+/// Runs a standard set of behavioral tests against a [PersistentDataStore] implementation.
+///
+/// ```ignore
+/// #[test]
+/// fn my_store_conforms() {
+///     PersistentDataStoreTestSuite::new(MyStoreHarness::new()).run();
+/// }
+/// ```
+pub struct PersistentDataStoreTestSuite<H: PersistentDataStoreTestHarness> {
+    harness: H,
+    prefix: String,
+    counter: AtomicUsize,
+}
+
+impl<H: PersistentDataStoreTestHarness> PersistentDataStoreTestSuite<H> {
+    /// Create a new test suite which obtains its stores from the provided harness.
+    pub fn new(harness: H) -> Self {
+        Self {
+            harness,
+            prefix: String::from("ldconformance"),
+            counter: AtomicUsize::new(0),
+        }
+    }
+
+    /// Sets the string each test case's prefix starts with. The default is `ldconformance`.
+    ///
+    /// Change this if the default could collide with data you care about, or if your store places
+    /// restrictions on the characters a prefix may contain.
+    pub fn prefix(&mut self, prefix: impl Into<String>) -> &mut Self {
+        self.prefix = prefix.into();
+        self
+    }
+
+    /// Runs every test case, panicking with a description of each failure if any of them fail.
+    pub fn run(&self) {
+        let failures = self.failures();
+        if !failures.is_empty() {
+            panic!(
+                "{} persistent data store conformance test(s) failed:\n{}",
+                failures.len(),
+                failures.join("\n")
+            );
+        }
+    }
+
+    /// Runs every test case, returning a description of each failure.
+    pub fn failures(&self) -> Vec<String> {
+        let mut failures = Vec::new();
+
+        for (name, case) in self.cases() {
+            let prefix = self.next_prefix();
+            let result = self
+                .harness
+                .clear_data(&prefix)
+                .map_err(|e| format!("clearing data failed: {}", e))
+                .and_then(|_| case(self, &prefix));
+
+            if let Err(e) = self.harness.clear_data(&prefix) {
+                warn!("failed to clear conformance data for {}: {}", prefix, e);
+            }
+
+            if let Err(message) = result {
+                failures.push(format!("{}: {}", name, message));
+            }
+        }
+
+        failures
+    }
+
+    fn cases(&self) -> Vec<(&'static str, TestCase<H>)> {
+        vec![
+            (
+                "store_is_not_initialized_before_init",
+                Self::store_is_not_initialized_before_init,
+            ),
+            (
+                "store_is_initialized_after_init",
+                Self::store_is_initialized_after_init,
+            ),
+            (
+                "empty_init_marks_store_initialized",
+                Self::empty_init_marks_store_initialized,
+            ),
+            (
+                "is_initialized_is_shared_across_instances",
+                Self::is_initialized_is_shared_across_instances,
+            ),
+            ("init_stores_all_items", Self::init_stores_all_items),
+            (
+                "init_replaces_all_existing_data",
+                Self::init_replaces_all_existing_data,
+            ),
+            (
+                "init_ignores_versions_of_existing_data",
+                Self::init_ignores_versions_of_existing_data,
+            ),
+            ("missing_items_are_none", Self::missing_items_are_none),
+            ("upsert_inserts_new_items", Self::upsert_inserts_new_items),
+            (
+                "upsert_replaces_older_versions",
+                Self::upsert_replaces_older_versions,
+            ),
+            (
+                "upsert_ignores_same_and_older_versions",
+                Self::upsert_ignores_same_and_older_versions,
+            ),
+            (
+                "upsert_retains_deleted_placeholders",
+                Self::upsert_retains_deleted_placeholders,
+            ),
+            (
+                "deleted_placeholders_are_version_guarded",
+                Self::deleted_placeholders_are_version_guarded,
+            ),
+            (
+                "remove_tombstone_only_removes_matching_placeholders",
+                Self::remove_tombstone_only_removes_matching_placeholders,
+            ),
+            (
+                "flags_and_segments_are_separate",
+                Self::flags_and_segments_are_separate,
+            ),
+            (
+                "writes_are_visible_to_other_instances",
+                Self::writes_are_visible_to_other_instances,
+            ),
+            ("prefixes_are_isolated", Self::prefixes_are_isolated),
+            (
+                "wrapper_round_trip_without_cache",
+                Self::wrapper_round_trip_without_cache,
+            ),
+            (
+                "wrapper_round_trip_with_cache_ttl",
+                Self::wrapper_round_trip_with_cache_ttl,
+            ),
+            (
+                "wrapper_round_trip_with_infinite_cache",
+                Self::wrapper_round_trip_with_infinite_cache,
+            ),
+        ]
+    }
+
+    fn next_prefix(&self) -> String {
+        format!(
+            "{}_{}_{}",
+            self.prefix,
+            std::process::id(),
+            self.counter.fetch_add(1, Ordering::SeqCst)
+        )
+    }
+
+    fn create_store(&self, prefix: &str) -> Result<Box<dyn PersistentDataStore>, String> {
+        self.harness
+            .create_store(prefix)
+            .map_err(|e| format!("creating store failed: {}", e))
+    }
+
+    fn store_is_not_initialized_before_init(&self, prefix: &str) -> Result<(), String> {
+        let store = self.create_store(prefix)?;
+        check(!store.is_initialized(), "fresh store reports initialized")
+    }
+
+    fn store_is_initialized_after_init(&self, prefix: &str) -> Result<(), String> {
+        let mut store = self.create_store(prefix)?;
+        store.init(sample_data()).map_err(store_error("init"))?;
+        check(store.is_initialized(), "store not initialized after init")
+    }
+
+    fn empty_init_marks_store_initialized(&self, prefix: &str) -> Result<(), String> {
+        let mut store = self.create_store(prefix)?;
+        store
+            .init(AllData {
+                flags: HashMap::new(),
+                segments: HashMap::new(),
+            })
+            .map_err(store_error("init"))?;
+        check(
+            store.is_initialized(),
+            "store not initialized after init with no data",
+        )
+    }
+
+    fn is_initialized_is_shared_across_instances(&self, prefix: &str) -> Result<(), String> {
+        let mut first = self.create_store(prefix)?;
+        let second = self.create_store(prefix)?;
+        check(!second.is_initialized(), "fresh store reports initialized")?;
+
+        first.init(sample_data()).map_err(store_error("init"))?;
+
+        check(
+            second.is_initialized(),
+            "store created before init did not observe another instance's init",
+        )?;
+        let third = self.create_store(prefix)?;
+        check(
+            third.is_initialized(),
+            "store created after init did not observe another instance's init",
+        )
+    }
+
+    fn init_stores_all_items(&self, prefix: &str) -> Result<(), String> {
+        let mut store = self.create_store(prefix)?;
+        store.init(sample_data()).map_err(store_error("init"))?;
+
+        let flag = store.flag("flag-a").map_err(store_error("flag"))?;
+        check_item(flag, &serialized_flag("flag-a", 1), "flag-a")?;
+        let flag = store.flag("flag-b").map_err(store_error("flag"))?;
+        check_item(flag, &serialized_flag("flag-b", 2), "flag-b")?;
+        let segment = store.segment("segment-a").map_err(store_error("segment"))?;
+        check_item(segment, &serialized_segment("segment-a", 3), "segment-a")?;
+
+        let all_flags = store.all_flags().map_err(store_error("all_flags"))?;
+        check_keys(&all_flags, &["flag-a", "flag-b"])?;
+        check_item(
+            all_flags.get("flag-a").cloned(),
+            &serialized_flag("flag-a", 1),
+            "flag-a in all_flags",
+        )
+    }
+
+    fn init_replaces_all_existing_data(&self, prefix: &str) -> Result<(), String> {
+        let mut store = self.create_store(prefix)?;
+        store.init(sample_data()).map_err(store_error("init"))?;
+        store
+            .init(AllData {
+                flags: HashMap::from([("flag-c".into(), serialized_flag("flag-c", 1))]),
+                segments: HashMap::new(),
+            })
+            .map_err(store_error("init"))?;
+
+        check(
+            store.flag("flag-a").map_err(store_error("flag"))?.is_none(),
+            "flag from the previous init was not removed",
+        )?;
+        check(
+            store
+                .segment("segment-a")
+                .map_err(store_error("segment"))?
+                .is_none(),
+            "segment from the previous init was not removed",
+        )?;
+        let all_flags = store.all_flags().map_err(store_error("all_flags"))?;
+        check_keys(&all_flags, &["flag-c"])
+    }
+
+    fn init_ignores_versions_of_existing_data(&self, prefix: &str) -> Result<(), String> {
+        let mut store = self.create_store(prefix)?;
+        store
+            .upsert(DataKind::Flag, "flag-a", serialized_flag("flag-a", 100))
+            .map_err(store_error("upsert"))?;
+        store.init(sample_data()).map_err(store_error("init"))?;
+
+        let flag = store.flag("flag-a").map_err(store_error("flag"))?;
+        check_item(flag, &serialized_flag("flag-a", 1), "flag-a")
+    }
+
+    fn missing_items_are_none(&self, prefix: &str) -> Result<(), String> {
+        let mut store = self.create_store(prefix)?;
+        store.init(sample_data()).map_err(store_error("init"))?;
+
+        check(
+            store
+                .flag("missing")
+                .map_err(store_error("flag"))?
+                .is_none(),
+            "missing flag was not None",
+        )?;
+        check(
+            store
+                .segment("missing")
+                .map_err(store_error("segment"))?
+                .is_none(),
+            "missing segment was not None",
+        )
+    }
+
+    fn upsert_inserts_new_items(&self, prefix: &str) -> Result<(), String> {
+        let mut store = self.create_store(prefix)?;
+        store.init(sample_data()).map_err(store_error("init"))?;
+
+        let updated = store
+            .upsert(DataKind::Flag, "flag-c", serialized_flag("flag-c", 1))
+            .map_err(store_error("upsert"))?;
+        check(updated, "inserting a new flag returned false")?;
+        let updated = store
+            .upsert(
+                DataKind::Segment,
+                "segment-c",
+                serialized_segment("segment-c", 1),
+            )
+            .map_err(store_error("upsert"))?;
+        check(updated, "inserting a new segment returned false")?;
+
+        let flag = store.flag("flag-c").map_err(store_error("flag"))?;
+        check_item(flag, &serialized_flag("flag-c", 1), "flag-c")?;
+        let segment = store.segment("segment-c").map_err(store_error("segment"))?;
+        check_item(segment, &serialized_segment("segment-c", 1), "segment-c")?;
+
+        let all_flags = store.all_flags().map_err(store_error("all_flags"))?;
+        check_keys(&all_flags, &["flag-a", "flag-b", "flag-c"])
+    }
+
+    fn upsert_replaces_older_versions(&self, prefix: &str) -> Result<(), String> {
+        let mut store = self.create_store(prefix)?;
+        store.init(sample_data()).map_err(store_error("init"))?;
+
+        let updated = store
+            .upsert(DataKind::Flag, "flag-a", serialized_flag("flag-a", 10))
+            .map_err(store_error("upsert"))?;
+        check(updated, "upserting a newer flag returned false")?;
+        let updated = store
+            .upsert(
+                DataKind::Segment,
+                "segment-a",
+                serialized_segment("segment-a", 10),
+            )
+            .map_err(store_error("upsert"))?;
+        check(updated, "upserting a newer segment returned false")?;
+
+        let flag = store.flag("flag-a").map_err(store_error("flag"))?;
+        check_item(flag, &serialized_flag("flag-a", 10), "flag-a")?;
+        let segment = store.segment("segment-a").map_err(store_error("segment"))?;
+        check_item(segment, &serialized_segment("segment-a", 10), "segment-a")
+    }
+
+    fn upsert_ignores_same_and_older_versions(&self, prefix: &str) -> Result<(), String> {
+        let mut store = self.create_store(prefix)?;
+        store
+            .init(AllData {
+                flags: HashMap::from([("flag-a".into(), serialized_flag("flag-a", 10))]),
+                segments: HashMap::from([(
+                    "segment-a".into(),
+                    serialized_segment("segment-a", 10),
+                )]),
+            })
+            .map_err(store_error("init"))?;
+
+        for version in [10, 9] {
+            let updated = store
+                .upsert(
+                    DataKind::Flag,
+                    "flag-a",
+                    with_salt(serialized_flag("flag-a", version)),
+                )
+                .map_err(store_error("upsert"))?;
+            check(
+                !updated,
+                &format!("upserting flag version {} over 10 returned true", version),
+            )?;
+
+            let updated = store
+                .upsert(
+                    DataKind::Segment,
+                    "segment-a",
+                    with_salt(serialized_segment("segment-a", version)),
+                )
+                .map_err(store_error("upsert"))?;
+            check(
+                !updated,
+                &format!(
+                    "upserting segment version {} over 10 returned true",
+                    version
+                ),
+            )?;
+        }
+
+        let flag = store.flag("flag-a").map_err(store_error("flag"))?;
+        check_item(flag, &serialized_flag("flag-a", 10), "flag-a")?;
+        let segment = store.segment("segment-a").map_err(store_error("segment"))?;
+        check_item(segment, &serialized_segment("segment-a", 10), "segment-a")
+    }
+
+    fn upsert_retains_deleted_placeholders(&self, prefix: &str) -> Result<(), String> {
+        let mut store = self.create_store(prefix)?;
+        store.init(sample_data()).map_err(store_error("init"))?;
+
+        let updated = store
+            .upsert(DataKind::Flag, "flag-a", tombstone(5))
+            .map_err(store_error("upsert"))?;
+        check(updated, "deleting a flag returned false")?;
+        let updated = store
+            .upsert(DataKind::Segment, "segment-a", tombstone(5))
+            .map_err(store_error("upsert"))?;
+        check(updated, "deleting a segment returned false")?;
+
+        let flag = store.flag("flag-a").map_err(store_error("flag"))?;
+        check_item(flag, &tombstone(5), "deleted flag-a")?;
+        let segment = store.segment("segment-a").map_err(store_error("segment"))?;
+        check_item(segment, &tombstone(5), "deleted segment-a")?;
+
+        let all_flags = store.all_flags().map_err(store_error("all_flags"))?;
+        check_keys(&all_flags, &["flag-a", "flag-b"])?;
+        check_item(
+            all_flags.get("flag-a").cloned(),
+            &tombstone(5),
+            "deleted flag-a in all_flags",
+        )
+    }
+
+    fn deleted_placeholders_are_version_guarded(&self, prefix: &str) -> Result<(), String> {
+        let mut store = self.create_store(prefix)?;
+        store.init(sample_data()).map_err(store_error("init"))?;
+
+        // A placeholder older than the stored item must not delete it.
+        let updated = store
+            .upsert(DataKind::Flag, "flag-b", tombstone(1))
+            .map_err(store_error("upsert"))?;
+        check(!updated, "deleting with an older version returned true")?;
+        let flag = store.flag("flag-b").map_err(store_error("flag"))?;
+        check_item(flag, &serialized_flag("flag-b", 2), "flag-b")?;
+
+        // An item older than a placeholder must not resurrect it.
+        store
+            .upsert(DataKind::Flag, "flag-a", tombstone(5))
+            .map_err(store_error("upsert"))?;
+        let updated = store
+            .upsert(DataKind::Flag, "flag-a", serialized_flag("flag-a", 4))
+            .map_err(store_error("upsert"))?;
+        check(!updated, "resurrecting with an older version returned true")?;
+        let flag = store.flag("flag-a").map_err(store_error("flag"))?;
+        check_item(flag, &tombstone(5), "deleted flag-a")?;
+
+        // A newer item replaces the placeholder.
+        let updated = store
+            .upsert(DataKind::Flag, "flag-a", serialized_flag("flag-a", 6))
+            .map_err(store_error("upsert"))?;
+        check(updated, "resurrecting with a newer version returned false")?;
+        let flag = store.flag("flag-a").map_err(store_error("flag"))?;
+        check_item(flag, &serialized_flag("flag-a", 6), "flag-a")
+    }
+
+    fn remove_tombstone_only_removes_matching_placeholders(
+        &self,
+        prefix: &str,
+    ) -> Result<(), String> {
+        let mut store = self.create_store(prefix)?;
+        store.init(sample_data()).map_err(store_error("init"))?;
+        store
+            .upsert(DataKind::Flag, "flag-a", tombstone(5))
+            .map_err(store_error("upsert"))?;
+
+        // Removing placeholders is optional, but a store must never remove anything else.
+        let removed = store
+            .remove_tombstone(DataKind::Flag, "flag-b", 2)
+            .map_err(store_error("remove_tombstone"))?;
+        check(!removed, "remove_tombstone removed a live item")?;
+        let flag = store.flag("flag-b").map_err(store_error("flag"))?;
+        check_item(flag, &serialized_flag("flag-b", 2), "flag-b")?;
+
+        let removed = store
+            .remove_tombstone(DataKind::Flag, "flag-a", 4)
+            .map_err(store_error("remove_tombstone"))?;
+        check(
+            !removed,
+            "remove_tombstone removed a placeholder with a different version",
+        )?;
+        let removed = store
+            .remove_tombstone(DataKind::Segment, "flag-a", 5)
+            .map_err(store_error("remove_tombstone"))?;
+        check(
+            !removed,
+            "remove_tombstone removed a placeholder of a different kind",
+        )?;
+        let flag = store.flag("flag-a").map_err(store_error("flag"))?;
+        check_item(flag, &tombstone(5), "deleted flag-a")?;
+
+        let removed = store
+            .remove_tombstone(DataKind::Flag, "flag-a", 5)
+            .map_err(store_error("remove_tombstone"))?;
+        let flag = store.flag("flag-a").map_err(store_error("flag"))?;
+        if removed {
+            check(flag.is_none(), "removed placeholder is still returned")?;
+            let all_flags = store.all_flags().map_err(store_error("all_flags"))?;
+            check_keys(&all_flags, &["flag-b"])
+        } else {
+            check_item(flag, &tombstone(5), "unremoved deleted flag-a")
+        }
+    }
+
+    fn flags_and_segments_are_separate(&self, prefix: &str) -> Result<(), String> {
+        let mut store = self.create_store(prefix)?;
+        store
+            .init(AllData {
+                flags: HashMap::from([("shared".into(), serialized_flag("shared", 1))]),
+                segments: HashMap::from([("shared".into(), serialized_segment("shared", 7))]),
+            })
+            .map_err(store_error("init"))?;
+
+        let flag = store.flag("shared").map_err(store_error("flag"))?;
+        check_item(flag, &serialized_flag("shared", 1), "flag shared")?;
+        let segment = store.segment("shared").map_err(store_error("segment"))?;
+        check_item(segment, &serialized_segment("shared", 7), "segment shared")?;
+
+        store
+            .upsert(DataKind::Segment, "shared", tombstone(8))
+            .map_err(store_error("upsert"))?;
+        let flag = store.flag("shared").map_err(store_error("flag"))?;
+        check_item(flag, &serialized_flag("shared", 1), "flag shared")?;
+
+        let all_flags = store.all_flags().map_err(store_error("all_flags"))?;
+        check_keys(&all_flags, &["shared"])
+    }
+
+    fn writes_are_visible_to_other_instances(&self, prefix: &str) -> Result<(), String> {
+        let mut first = self.create_store(prefix)?;
+        let second = self.create_store(prefix)?;
+        first.init(sample_data()).map_err(store_error("init"))?;
+
+        let flag = second.flag("flag-a").map_err(store_error("flag"))?;
+        check_item(flag, &serialized_flag("flag-a", 1), "flag-a")?;
+
+        first
+            .upsert(DataKind::Flag, "flag-a", serialized_flag("flag-a", 2))
+            .map_err(store_error("upsert"))?;
+        let flag = second.flag("flag-a").map_err(store_error("flag"))?;
+        check_item(flag, &serialized_flag("flag-a", 2), "updated flag-a")
+    }
+
+    fn prefixes_are_isolated(&self, prefix: &str) -> Result<(), String> {
+        let other_prefix = format!("{}_other", prefix);
+        self.harness
+            .clear_data(&other_prefix)
+            .map_err(|e| format!("clearing data failed: {}", e))?;
+
+        let result = (|| {
+            let mut store = self.create_store(prefix)?;
+            let mut other = self.create_store(&other_prefix)?;
+            store.init(sample_data()).map_err(store_error("init"))?;
+
+            check(
+                !other.is_initialized(),
+                "init under one prefix initialized another",
+            )?;
+            check(
+                other.flag("flag-a").map_err(store_error("flag"))?.is_none(),
+                "flag is visible under another prefix",
+            )?;
+
+            other
+                .init(AllData {
+                    flags: HashMap::from([("flag-c".into(), serialized_flag("flag-c", 1))]),
+                    segments: HashMap::new(),
+                })
+                .map_err(store_error("init"))?;
+
+            let all_flags = store.all_flags().map_err(store_error("all_flags"))?;
+            check_keys(&all_flags, &["flag-a", "flag-b"])
+        })();
+
+        if let Err(e) = self.harness.clear_data(&other_prefix) {
+            warn!(
+                "failed to clear conformance data for {}: {}",
+                other_prefix, e
+            );
+        }
+
+        result
+    }
+
+    fn wrapper_round_trip_without_cache(&self, prefix: &str) -> Result<(), String> {
+        self.wrapper_round_trip(prefix, Some(Duration::ZERO))
+    }
+
+    fn wrapper_round_trip_with_cache_ttl(&self, prefix: &str) -> Result<(), String> {
+        self.wrapper_round_trip(prefix, Some(Duration::from_secs(30)))
+    }
+
+    fn wrapper_round_trip_with_infinite_cache(&self, prefix: &str) -> Result<(), String> {
+        self.wrapper_round_trip(prefix, None)
+    }
+
+    fn wrapper_round_trip(&self, prefix: &str, cache_ttl: Option<Duration>) -> Result<(), String> {
+        let mut wrapper = PersistentDataStoreWrapper::new(self.create_store(prefix)?, cache_ttl);
+
+        wrapper.init(AllData {
+            flags: HashMap::from([
+                ("flag-a".into(), flag("flag-a", 1)),
+                ("flag-b".into(), flag("flag-b", 2)),
+            ]),
+            segments: HashMap::from([("segment-a".into(), segment("segment-a", 3))]),
+        });
+
+        check_version(wrapper.flag("flag-a").map(|f| f.version), 1, "flag-a")?;
+        check_version(
+            wrapper.segment("segment-a").map(|s| s.version),
+            3,
+            "segment-a",
+        )?;
+        check_keys(&wrapper.all_flags(), &["flag-a", "flag-b"])?;
+
+        wrapper
+            .upsert(
+                "flag-a",
+                PatchTarget::Flag(StorageItem::Item(flag("flag-a", 5))),
+            )
+            .map_err(|e| format!("wrapper upsert failed: {}", e))?;
+        wrapper
+            .upsert(
+                "flag-a",
+                PatchTarget::Flag(StorageItem::Item(flag("flag-a", 4))),
+            )
+            .map_err(|e| format!("wrapper upsert failed: {}", e))?;
+        check_version(wrapper.flag("flag-a").map(|f| f.version), 5, "flag-a")?;
+
+        wrapper
+            .upsert("flag-b", PatchTarget::Flag(StorageItem::Tombstone(6)))
+            .map_err(|e| format!("wrapper upsert failed: {}", e))?;
+        wrapper
+            .upsert("segment-a", PatchTarget::Segment(StorageItem::Tombstone(6)))
+            .map_err(|e| format!("wrapper upsert failed: {}", e))?;
+        check(
+            wrapper.flag("flag-b").is_none(),
+            "deleted flag is returned by the wrapper",
+        )?;
+        check(
+            wrapper.segment("segment-a").is_none(),
+            "deleted segment is returned by the wrapper",
+        )?;
+        check_keys(&wrapper.all_flags(), &["flag-a"])?;
+
+        // A new wrapper starts with an empty cache, so it must find everything in the store.
+        let fresh = PersistentDataStoreWrapper::new(self.create_store(prefix)?, cache_ttl);
+        check_version(fresh.flag("flag-a").map(|f| f.version), 5, "flag-a")?;
+        check(
+            fresh.flag("flag-b").is_none(),
+            "deleted flag is returned by a new wrapper",
+        )?;
+        check_keys(&fresh.all_flags(), &["flag-a"])?;
+
+        let store = self.create_store(prefix)?;
+        let placeholder = store.flag("flag-b").map_err(store_error("flag"))?;
+        check(
+            matches!(
+                placeholder,
+                Some(SerializedItem {
+                    deleted: true,
+                    version: 6,
+                    ..
+                })
+            ),
+            "wrapper delete did not store a placeholder",
+        )
+    }
+}
+
+fn check(condition: bool, message: &str) -> Result<(), String> {
+    if condition {
+        Ok(())
+    } else {
+        Err(message.to_string())
+    }
+}
+
+fn check_item(
+    actual: Option<SerializedItem>,
+    expected: &SerializedItem,
+    what: &str,
+) -> Result<(), String> {
+    let actual = actual.ok_or_else(|| format!("{} was not found", what))?;
+
+    check(
+        actual.version == expected.version,
+        &format!(
+            "{} has version {}, expected {}",
+            what, actual.version, expected.version
+        ),
+    )?;
+    check(
+        actual.deleted == expected.deleted,
+        &format!(
+            "{} has deleted {}, expected {}",
+            what, actual.deleted, expected.deleted
+        ),
+    )?;
+
+    // Deleted placeholders are identified by their flag and version alone; stores are free to
+    // represent their contents however they like.
+    if expected.deleted {
+        return Ok(());
+    }
+
+    let actual_json: serde_json::Value = serde_json::from_str(&actual.serialized_item)
+        .map_err(|e| format!("{} is not valid JSON: {}", what, e))?;
+    let expected_json: serde_json::Value =
+        serde_json::from_str(&expected.serialized_item).expect("fixtures are valid JSON");
+    check(
+        actual_json == expected_json,
+        &format!(
+            "{} was {}, expected {}",
+            what, actual.serialized_item, expected.serialized_item
+        ),
+    )
+}
+
+fn check_keys<T>(items: &HashMap<String, T>, expected: &[&str]) -> Result<(), String> {
+    let mut actual: Vec<&str> = items.keys().map(String::as_str).collect();
+    actual.sort_unstable();
+    let mut expected = expected.to_vec();
+    expected.sort_unstable();
+
+    check(
+        actual == expected,
+        &format!("all_flags returned {:?}, expected {:?}", actual, expected),
+    )
+}
+
+fn check_version(actual: Option<u64>, expected: u64, what: &str) -> Result<(), String> {
+    match actual {
+        Some(version) => check(
+            version == expected,
+            &format!("{} has version {}, expected {}", what, version, expected),
+        ),
+        None => Err(format!("{} was not found", what)),
+    }
+}
+
+fn store_error(
+    operation: &'static str,
+) -> impl Fn(super::persistent_store::PersistentStoreError) -> String {
+    move |e| format!("{} failed: {}", operation, e)
+}
+
+fn flag(key: &str, version: u64) -> Flag {
+    serde_json::from_value(serde_json::json!({
+        "key": key,
+        "version": version,
+        "on": true,
+        "targets": [],
+        "rules": [],
+        "prerequisites": [],
+        "fallthrough": {"variation": 1},
+        "offVariation": 0,
+        "variations": [false, true],
+        "salt": "kosher",
+    }))
+    .expect("fixture flag is valid")
+}
+
+fn segment(key: &str, version: u64) -> Segment {
+    serde_json::from_value(serde_json::json!({
+        "key": key,
+        "version": version,
+        "included": ["alice"],
+        "excluded": [],
+        "rules": [],
+        "salt": "salty",
+    }))
+    .expect("fixture segment is valid")
+}
+
+fn serialized_flag(key: &str, version: u64) -> SerializedItem {
+    SerializedItem::try_from(StorageItem::Item(flag(key, version))).expect("fixture serializes")
+}
+
+fn serialized_segment(key: &str, version: u64) -> SerializedItem {
+    SerializedItem::try_from(StorageItem::Item(segment(key, version))).expect("fixture serializes")
+}
+
+/// Changes an item's contents without changing its version, so that an upsert which should have
+/// been ignored is detectable.
+fn with_salt(mut item: SerializedItem) -> SerializedItem {
+    let mut value: serde_json::Value =
+        serde_json::from_str(&item.serialized_item).expect("fixtures are valid JSON");
+    value["salt"] = serde_json::Value::from("stale");
+    item.serialized_item = value.to_string();
+    item
+}
+
+fn tombstone(version: u64) -> SerializedItem {
+    SerializedItem::try_from(StorageItem::<Flag>::Tombstone(version)).expect("fixture serializes")
+}
+
+fn sample_data() -> AllData<SerializedItem, SerializedItem> {
+    AllData {
+        flags: HashMap::from([
+            ("flag-a".into(), serialized_flag("flag-a", 1)),
+            ("flag-b".into(), serialized_flag("flag-b", 2)),
+        ]),
+        segments: HashMap::from([("segment-a".into(), serialized_segment("segment-a", 3))]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::sync::Arc;
+
+    use parking_lot::Mutex;
+
+    use super::*;
+    use crate::stores::persistent_store::PersistentStoreError;
+
+    #[derive(Default)]
+    struct PrefixData {
+        initialized: bool,
+        flags: HashMap<String, SerializedItem>,
+        segments: HashMap<String, SerializedItem>,
+    }
+
+    type SharedData = Arc<Mutex<HashMap<String, PrefixData>>>;
+
+    /// A reference implementation of the persistent store contract, backed by shared memory.
+    struct SharedMemoryStore {
+        prefix: String,
+        data: SharedData,
+        version_guarded: bool,
+    }
+
+    impl PersistentDataStore for SharedMemoryStore {
+        fn init(
+            &mut self,
+            all_data: AllData<SerializedItem, SerializedItem>,
+        ) -> Result<(), PersistentStoreError> {
+            self.data.lock().insert(
+                self.prefix.clone(),
+                PrefixData {
+                    initialized: true,
+                    flags: all_data.flags,
+                    segments: all_data.segments,
+                },
+            );
+            Ok(())
+        }
+
+        fn flag(&self, key: &str) -> Result<Option<SerializedItem>, PersistentStoreError> {
+            let data = self.data.lock();
+            Ok(data
+                .get(&self.prefix)
+                .and_then(|d| d.flags.get(key).cloned()))
+        }
+
+        fn segment(&self, key: &str) -> Result<Option<SerializedItem>, PersistentStoreError> {
+            let data = self.data.lock();
+            Ok(data
+                .get(&self.prefix)
+                .and_then(|d| d.segments.get(key).cloned()))
+        }
+
+        fn all_flags(&self) -> Result<HashMap<String, SerializedItem>, PersistentStoreError> {
+            let data = self.data.lock();
+            Ok(data
+                .get(&self.prefix)
+                .map(|d| d.flags.clone())
+                .unwrap_or_default())
+        }
+
+        fn upsert(
+            &mut self,
+            kind: DataKind,
+            key: &str,
+            serialized_item: SerializedItem,
+        ) -> Result<bool, PersistentStoreError> {
+            let mut data = self.data.lock();
+            let prefix_data = data.entry(self.prefix.clone()).or_default();
+            let items = match kind {
+                DataKind::Flag => &mut prefix_data.flags,
+                DataKind::Segment => &mut prefix_data.segments,
+            };
+
+            let newer = items
+                .get(key)
+                .map(|existing| existing.version < serialized_item.version)
+                .unwrap_or(true);
+            if newer || !self.version_guarded {
+                items.insert(key.to_string(), serialized_item);
+            }
+
+            Ok(newer)
+        }
+
+        fn is_initialized(&self) -> bool {
+            self.data
+                .lock()
+                .get(&self.prefix)
+                .map(|d| d.initialized)
+                .unwrap_or(false)
+        }
+    }
+
+    struct SharedMemoryHarness {
+        data: SharedData,
+        version_guarded: bool,
+    }
+
+    impl SharedMemoryHarness {
+        fn new(version_guarded: bool) -> Self {
+            Self {
+                data: SharedData::default(),
+                version_guarded,
+            }
+        }
+    }
+
+    impl PersistentDataStoreTestHarness for SharedMemoryHarness {
+        fn create_store(
+            &self,
+            prefix: &str,
+        ) -> Result<Box<dyn PersistentDataStore>, std::io::Error> {
+            Ok(Box::new(SharedMemoryStore {
+                prefix: prefix.to_string(),
+                data: self.data.clone(),
+                version_guarded: self.version_guarded,
+            }))
+        }
+
+        fn clear_data(&self, prefix: &str) -> Result<(), std::io::Error> {
+            self.data.lock().remove(prefix);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn conforming_store_passes() {
+        PersistentDataStoreTestSuite::new(SharedMemoryHarness::new(true)).run();
+    }
+
+    #[test]
+    fn store_without_version_guard_fails() {
+        let failures =
+            PersistentDataStoreTestSuite::new(SharedMemoryHarness::new(false)).failures();
+
+        assert!(failures
+            .iter()
+            .any(|f| f.starts_with("upsert_ignores_same_and_older_versions: ")));
+        assert!(failures
+            .iter()
+            .any(|f| f.starts_with("deleted_placeholders_are_version_guarded: ")));
+        assert!(!failures
+            .iter()
+            .any(|f| f.starts_with("init_stores_all_items: ")));
+    }
+
+    #[test]
+    #[should_panic(expected = "persistent data store conformance test(s) failed")]
+    fn run_panics_on_failure() {
+        PersistentDataStoreTestSuite::new(SharedMemoryHarness::new(false)).run();
+    }
+}
@@ -0,0 +1,497 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use data_encoding::BASE64;
+use futures::future::{BoxFuture, FutureExt};
+use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, AES_256_GCM, NONCE_LEN};
+use ring::rand::{SecureRandom, SystemRandom};
+use thiserror::Error;
+
+use super::persistent_store::{AsyncPersistentDataStore, PersistentStoreError};
+use super::store_types::{AllData, DataKind, SerializedItem};
+
+// Marks a serialized item as encrypted. Plain serialized items are JSON objects, so they can never
+// start with this prefix.
+const ENVELOPE_PREFIX: &str = "$ldenc1";
+
+/// Error type used to represent an invalid [PersistentStoreEncryption] configuration.
+#[non_exhaustive]
+#[derive(Debug, Error)]
+pub enum EncryptionError {
+    /// The key was not the length AES-256-GCM requires.
+    #[error("encryption keys must be {expected} bytes long, got {actual}")]
+    InvalidKeyLength {
+        /// The required key length in bytes.
+        expected: usize,
+        /// The length of the key provided.
+        actual: usize,
+    },
+
+    /// The key id was empty or contained unsupported characters.
+    #[error("key id {0:?} must be non-empty and contain only ASCII letters, digits, '-' and '_'")]
+    InvalidKeyId(String),
+
+    /// A key with the same id was already configured.
+    #[error("key id {0:?} has already been configured")]
+    DuplicateKeyId(String),
+}
+
+/// Configures encryption of the items a persistent data store holds.
+///
+/// Each flag and segment is encrypted and authenticated with AES-256-GCM before it is passed to
+/// the [crate::PersistentDataStore], and decrypted when it is read back. An item's key, kind,
+/// version, and deletion status are bound to its ciphertext, so an encrypted item cannot be
+/// replayed under another key or version without detection. The version and deletion status are
+/// stored unencrypted, as stores need them to order updates.
+///
+/// Every encrypted item records the id of the key it was encrypted with. To rotate keys, make the
+/// new key the primary key and add the old key with
+/// [PersistentStoreEncryption::decryption_key]; items are re-encrypted with the new key as they
+/// are updated, or all at once the next time the SDK receives a full data set.
+#[derive(Clone)]
+pub struct PersistentStoreEncryption {
+    primary_key_id: String,
+    keys: HashMap<String, Arc<LessSafeKey>>,
+    allow_plaintext_reads: bool,
+}
+
+impl PersistentStoreEncryption {
+    /// Create a new configuration which encrypts items with the provided 256-bit key, identified
+    /// by `key_id`.
+    pub fn new(key_id: impl Into<String>, key: &[u8]) -> Result<Self, EncryptionError> {
+        let key_id = key_id.into();
+        let mut keys = HashMap::new();
+        keys.insert(key_id.clone(), Self::parse_key(&key_id, key)?);
+
+        Ok(Self {
+            primary_key_id: key_id,
+            keys,
+            allow_plaintext_reads: false,
+        })
+    }
+
+    /// Adds a key which is only used to decrypt items. Use this to keep items written with a
+    /// previous key readable after rotating to a new key.
+    pub fn decryption_key(
+        &mut self,
+        key_id: impl Into<String>,
+        key: &[u8],
+    ) -> Result<&mut Self, EncryptionError> {
+        let key_id = key_id.into();
+        if self.keys.contains_key(&key_id) {
+            return Err(EncryptionError::DuplicateKeyId(key_id));
+        }
+
+        let key = Self::parse_key(&key_id, key)?;
+        self.keys.insert(key_id, key);
+        Ok(self)
+    }
+
+    /// Specifies whether items which are not encrypted may still be read. This allows a store
+    /// populated before encryption was enabled to keep serving until the SDK next receives a full
+    /// data set, at which point every item is rewritten encrypted. The default is false, in which
+    /// case unencrypted items are treated as unreadable.
+    pub fn allow_plaintext_reads(&mut self, allow: bool) -> &mut Self {
+        self.allow_plaintext_reads = allow;
+        self
+    }
+
+    fn parse_key(key_id: &str, key: &[u8]) -> Result<Arc<LessSafeKey>, EncryptionError> {
+        if key_id.is_empty()
+            || !key_id
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        {
+            return Err(EncryptionError::InvalidKeyId(key_id.to_string()));
+        }
+
+        let unbound_key =
+            UnboundKey::new(&AES_256_GCM, key).map_err(|_| EncryptionError::InvalidKeyLength {
+                expected: AES_256_GCM.key_len(),
+                actual: key.len(),
+            })?;
+
+        Ok(Arc::new(LessSafeKey::new(unbound_key)))
+    }
+}
+
+/// An [AsyncPersistentDataStore] which encrypts items before passing them to another store, and
+/// decrypts them as they are read back.
+pub(super) struct EncryptedPersistentDataStore {
+    store: Arc<dyn AsyncPersistentDataStore>,
+    encryption: PersistentStoreEncryption,
+    rng: SystemRandom,
+}
+
+impl EncryptedPersistentDataStore {
+    pub(super) fn new(
+        store: Arc<dyn AsyncPersistentDataStore>,
+        encryption: PersistentStoreEncryption,
+    ) -> Self {
+        Self {
+            store,
+            encryption,
+            rng: SystemRandom::new(),
+        }
+    }
+
+    fn encrypt(
+        &self,
+        kind: DataKind,
+        key: &str,
+        item: SerializedItem,
+    ) -> Result<SerializedItem, PersistentStoreError> {
+        let key_id = &self.encryption.primary_key_id;
+        let sealing_key = &self.encryption.keys[key_id];
+
+        let mut nonce_bytes = [0u8; NONCE_LEN];
+        self.rng
+            .fill(&mut nonce_bytes)
+            .map_err(|_| PersistentStoreError::new("failed to generate encryption nonce"))?;
+
+        let mut in_out = item.serialized_item.into_bytes();
+        sealing_key
+            .seal_in_place_append_tag(
+                Nonce::assume_unique_for_key(nonce_bytes),
+                Aad::from(associated_data(kind, key, item.version, item.deleted)),
+                &mut in_out,
+            )
+            .map_err(|_| PersistentStoreError::new("failed to encrypt item"))?;
+
+        let mut payload = nonce_bytes.to_vec();
+        payload.extend_from_slice(&in_out);
+
+        Ok(SerializedItem {
+            version: item.version,
+            deleted: item.deleted,
+            serialized_item: format!("{}:{}:{}", ENVELOPE_PREFIX, key_id, BASE64.encode(&payload)),
+        })
+    }
+
+    fn decrypt(
+        &self,
+        kind: DataKind,
+        key: &str,
+        item: SerializedItem,
+    ) -> Result<SerializedItem, PersistentStoreError> {
+        let envelope = match item.serialized_item.strip_prefix(ENVELOPE_PREFIX) {
+            Some(envelope) => envelope,
+            None if self.encryption.allow_plaintext_reads => return Ok(item),
+            None => {
+                return Err(PersistentStoreError::new(format!(
+                    "item {} is not encrypted",
+                    key
+                )))
+            }
+        };
+
+        let (key_id, payload) = envelope
+            .strip_prefix(':')
+            .and_then(|envelope| envelope.split_once(':'))
+            .ok_or_else(|| PersistentStoreError::new(format!("item {} is malformed", key)))?;
+        let opening_key = self.encryption.keys.get(key_id).ok_or_else(|| {
+            PersistentStoreError::new(format!(
+                "item {} was encrypted with unknown key {:?}",
+                key, key_id
+            ))
+        })?;
+
+        let mut payload = BASE64
+            .decode(payload.as_bytes())
+            .map_err(|e| PersistentStoreError::new(format!("item {} is malformed: {}", key, e)))?;
+        if payload.len() < NONCE_LEN {
+            return Err(PersistentStoreError::new(format!(
+                "item {} is malformed",
+                key
+            )));
+        }
+        let (nonce_bytes, in_out) = payload.split_at_mut(NONCE_LEN);
+        let nonce = Nonce::try_assume_unique_for_key(nonce_bytes)
+            .map_err(|_| PersistentStoreError::new(format!("item {} is malformed", key)))?;
+
+        let plaintext = opening_key
+            .open_in_place(
+                nonce,
+                Aad::from(associated_data(kind, key, item.version, item.deleted)),
+                in_out,
+            )
+            .map_err(|_| {
+                PersistentStoreError::new(format!("item {} failed authentication", key))
+            })?;
+        let serialized_item = String::from_utf8(plaintext.to_vec()).map_err(|e| {
+            PersistentStoreError::new(format!("item {} is not valid UTF-8: {}", key, e))
+        })?;
+
+        Ok(SerializedItem {
+            version: item.version,
+            deleted: item.deleted,
+            serialized_item,
+        })
+    }
+
+    fn encrypt_all(
+        &self,
+        kind: DataKind,
+        items: HashMap<String, SerializedItem>,
+    ) -> Result<HashMap<String, SerializedItem>, PersistentStoreError> {
+        items
+            .into_iter()
+            .map(|(key, item)| {
+                let encrypted = self.encrypt(kind, &key, item)?;
+                Ok((key, encrypted))
+            })
+            .collect()
+    }
+}
+
+fn associated_data(kind: DataKind, key: &str, version: u64, deleted: bool) -> Vec<u8> {
+    let kind = match kind {
+        DataKind::Flag => "flags",
+        DataKind::Segment => "segments",
+    };
+    format!("{}:{}:{}:{}", kind, version, deleted, key).into_bytes()
+}
+
+impl AsyncPersistentDataStore for EncryptedPersistentDataStore {
+    fn init(
+        &self,
+        all_data: AllData<SerializedItem, SerializedItem>,
+    ) -> BoxFuture<'_, Result<(), PersistentStoreError>> {
+        async move {
+            let encrypted = AllData {
+                flags: self.encrypt_all(DataKind::Flag, all_data.flags)?,
+                segments: self.encrypt_all(DataKind::Segment, all_data.segments)?,
+            };
+            self.store.init(encrypted).await
+        }
+        .boxed()
+    }
+
+    fn flag<'a>(
+        &'a self,
+        key: &'a str,
+    ) -> BoxFuture<'a, Result<Option<SerializedItem>, PersistentStoreError>> {
+        async move {
+            self.store
+                .flag(key)
+                .await?
+                .map(|item| self.decrypt(DataKind::Flag, key, item))
+                .transpose()
+        }
+        .boxed()
+    }
+
+    fn segment<'a>(
+        &'a self,
+        key: &'a str,
+    ) -> BoxFuture<'a, Result<Option<SerializedItem>, PersistentStoreError>> {
+        async move {
+            self.store
+                .segment(key)
+                .await?
+                .map(|item| self.decrypt(DataKind::Segment, key, item))
+                .transpose()
+        }
+        .boxed()
+    }
+
+    fn all_flags(
+        &self,
+    ) -> BoxFuture<'_, Result<HashMap<String, SerializedItem>, PersistentStoreError>> {
+        async move {
+            self.store
+                .all_flags()
+                .await?
+                .into_iter()
+                .map(|(key, item)| {
+                    let decrypted = self.decrypt(DataKind::Flag, &key, item)?;
+                    Ok((key, decrypted))
+                })
+                .collect()
+        }
+        .boxed()
+    }
+
+    fn upsert<'a>(
+        &'a self,
+        kind: DataKind,
+        key: &'a str,
+        serialized_item: SerializedItem,
+    ) -> BoxFuture<'a, Result<bool, PersistentStoreError>> {
+        async move {
+            let encrypted = self.encrypt(kind, key, serialized_item)?;
+            self.store.upsert(kind, key, encrypted).await
+        }
+        .boxed()
+    }
+
+    fn is_initialized(&self) -> BoxFuture<'_, bool> {
+        self.store.is_initialized()
+    }
+
+    fn remove_tombstone<'a>(
+        &'a self,
+        kind: DataKind,
+        key: &'a str,
+        version: u64,
+    ) -> BoxFuture<'a, Result<bool, PersistentStoreError>> {
+        self.store.remove_tombstone(kind, key, version)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::convert::TryFrom;
+
+    use maplit::hashmap;
+
+    use super::*;
+    use crate::stores::persistent_store::tests::SharedPersistentDataStore;
+    use crate::stores::persistent_store::{PersistentDataStore, PersistentDataStoreAdapter};
+    use crate::stores::store_types::StorageItem;
+    use crate::test_common::basic_flag;
+
+    const OLD_KEY: [u8; 32] = [1; 32];
+    const NEW_KEY: [u8; 32] = [2; 32];
+
+    fn serialized_flag(key: &str, version: u64) -> SerializedItem {
+        let mut flag = basic_flag(key);
+        flag.version = version;
+        SerializedItem::try_from(StorageItem::Item(flag)).unwrap()
+    }
+
+    fn encrypted_store(
+        encryption: PersistentStoreEncryption,
+    ) -> (SharedPersistentDataStore, EncryptedPersistentDataStore) {
+        let inner = SharedPersistentDataStore::new(AllData {
+            flags: HashMap::new(),
+            segments: HashMap::new(),
+        });
+        let store = EncryptedPersistentDataStore::new(
+            Arc::new(PersistentDataStoreAdapter::new(Box::new(inner.clone()))),
+            encryption,
+        );
+        (inner, store)
+    }
+
+    #[tokio::test]
+    async fn items_are_encrypted_at_rest() {
+        let (inner, store) =
+            encrypted_store(PersistentStoreEncryption::new("old", &OLD_KEY).unwrap());
+        store
+            .init(AllData {
+                flags: hashmap!["flag".into() => serialized_flag("flag", 1)],
+                segments: HashMap::new(),
+            })
+            .await
+            .unwrap();
+        store
+            .upsert(DataKind::Flag, "other", serialized_flag("other", 2))
+            .await
+            .unwrap();
+
+        for key in ["flag", "other"] {
+            let raw = inner.flag(key).unwrap().unwrap();
+            assert!(raw.serialized_item.starts_with("$ldenc1:old:"));
+            assert!(!raw.serialized_item.contains(key));
+        }
+        assert_eq!(2, inner.flag("other").unwrap().unwrap().version);
+
+        let flag = store.flag("flag").await.unwrap().unwrap();
+        assert_eq!(
+            serialized_flag("flag", 1).serialized_item,
+            flag.serialized_item
+        );
+        assert_eq!(2, store.all_flags().await.unwrap().len());
+    }
+
+    #[tokio::test]
+    async fn items_remain_readable_after_key_rotation() {
+        let (inner, store) =
+            encrypted_store(PersistentStoreEncryption::new("old", &OLD_KEY).unwrap());
+        store
+            .upsert(DataKind::Flag, "flag", serialized_flag("flag", 1))
+            .await
+            .unwrap();
+
+        let mut encryption = PersistentStoreEncryption::new("new", &NEW_KEY).unwrap();
+        encryption.decryption_key("old", &OLD_KEY).unwrap();
+        let rotated = EncryptedPersistentDataStore::new(
+            Arc::new(PersistentDataStoreAdapter::new(Box::new(inner.clone()))),
+            encryption,
+        );
+
+        assert!(rotated.flag("flag").await.unwrap().is_some());
+        rotated
+            .upsert(DataKind::Flag, "flag", serialized_flag("flag", 2))
+            .await
+            .unwrap();
+        assert!(inner
+            .flag("flag")
+            .unwrap()
+            .unwrap()
+            .serialized_item
+            .starts_with("$ldenc1:new:"));
+
+        // The old configuration cannot read items written with the new key.
+        assert!(store.flag("flag").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn tampered_items_are_rejected() {
+        let (mut inner, store) =
+            encrypted_store(PersistentStoreEncryption::new("old", &OLD_KEY).unwrap());
+        store
+            .upsert(DataKind::Flag, "flag", serialized_flag("flag", 1))
+            .await
+            .unwrap();
+
+        // Replaying an item under another key or version fails authentication.
+        let mut raw = inner.flag("flag").unwrap().unwrap();
+        inner.upsert(DataKind::Flag, "other", raw.clone()).unwrap();
+        assert!(store.flag("other").await.is_err());
+
+        raw.version = 5;
+        inner.upsert(DataKind::Flag, "flag", raw).unwrap();
+        assert!(store.flag("flag").await.is_err());
+        assert!(store.segment("flag").await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn plaintext_items_are_only_read_when_allowed() {
+        let mut encryption = PersistentStoreEncryption::new("old", &OLD_KEY).unwrap();
+        let (mut inner, store) = encrypted_store(encryption.clone());
+        inner
+            .upsert(DataKind::Flag, "flag", serialized_flag("flag", 1))
+            .unwrap();
+        assert!(store.flag("flag").await.is_err());
+
+        encryption.allow_plaintext_reads(true);
+        let permissive = EncryptedPersistentDataStore::new(
+            Arc::new(PersistentDataStoreAdapter::new(Box::new(inner.clone()))),
+            encryption,
+        );
+        assert!(permissive.flag("flag").await.unwrap().is_some());
+    }
+
+    #[test]
+    fn invalid_configuration_is_rejected() {
+        assert!(matches!(
+            PersistentStoreEncryption::new("key", &[0; 16]),
+            Err(EncryptionError::InvalidKeyLength {
+                expected: 32,
+                actual: 16
+            })
+        ));
+        assert!(matches!(
+            PersistentStoreEncryption::new("bad:id", &OLD_KEY),
+            Err(EncryptionError::InvalidKeyId(_))
+        ));
+        assert!(matches!(
+            PersistentStoreEncryption::new("old", &OLD_KEY)
+                .unwrap()
+                .decryption_key("old", &NEW_KEY),
+            Err(EncryptionError::DuplicateKeyId(_))
+        ));
+    }
+}
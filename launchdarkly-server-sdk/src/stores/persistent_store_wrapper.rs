@@ -1,16 +1,26 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::convert::{TryFrom, TryInto};
+use std::future::Future;
 use std::iter::FromIterator;
+use std::sync::{Arc, OnceLock};
 use std::time::Duration;
 
+use futures::future::{BoxFuture, FutureExt};
 use launchdarkly_server_sdk_evaluation::{Flag, Segment, Store};
+use parking_lot::Mutex;
+use tokio::runtime::{Handle, Runtime, RuntimeFlavor};
 
-use super::persistent_store::PersistentDataStore;
+use super::persistent_store::AsyncPersistentDataStore;
+#[cfg(any(test, feature = "store-testing"))]
+use super::persistent_store::{PersistentDataStore, PersistentDataStoreAdapter};
+use super::persistent_store_builders::PersistentDataStoreStats;
 use super::persistent_store_cache::CachePair;
-use super::store::{DataStore, UpdateError};
+use super::single_flight::SingleFlight;
+use super::store::{AsyncStore, DataStore, UpdateError};
 use super::store_types::{
     AllData, DataKind, PatchTarget, SerializeToSerializedItem, SerializedItem, StorageItem,
 };
+use super::tombstones::TombstoneTracker;
 
 trait WithKind {
     const KIND: DataKind;
@@ -24,23 +34,226 @@ impl WithKind for StorageItem<Segment> {
     const KIND: DataKind = DataKind::Segment;
 }
 
+/// A runtime for store operations and refreshes which are started outside of a runtime that can
+/// run them. It is created on first use and lives for the rest of the process.
+fn store_runtime() -> &'static Runtime {
+    static STORE_RUNTIME: OnceLock<Runtime> = OnceLock::new();
+    STORE_RUNTIME.get_or_init(|| {
+        tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(1)
+            .thread_name("ld-persistent-store")
+            .enable_all()
+            .build()
+            .expect("failed to start the persistent store runtime")
+    })
+}
+
+/// Removes a background refresh from the set of in-flight refreshes once it finishes, or once it
+/// is dropped because the runtime it was spawned on shut down.
+struct RefreshGuard {
+    refreshing: Arc<Mutex<HashSet<String>>>,
+    key: String,
+}
+
+impl Drop for RefreshGuard {
+    fn drop(&mut self) {
+        self.refreshing.lock().remove(&self.key);
+    }
+}
+
+#[derive(Clone)]
 pub(super) struct PersistentDataStoreWrapper {
-    store: Box<dyn PersistentDataStore>,
+    store: Arc<dyn AsyncPersistentDataStore>,
     flags: CachePair<Flag>,
     segments: CachePair<Segment>,
+    refreshing: Arc<Mutex<HashSet<String>>>,
+    runtime: Option<Handle>,
+    synchronous: bool,
+    flag_loads: Arc<SingleFlight<Option<Flag>>>,
+    segment_loads: Arc<SingleFlight<Option<Segment>>>,
+    all_flags_loads: Arc<SingleFlight<HashMap<String, Flag>>>,
+    tombstones: Arc<Mutex<TombstoneTracker>>,
 }
 
 impl PersistentDataStoreWrapper {
+    #[cfg(any(test, feature = "store-testing"))]
     pub(super) fn new(store: Box<dyn PersistentDataStore>, cache_ttl: Option<Duration>) -> Self {
+        Self::new_async(Arc::new(PersistentDataStoreAdapter::new(store)), cache_ttl).synchronous()
+    }
+
+    pub(super) fn new_async(
+        store: Arc<dyn AsyncPersistentDataStore>,
+        cache_ttl: Option<Duration>,
+    ) -> Self {
         Self {
             store,
-            flags: CachePair::new(String::from("flags"), cache_ttl),
-            segments: CachePair::new(String::from("segments"), cache_ttl),
+            flags: CachePair::new(String::from("flags"), cache_ttl, false),
+            segments: CachePair::new(String::from("segments"), cache_ttl, false),
+            refreshing: Arc::new(Mutex::new(HashSet::new())),
+            runtime: Handle::try_current().ok(),
+            synchronous: false,
+            flag_loads: SingleFlight::new(Default::default()),
+            segment_loads: SingleFlight::new(Default::default()),
+            all_flags_loads: SingleFlight::new(Default::default()),
+            tombstones: Arc::new(Mutex::new(TombstoneTracker::new(None))),
         }
     }
 
-    fn upsert_storage_item<T>(
-        &mut self,
+    /// Remove deleted item placeholders from the store once they are older than `retention`.
+    pub(super) fn tombstone_retention(mut self, retention: Option<Duration>) -> Self {
+        self.tombstones = Arc::new(Mutex::new(TombstoneTracker::new(retention)));
+        self
+    }
+
+    async fn purge_expired_tombstones(&self) {
+        let expired = self.tombstones.lock().take_expired();
+
+        for tombstone in expired {
+            let result = self
+                .store
+                .remove_tombstone(tombstone.kind, &tombstone.key, tombstone.version)
+                .await;
+
+            match result {
+                Ok(true) => {
+                    debug!(
+                        "purged deleted {:?} placeholder {}",
+                        tombstone.kind, tombstone.key
+                    );
+                    match tombstone.kind {
+                        DataKind::Flag => self.flags.forget(&tombstone.key),
+                        DataKind::Segment => self.segments.forget(&tombstone.key),
+                    }
+                }
+                Ok(false) => (),
+                Err(e) => warn!(
+                    "persistent store failed to purge deleted placeholder {}: {}",
+                    tombstone.key, e
+                ),
+            }
+        }
+    }
+
+    /// Mark the store as one which is adapted from a [PersistentDataStore], and so never needs a
+    /// runtime to complete its operations.
+    pub(super) fn synchronous(mut self) -> Self {
+        self.synchronous = true;
+        self
+    }
+
+    /// Record how often concurrent cache misses are coalesced into a single store read in the
+    /// provided stats.
+    pub(super) fn stats(mut self, stats: &PersistentDataStoreStats) -> Self {
+        self.flag_loads = SingleFlight::new(stats.coalesced_flag_reads.clone());
+        self.segment_loads = SingleFlight::new(stats.coalesced_segment_reads.clone());
+        self.all_flags_loads = SingleFlight::new(stats.coalesced_all_flags_reads.clone());
+        self
+    }
+
+    /// Serve cached items which have outlived the cache TTL, rather than evicting them, while
+    /// refreshing them from the store in the background.
+    pub(super) fn stale_while_revalidate(mut self, cache_ttl: Option<Duration>) -> Self {
+        self.flags = CachePair::new(String::from("flags"), cache_ttl, true);
+        self.segments = CachePair::new(String::from("segments"), cache_ttl, true);
+        self
+    }
+
+    /// Drives a store operation to completion from synchronous code.
+    ///
+    /// Operations on synchronous stores complete on their first poll, unless they wait on a
+    /// concurrent load of the same item, so they never need a runtime to be driven. Otherwise, on
+    /// a multi-threaded tokio runtime the worker thread is handed over to the runtime while we
+    /// wait, so other tasks (including the store's own I/O) can make progress.
+    ///
+    /// A current-thread runtime cannot make progress while its only thread is blocked here, so in
+    /// that case, and outside of any runtime, operations on asynchronous stores are run on the
+    /// SDK's own store runtime instead, from a separate thread if the caller is already within a
+    /// runtime. Stores which depend on I/O resources bound to the application's current-thread
+    /// runtime cannot complete there, and should be used through the asynchronous evaluation
+    /// methods instead.
+    fn block_on<F>(&self, future: F) -> F::Output
+    where
+        F: Future + Send,
+        F::Output: Send,
+    {
+        let mut future = std::pin::pin!(future);
+
+        match Handle::try_current() {
+            Ok(handle) if handle.runtime_flavor() == RuntimeFlavor::MultiThread => {
+                if let Some(output) = (&mut future).now_or_never() {
+                    return output;
+                }
+                tokio::task::block_in_place(|| handle.block_on(future))
+            }
+            _ if self.synchronous => futures::executor::block_on(future),
+            current => {
+                // Timers and I/O are registered with the runtime which first polls them, so even
+                // the first poll has to happen within the store runtime.
+                let runtime = store_runtime();
+                let first_poll = {
+                    let _guard = runtime.enter();
+                    (&mut future).now_or_never()
+                };
+                if let Some(output) = first_poll {
+                    return output;
+                }
+                if current.is_err() {
+                    return runtime.block_on(future);
+                }
+                std::thread::scope(|scope| {
+                    scope
+                        .spawn(|| runtime.block_on(future))
+                        .join()
+                        .unwrap_or_else(|panic| std::panic::resume_unwind(panic))
+                })
+            }
+        }
+    }
+
+    /// Populates the cache of all flags from the store, if the store has been initialized.
+    pub(super) fn prewarm(&self) {
+        self.block_on(async {
+            if self.store.is_initialized().await {
+                self.load_all_flags().await;
+                debug!("flag cache has been pre-warmed from the persistent store");
+            }
+        })
+    }
+
+    /// Runs a refresh of a stale cache entry in the background, unless a refresh of the same entry
+    /// is already underway.
+    ///
+    /// Refreshes run on the current tokio runtime, falling back to the runtime the store was
+    /// created on, and finally to the SDK's own store runtime if neither is available.
+    fn refresh_in_background<F>(&self, refresh_key: String, refresh: F)
+    where
+        F: Future<Output = ()> + Send + 'static,
+    {
+        if !self.refreshing.lock().insert(refresh_key.clone()) {
+            return;
+        }
+
+        let guard = RefreshGuard {
+            refreshing: self.refreshing.clone(),
+            key: refresh_key,
+        };
+        let task = async move {
+            refresh.await;
+            drop(guard);
+        };
+
+        match Handle::try_current().ok().or_else(|| self.runtime.clone()) {
+            Some(handle) => {
+                handle.spawn(task);
+            }
+            None => {
+                store_runtime().spawn(task);
+            }
+        }
+    }
+
+    async fn upsert_storage_item<T>(
+        &self,
         key: &str,
         data: StorageItem<T>,
     ) -> Result<bool, UpdateError>
@@ -48,10 +261,23 @@ impl PersistentDataStoreWrapper {
         StorageItem<T>: WithKind,
         StorageItem<T>: SerializeToSerializedItem,
     {
+        let tombstone_version = match data {
+            StorageItem::Tombstone(version) => Some(version),
+            StorageItem::Item(_) => None,
+        };
         let serialized = data
             .serialize_to_serialized_item()
             .map_err(UpdateError::ParseError)?;
-        let was_updated = self.store.upsert(StorageItem::<T>::KIND, key, serialized)?;
+        let was_updated = self
+            .store
+            .upsert(StorageItem::<T>::KIND, key, serialized)
+            .await?;
+
+        if let (true, Some(version)) = (was_updated, tombstone_version) {
+            self.tombstones
+                .lock()
+                .track(StorageItem::<T>::KIND, key, version);
+        }
 
         Ok(was_updated)
     }
@@ -64,14 +290,10 @@ impl PersistentDataStoreWrapper {
     ) {
         if was_updated {
             cache.insert_single(data.clone(), key);
-            // If the cache is infinite, we need to update the all flags cache. Otherwise, we can
-            // just invalidate the cache and let it re-populate the next time it is required.
-            if cache.cache_is_infinite() {
-                if let Some(mut map) = cache.get_all() {
-                    map.insert(key.to_string(), data);
-                    cache.insert_all(map);
-                }
-            } else {
+            // If the cache is infinite, or keeps serving entries while they are refreshed, we need
+            // to update the all flags cache. Otherwise, we can just invalidate the cache and let it
+            // re-populate the next time it is required.
+            if !cache.update_in_all(key, data) {
                 cache.invalidate_all();
             }
         } else {
@@ -81,11 +303,19 @@ impl PersistentDataStoreWrapper {
     }
 
     fn upsert_flag(&mut self, flag_key: &str, data: StorageItem<Flag>) -> Result<(), UpdateError> {
-        let was_updated = self.upsert_storage_item(flag_key, data.clone())?;
+        self.block_on(self.upsert_flag_async(flag_key, data))
+    }
+
+    async fn upsert_flag_async(
+        &self,
+        flag_key: &str,
+        data: StorageItem<Flag>,
+    ) -> Result<(), UpdateError> {
+        let was_updated = self.upsert_storage_item(flag_key, data.clone()).await?;
 
         Self::add_to_cache(was_updated, &self.flags, flag_key, data);
         if !was_updated {
-            let _ = self.flag(flag_key); // Force repopulating the cache
+            let _ = self.flag_async(flag_key).await; // Force repopulating the cache
         }
 
         Ok(())
@@ -96,11 +326,19 @@ impl PersistentDataStoreWrapper {
         segment_key: &str,
         data: StorageItem<Segment>,
     ) -> Result<(), UpdateError> {
-        let was_updated = self.upsert_storage_item(segment_key, data.clone())?;
+        self.block_on(self.upsert_segment_async(segment_key, data))
+    }
+
+    async fn upsert_segment_async(
+        &self,
+        segment_key: &str,
+        data: StorageItem<Segment>,
+    ) -> Result<(), UpdateError> {
+        let was_updated = self.upsert_storage_item(segment_key, data.clone()).await?;
 
         Self::add_to_cache(was_updated, &self.segments, segment_key, data);
         if !was_updated {
-            let _ = self.segment(segment_key); // Force repopulating the cache
+            let _ = self.segment_async(segment_key).await; // Force repopulating the cache
         }
 
         Ok(())
@@ -127,21 +365,43 @@ impl PersistentDataStoreWrapper {
         self.cache_segments(all_data.segments);
         debug!("flag and segment caches have been updated");
     }
-}
 
-impl Store for PersistentDataStoreWrapper {
-    fn flag(&self, key: &str) -> Option<Flag> {
-        if let Some(item) = self.flags.get_one(key) {
-            return item.into();
+    async fn flag_async(&self, key: &str) -> Option<Flag> {
+        if let Some(hit) = self.flags.get_one(key) {
+            if hit.stale {
+                let wrapper = self.clone();
+                let key = key.to_string();
+                self.refresh_in_background(format!("flag:{}", key), async move {
+                    wrapper.load_flag(&key).await;
+                });
+            }
+            return hit.value.into();
         }
 
-        match self.store.flag(key) {
+        self.load_flag(key).await
+    }
+
+    /// Reads a flag from the store into the cache. Concurrent loads of the same flag share a single
+    /// store read.
+    async fn load_flag(&self, key: &str) -> Option<Flag> {
+        let wrapper = self.clone();
+        let owned_key = key.to_string();
+        self.flag_loads
+            .load(
+                key,
+                move || async move { wrapper.fetch_flag(&owned_key).await },
+            )
+            .await
+    }
+
+    async fn fetch_flag(&self, key: &str) -> Option<Flag> {
+        match self.store.flag(key).await {
             Ok(Some(serialized_item)) => {
                 let storage_item: Result<StorageItem<Flag>, serde_json::Error> =
                     serialized_item.try_into();
                 match storage_item {
                     Ok(item) => {
-                        self.flags.insert_single(item.clone(), key);
+                        self.flags.insert_single_unless_newer(item.clone(), key);
                         item.into()
                     }
                     Err(e) => {
@@ -150,7 +410,10 @@ impl Store for PersistentDataStoreWrapper {
                     }
                 }
             }
-            Ok(None) => None,
+            Ok(None) => {
+                self.flags.invalidate_single(key);
+                None
+            }
             Err(e) => {
                 warn!("persistent store failed to retrieve flag: {}", e);
                 None
@@ -158,18 +421,41 @@ impl Store for PersistentDataStoreWrapper {
         }
     }
 
-    fn segment(&self, key: &str) -> Option<Segment> {
-        if let Some(item) = self.segments.get_one(key) {
-            return item.into();
+    async fn segment_async(&self, key: &str) -> Option<Segment> {
+        if let Some(hit) = self.segments.get_one(key) {
+            if hit.stale {
+                let wrapper = self.clone();
+                let key = key.to_string();
+                self.refresh_in_background(format!("segment:{}", key), async move {
+                    wrapper.load_segment(&key).await;
+                });
+            }
+            return hit.value.into();
         }
 
-        match self.store.segment(key) {
+        self.load_segment(key).await
+    }
+
+    /// Reads a segment from the store into the cache. Concurrent loads of the same segment share a
+    /// single store read.
+    async fn load_segment(&self, key: &str) -> Option<Segment> {
+        let wrapper = self.clone();
+        let owned_key = key.to_string();
+        self.segment_loads
+            .load(key, move || async move {
+                wrapper.fetch_segment(&owned_key).await
+            })
+            .await
+    }
+
+    async fn fetch_segment(&self, key: &str) -> Option<Segment> {
+        match self.store.segment(key).await {
             Ok(Some(serialized_item)) => {
                 let storage_item: Result<StorageItem<Segment>, serde_json::Error> =
                     serialized_item.try_into();
                 match storage_item {
                     Ok(item) => {
-                        self.segments.insert_single(item.clone(), key);
+                        self.segments.insert_single_unless_newer(item.clone(), key);
                         item.into()
                     }
                     Err(e) => {
@@ -178,19 +464,21 @@ impl Store for PersistentDataStoreWrapper {
                     }
                 }
             }
-            Ok(None) => None,
+            Ok(None) => {
+                self.segments.invalidate_single(key);
+                None
+            }
             Err(e) => {
                 warn!("persistent store failed to retrieve segment: {}", e);
                 None
             }
         }
     }
-}
 
-impl DataStore for PersistentDataStoreWrapper {
-    fn init(&mut self, all_data: AllData<Flag, Segment>) {
+    async fn init_async(&self, all_data: AllData<Flag, Segment>) {
         self.flags.invalidate_everything();
         self.segments.invalidate_everything();
+        self.tombstones.lock().clear();
 
         let serialized_data = AllData::<SerializedItem, SerializedItem>::try_from(all_data.clone());
 
@@ -200,7 +488,7 @@ impl DataStore for PersistentDataStoreWrapper {
                 e
             ),
             Ok(data) => {
-                let result = self.store.init(data);
+                let result = self.store.init(data).await;
 
                 match result {
                     Ok(()) => {
@@ -219,16 +507,37 @@ impl DataStore for PersistentDataStoreWrapper {
         }
     }
 
-    fn all_flags(&self) -> HashMap<String, Flag> {
-        if let Some(flag_items) = self.flags.get_all() {
-            let flag_iter = flag_items.into_iter().filter_map(|(key, item)| match item {
+    async fn all_flags_async(&self) -> HashMap<String, Flag> {
+        if let Some(hit) = self.flags.get_all() {
+            if hit.stale {
+                let wrapper = self.clone();
+                self.refresh_in_background(String::from("all:flags"), async move {
+                    wrapper.load_all_flags().await;
+                });
+            }
+            let flag_iter = hit.value.into_iter().filter_map(|(key, item)| match item {
                 StorageItem::Item(flag) => Some((key, flag)),
                 StorageItem::Tombstone(_) => None,
             });
             return HashMap::from_iter(flag_iter);
         }
 
-        match self.store.all_flags() {
+        self.load_all_flags().await
+    }
+
+    /// Reads all flags from the store into the cache. Concurrent loads share a single store read.
+    async fn load_all_flags(&self) -> HashMap<String, Flag> {
+        let wrapper = self.clone();
+        self.all_flags_loads
+            .load(
+                "all",
+                move || async move { wrapper.fetch_all_flags().await },
+            )
+            .await
+    }
+
+    async fn fetch_all_flags(&self) -> HashMap<String, Flag> {
+        match self.store.all_flags().await {
             Ok(serialized_flags) => {
                 let flags: Result<HashMap<String, StorageItem<Flag>>, serde_json::Error> =
                     serialized_flags
@@ -260,8 +569,40 @@ impl DataStore for PersistentDataStoreWrapper {
             }
         }
     }
+}
+
+impl Store for PersistentDataStoreWrapper {
+    fn flag(&self, key: &str) -> Option<Flag> {
+        self.block_on(self.flag_async(key))
+    }
+
+    fn segment(&self, key: &str) -> Option<Segment> {
+        self.block_on(self.segment_async(key))
+    }
+}
+
+impl AsyncStore for PersistentDataStoreWrapper {
+    fn flag<'a>(&'a self, key: &'a str) -> BoxFuture<'a, Option<Flag>> {
+        self.flag_async(key).boxed()
+    }
+
+    fn segment<'a>(&'a self, key: &'a str) -> BoxFuture<'a, Option<Segment>> {
+        self.segment_async(key).boxed()
+    }
+}
+
+impl DataStore for PersistentDataStoreWrapper {
+    fn init(&mut self, all_data: AllData<Flag, Segment>) {
+        self.block_on(self.init_async(all_data))
+    }
+
+    fn all_flags(&self) -> HashMap<String, Flag> {
+        self.block_on(self.all_flags_async())
+    }
 
     fn upsert(&mut self, key: &str, data: PatchTarget) -> Result<(), UpdateError> {
+        self.block_on(self.purge_expired_tombstones());
+
         match data {
             PatchTarget::Flag(item) => self.upsert_flag(key, item),
             PatchTarget::Segment(item) => self.upsert_segment(key, item),
@@ -275,23 +616,33 @@ impl DataStore for PersistentDataStoreWrapper {
     fn to_store(&self) -> &dyn Store {
         self
     }
+
+    fn to_async_store(&self) -> Option<Arc<dyn AsyncStore>> {
+        Some(Arc::new(self.clone()))
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use crate::stores::{
-        persistent_store::tests::InMemoryPersistentDataStore,
+        persistent_store::tests::{
+            InMemoryPersistentDataStore, SharedPersistentDataStore, YieldingPersistentDataStore,
+        },
+        persistent_store::PersistentDataStore,
         store::DataStore,
-        store_types::{PatchTarget, StorageItem},
+        store_types::{DataKind, PatchTarget, SerializedItem, StorageItem},
     };
-    use launchdarkly_server_sdk_evaluation::Store;
+    use launchdarkly_server_sdk_evaluation::{Flag, Store};
     use maplit::hashmap;
 
     use crate::stores::{persistent_store::tests::NullPersistentDataStore, store_types::AllData};
     use crate::test_common::{basic_flag, basic_segment};
-    use std::{collections::HashMap, time::Duration};
+    use std::{collections::HashMap, convert::TryFrom, sync::Arc, time::Duration};
+
+    use futures::future::join_all;
 
     use super::PersistentDataStoreWrapper;
+    use crate::stores::persistent_store_builders::PersistentDataStoreStats;
 
     #[test]
     fn can_retrieve_flags_without_cache() {
@@ -321,6 +672,105 @@ mod tests {
         assert_eq!(segment.key, "segment");
     }
 
+    #[tokio::test]
+    async fn can_retrieve_items_from_async_store() {
+        let store = YieldingPersistentDataStore::new(InMemoryPersistentDataStore {
+            data: AllData {
+                flags: HashMap::new(),
+                segments: HashMap::new(),
+            },
+            initialized: false,
+        });
+
+        let wrapper =
+            PersistentDataStoreWrapper::new_async(Arc::new(store), Some(Duration::from_secs(0)));
+
+        let all_data = AllData {
+            flags: hashmap!["flag".into() => basic_flag("flag")],
+            segments: hashmap!["segment".into() => basic_segment("segment")],
+        };
+
+        wrapper.init_async(all_data).await;
+        assert_eq!(1, wrapper.all_flags_async().await.len());
+
+        let flag = wrapper.flag_async("flag").await.unwrap();
+        assert_eq!(flag.key, "flag");
+        assert!(wrapper.flag_async("missing").await.is_none());
+
+        let segment = wrapper.segment_async("segment").await.unwrap();
+        assert_eq!(segment.key, "segment");
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn sync_reads_from_async_store_do_not_panic_within_runtime() {
+        let store = YieldingPersistentDataStore::new(InMemoryPersistentDataStore {
+            data: AllData {
+                flags: HashMap::new(),
+                segments: HashMap::new(),
+            },
+            initialized: false,
+        });
+
+        let mut wrapper =
+            PersistentDataStoreWrapper::new_async(Arc::new(store), Some(Duration::from_secs(0)));
+
+        wrapper.init(AllData {
+            flags: hashmap!["flag".into() => basic_flag("flag")],
+            segments: HashMap::new(),
+        });
+
+        assert_eq!(wrapper.flag("flag").unwrap().key, "flag");
+    }
+
+    #[tokio::test]
+    async fn sync_access_to_timer_based_store_completes_on_current_thread_runtime() {
+        let store = YieldingPersistentDataStore::sleeping(
+            InMemoryPersistentDataStore {
+                data: AllData {
+                    flags: HashMap::new(),
+                    segments: HashMap::new(),
+                },
+                initialized: false,
+            },
+            Duration::from_millis(10),
+        );
+
+        let mut wrapper =
+            PersistentDataStoreWrapper::new_async(Arc::new(store), Some(Duration::from_secs(0)));
+
+        wrapper.init(AllData {
+            flags: hashmap!["flag".into() => basic_flag("flag")],
+            segments: HashMap::new(),
+        });
+
+        assert_eq!(wrapper.flag("flag").unwrap().key, "flag");
+        assert_eq!(wrapper.all_flags().len(), 1);
+    }
+
+    #[test]
+    fn sync_access_to_timer_based_store_completes_outside_runtime() {
+        let store = YieldingPersistentDataStore::sleeping(
+            InMemoryPersistentDataStore {
+                data: AllData {
+                    flags: HashMap::new(),
+                    segments: HashMap::new(),
+                },
+                initialized: false,
+            },
+            Duration::from_millis(10),
+        );
+
+        let mut wrapper =
+            PersistentDataStoreWrapper::new_async(Arc::new(store), Some(Duration::from_secs(0)));
+
+        wrapper.init(AllData {
+            flags: hashmap!["flag".into() => basic_flag("flag")],
+            segments: HashMap::new(),
+        });
+
+        assert_eq!(wrapper.flag("flag").unwrap().key, "flag");
+    }
+
     #[test]
     fn retrieving_flags_uses_cache() {
         let store = NullPersistentDataStore { initialized: false };
@@ -458,7 +908,7 @@ mod tests {
         let result = wrapper.upsert_segment("segment", StorageItem::Item(updated_segment));
         assert!(result.is_ok());
 
-        let segments = wrapper.segments.get_all().unwrap();
+        let segments = wrapper.segments.get_all().unwrap().value;
         let retrieved_segment = segments.get("segment").unwrap();
 
         match retrieved_segment {
@@ -466,4 +916,191 @@ mod tests {
             _ => panic!("Failed to retrieve correct segment"),
         };
     }
+
+    fn serialized_flag(key: &str, version: u64) -> SerializedItem {
+        let mut flag = basic_flag(key);
+        flag.version = version;
+        SerializedItem::try_from(StorageItem::Item(flag)).unwrap()
+    }
+
+    fn shared_store_with_flag(version: u64) -> SharedPersistentDataStore {
+        SharedPersistentDataStore::new(AllData {
+            flags: hashmap!["flag".into() => serialized_flag("flag", version)],
+            segments: HashMap::new(),
+        })
+    }
+
+    async fn wait_for_flag_version(wrapper: &PersistentDataStoreWrapper, version: u64) {
+        for _ in 0..100 {
+            if wrapper.flag_async("flag").await.map(|f: Flag| f.version) == Some(version) {
+                return;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        panic!("flag was never refreshed to version {}", version);
+    }
+
+    #[tokio::test]
+    async fn stale_items_are_served_while_refreshing() {
+        let store = shared_store_with_flag(1);
+        let ttl = Some(Duration::from_millis(50));
+        let wrapper = PersistentDataStoreWrapper::new(Box::new(store.clone()), ttl)
+            .stale_while_revalidate(ttl);
+
+        assert_eq!(1, wrapper.flag_async("flag").await.unwrap().version);
+        assert_eq!(1, store.reads());
+
+        // Another process updates the store without going through this wrapper.
+        store
+            .clone()
+            .upsert(DataKind::Flag, "flag", serialized_flag("flag", 2))
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(60)).await;
+
+        // The expired item is still served, and a refresh is started in the background.
+        assert_eq!(1, wrapper.flag_async("flag").await.unwrap().version);
+        wait_for_flag_version(&wrapper, 2).await;
+        assert_eq!(2, store.reads());
+    }
+
+    #[tokio::test]
+    async fn expired_items_are_reloaded_without_stale_while_revalidate() {
+        let store = shared_store_with_flag(1);
+        let wrapper = PersistentDataStoreWrapper::new(
+            Box::new(store.clone()),
+            Some(Duration::from_millis(50)),
+        );
+
+        assert_eq!(1, wrapper.flag_async("flag").await.unwrap().version);
+        store
+            .clone()
+            .upsert(DataKind::Flag, "flag", serialized_flag("flag", 2))
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(60)).await;
+
+        assert_eq!(2, wrapper.flag_async("flag").await.unwrap().version);
+    }
+
+    #[test]
+    fn stale_items_are_refreshed_without_a_runtime() {
+        let store = shared_store_with_flag(1);
+        let ttl = Some(Duration::from_millis(50));
+        let wrapper = PersistentDataStoreWrapper::new(Box::new(store.clone()), ttl)
+            .stale_while_revalidate(ttl);
+
+        assert_eq!(1, wrapper.flag("flag").unwrap().version);
+        store
+            .clone()
+            .upsert(DataKind::Flag, "flag", serialized_flag("flag", 2))
+            .unwrap();
+        std::thread::sleep(Duration::from_millis(60));
+
+        assert_eq!(1, wrapper.flag("flag").unwrap().version);
+        for _ in 0..100 {
+            if wrapper.flag("flag").unwrap().version == 2 {
+                return;
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        panic!("flag was never refreshed");
+    }
+
+    #[test]
+    fn prewarm_populates_all_flags_cache() {
+        let store = shared_store_with_flag(1);
+        let ttl = Some(Duration::from_secs(100));
+        let wrapper = PersistentDataStoreWrapper::new(Box::new(store.clone()), ttl)
+            .stale_while_revalidate(ttl);
+
+        wrapper.prewarm();
+        assert_eq!(1, store.reads());
+
+        assert_eq!(1, wrapper.all_flags().len());
+        assert_eq!(1, wrapper.flag("flag").unwrap().version);
+        assert_eq!(1, store.reads());
+    }
+
+    #[test]
+    fn upserts_keep_prewarmed_all_flags_cache() {
+        let store = shared_store_with_flag(1);
+        let ttl = Some(Duration::from_secs(100));
+        let mut wrapper = PersistentDataStoreWrapper::new(Box::new(store.clone()), ttl)
+            .stale_while_revalidate(ttl);
+        wrapper.prewarm();
+
+        let mut updated = basic_flag("flag");
+        updated.version = 2;
+        wrapper
+            .upsert("flag", PatchTarget::Flag(StorageItem::Item(updated)))
+            .unwrap();
+
+        assert_eq!(2, wrapper.all_flags()["flag"].version);
+        assert_eq!(1, store.reads());
+    }
+
+    #[tokio::test]
+    async fn concurrent_misses_are_coalesced() {
+        let store = shared_store_with_flag(1);
+        let stats = PersistentDataStoreStats::default();
+        let wrapper = PersistentDataStoreWrapper::new_async(
+            Arc::new(YieldingPersistentDataStore::new(store.clone())),
+            Some(Duration::from_secs(100)),
+        )
+        .stats(&stats);
+
+        // Waiters which arrive after the shared read completes are served from the cache instead,
+        // so every waiter is either coalesced or a cache hit.
+        let flags = join_all((0..5).map(|_| wrapper.flag_async("flag"))).await;
+        assert!(flags.iter().all(|f| f.as_ref().unwrap().version == 1));
+        assert_eq!(1, store.reads());
+        assert!(stats.coalesced_flag_reads() >= 1);
+
+        let all_flags = join_all((0..3).map(|_| wrapper.all_flags_async())).await;
+        assert!(all_flags.iter().all(|flags| flags.len() == 1));
+        assert_eq!(2, store.reads());
+        assert!(stats.coalesced_all_flags_reads() >= 1);
+
+        let segments = join_all((0..2).map(|_| wrapper.segment_async("missing"))).await;
+        assert!(segments.iter().all(Option::is_none));
+        assert_eq!(3, store.reads());
+        assert_eq!(1, stats.coalesced_segment_reads());
+    }
+
+    #[test]
+    fn expired_tombstones_are_removed_from_store_and_cache() {
+        let store = shared_store_with_flag(1);
+        let mut wrapper = PersistentDataStoreWrapper::new(Box::new(store.clone()), None)
+            .tombstone_retention(Some(Duration::from_millis(20)));
+
+        wrapper
+            .upsert("flag", PatchTarget::Flag(StorageItem::Tombstone(2)))
+            .unwrap();
+        assert!(store.flag("flag").unwrap().unwrap().deleted);
+
+        std::thread::sleep(Duration::from_millis(30));
+        wrapper
+            .upsert("other", PatchTarget::Flag(StorageItem::Tombstone(1)))
+            .unwrap();
+
+        assert!(store.flag("flag").unwrap().is_none());
+        assert!(wrapper.flags.get_one("flag").is_none());
+        assert!(store.flag("other").unwrap().unwrap().deleted);
+    }
+
+    #[test]
+    fn init_forgets_tracked_tombstones() {
+        let store = shared_store_with_flag(1);
+        let mut wrapper = PersistentDataStoreWrapper::new(Box::new(store.clone()), None)
+            .tombstone_retention(Some(Duration::ZERO));
+
+        wrapper
+            .upsert("flag", PatchTarget::Flag(StorageItem::Tombstone(2)))
+            .unwrap();
+        wrapper.init(AllData {
+            flags: hashmap!["flag".into() => basic_flag("flag")],
+            segments: HashMap::new(),
+        });
+
+        assert!(wrapper.tombstones.lock().take_expired().is_empty());
+    }
 }
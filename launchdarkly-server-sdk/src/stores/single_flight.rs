@@ -0,0 +1,127 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Weak};
+
+use futures::future::{BoxFuture, FutureExt, Shared};
+use parking_lot::Mutex;
+
+/// Coalesces concurrent loads of the same key, so that only one of them does the work and every
+/// caller receives its result.
+pub(super) struct SingleFlight<V: Clone> {
+    in_flight: Mutex<HashMap<String, Shared<BoxFuture<'static, V>>>>,
+    coalesced: Arc<AtomicU64>,
+}
+
+impl<V: Clone + Send + Sync + 'static> SingleFlight<V> {
+    /// Creates a new instance which increments `coalesced` each time a caller joins a load which
+    /// is already in flight.
+    pub fn new(coalesced: Arc<AtomicU64>) -> Arc<Self> {
+        Arc::new(Self {
+            in_flight: Mutex::new(HashMap::new()),
+            coalesced,
+        })
+    }
+
+    /// Returns the result of the load in flight for `key`, or starts one by calling `load` if
+    /// there is none.
+    pub async fn load<F, Fut>(self: &Arc<Self>, key: &str, load: F) -> V
+    where
+        F: FnOnce() -> Fut,
+        Fut: Future<Output = V> + Send + 'static,
+    {
+        let shared = {
+            let mut in_flight = self.in_flight.lock();
+            match in_flight.get(key) {
+                Some(shared) => {
+                    self.coalesced.fetch_add(1, Ordering::Relaxed);
+                    shared.clone()
+                }
+                None => {
+                    let single_flight: Weak<Self> = Arc::downgrade(self);
+                    let owned_key = key.to_string();
+                    let future = load();
+                    let shared = async move {
+                        let value = future.await;
+                        // Later callers must not receive this result once it may be out of date,
+                        // so the load is forgotten before anyone observes its result.
+                        if let Some(single_flight) = single_flight.upgrade() {
+                            single_flight.in_flight.lock().remove(&owned_key);
+                        }
+                        value
+                    }
+                    .boxed()
+                    .shared();
+                    in_flight.insert(key.to_string(), shared.clone());
+                    shared
+                }
+            }
+        };
+
+        shared.await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::AtomicUsize;
+
+    use futures::channel::oneshot;
+    use futures::future::join_all;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn concurrent_loads_of_the_same_key_are_coalesced() {
+        let coalesced = Arc::new(AtomicU64::new(0));
+        let single_flight = SingleFlight::new(coalesced.clone());
+        let loads = Arc::new(AtomicUsize::new(0));
+        let (tx, rx) = oneshot::channel::<u64>();
+        let mut rx = Some(rx);
+
+        let waiters = (0..5)
+            .map(|_| {
+                let loads = loads.clone();
+                let rx = rx.take();
+                single_flight.load("key", move || async move {
+                    loads.fetch_add(1, Ordering::SeqCst);
+                    rx.expect("only the first load runs").await.unwrap()
+                })
+            })
+            .collect::<Vec<_>>();
+
+        let results = tokio::join!(join_all(waiters), async move {
+            tokio::task::yield_now().await;
+            tx.send(42).unwrap();
+        })
+        .0;
+
+        assert_eq!(vec![42; 5], results);
+        assert_eq!(1, loads.load(Ordering::SeqCst));
+        assert_eq!(4, coalesced.load(Ordering::Relaxed));
+    }
+
+    #[tokio::test]
+    async fn loads_of_different_keys_are_independent() {
+        let coalesced = Arc::new(AtomicU64::new(0));
+        let single_flight = SingleFlight::new(coalesced.clone());
+
+        let (a, b) = tokio::join!(
+            single_flight.load("a", || async { 1 }),
+            single_flight.load("b", || async { 2 })
+        );
+
+        assert_eq!((1, 2), (a, b));
+        assert_eq!(0, coalesced.load(Ordering::Relaxed));
+    }
+
+    #[tokio::test]
+    async fn completed_loads_are_not_reused() {
+        let coalesced = Arc::new(AtomicU64::new(0));
+        let single_flight = SingleFlight::new(coalesced.clone());
+
+        assert_eq!(1, single_flight.load("key", || async { 1 }).await);
+        assert_eq!(2, single_flight.load("key", || async { 2 }).await);
+        assert_eq!(0, coalesced.load(Ordering::Relaxed));
+    }
+}
@@ -0,0 +1,559 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use parking_lot::Mutex;
+use rusqlite::{params, Connection, OpenFlags, OptionalExtension};
+
+use super::persistent_store::{PersistentDataStore, PersistentStoreError};
+use super::persistent_store_builders::PersistentDataStoreFactory;
+use super::store_types::{AllData, DataKind, SerializedItem};
+
+const DEFAULT_BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+const DEFAULT_PREFIX: &str = "launchdarkly";
+const INITED_MARKER: &str = "$inited";
+
+impl From<rusqlite::Error> for PersistentStoreError {
+    fn from(error: rusqlite::Error) -> Self {
+        PersistentStoreError::new(format!("sqlite error: {}", error))
+    }
+}
+
+fn kind_name(kind: &DataKind) -> &'static str {
+    match kind {
+        DataKind::Flag => "features",
+        DataKind::Segment => "segments",
+    }
+}
+
+/// A [PersistentDataStore] backed by an embedded SQLite database.
+///
+/// The SQLite library is compiled into the SDK, so no system installation is required. Several
+/// processes on the same host may share a single database file; in that case one of them should
+/// receive updates from LaunchDarkly while the others run in daemon mode (see
+/// [crate::ConfigBuilder::daemon_mode]).
+///
+/// Instances are normally created through a [SqlitePersistentDataStoreBuilder], which is passed
+/// to a [crate::PersistentDataStoreBuilder].
+pub struct SqlitePersistentDataStore {
+    connection: Mutex<Connection>,
+    items_table: String,
+    meta_table: String,
+}
+
+impl SqlitePersistentDataStore {
+    /// Opens (or creates) the SQLite database at `path`, creating the required tables if they do
+    /// not yet exist.
+    ///
+    /// Table names are derived from `prefix`, which allows several LaunchDarkly environments to
+    /// share one database file.
+    pub fn open(
+        path: impl Into<PathBuf>,
+        prefix: &str,
+        busy_timeout: Duration,
+    ) -> Result<Self, PersistentStoreError> {
+        let connection = Connection::open_with_flags(
+            path.into(),
+            OpenFlags::SQLITE_OPEN_READ_WRITE
+                | OpenFlags::SQLITE_OPEN_CREATE
+                | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+        )?;
+        Self::from_connection(connection, prefix, busy_timeout)
+    }
+
+    /// Creates a store backed by a private in-memory database. The data is discarded when the
+    /// store is dropped, so this is mostly useful for testing.
+    pub fn in_memory(prefix: &str) -> Result<Self, PersistentStoreError> {
+        Self::from_connection(Connection::open_in_memory()?, prefix, DEFAULT_BUSY_TIMEOUT)
+    }
+
+    fn from_connection(
+        connection: Connection,
+        prefix: &str,
+        busy_timeout: Duration,
+    ) -> Result<Self, PersistentStoreError> {
+        if prefix.is_empty()
+            || !prefix
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_')
+        {
+            return Err(PersistentStoreError::new(
+                "sqlite table prefix must be non-empty and contain only ASCII letters, digits, and underscores",
+            ));
+        }
+
+        connection.busy_timeout(busy_timeout)?;
+        // WAL allows readers in other processes to proceed while an update is being written. The
+        // pragma reports the resulting mode, which we do not need.
+        connection.query_row("PRAGMA journal_mode=WAL", [], |_| Ok(()))?;
+
+        let store = Self {
+            connection: Mutex::new(connection),
+            items_table: format!("{}_items", prefix),
+            meta_table: format!("{}_meta", prefix),
+        };
+        store.create_tables()?;
+
+        Ok(store)
+    }
+
+    fn create_tables(&self) -> Result<(), PersistentStoreError> {
+        self.connection.lock().execute_batch(&format!(
+            "CREATE TABLE IF NOT EXISTS {items} (
+                kind TEXT NOT NULL,
+                key TEXT NOT NULL,
+                version INTEGER NOT NULL,
+                deleted INTEGER NOT NULL,
+                item TEXT NOT NULL,
+                PRIMARY KEY (kind, key)
+            );
+            CREATE TABLE IF NOT EXISTS {meta} (
+                key TEXT PRIMARY KEY NOT NULL,
+                value TEXT NOT NULL
+            );",
+            items = self.items_table,
+            meta = self.meta_table,
+        ))?;
+
+        Ok(())
+    }
+
+    fn get(
+        &self,
+        kind: DataKind,
+        key: &str,
+    ) -> Result<Option<SerializedItem>, PersistentStoreError> {
+        let connection = self.connection.lock();
+        let item = connection
+            .query_row(
+                &format!(
+                    "SELECT version, deleted, item FROM {} WHERE kind = ?1 AND key = ?2",
+                    self.items_table
+                ),
+                params![kind_name(&kind), key],
+                |row| {
+                    Ok(SerializedItem {
+                        version: row.get::<_, i64>(0)? as u64,
+                        deleted: row.get(1)?,
+                        serialized_item: row.get(2)?,
+                    })
+                },
+            )
+            .optional()?;
+
+        Ok(item)
+    }
+}
+
+impl PersistentDataStore for SqlitePersistentDataStore {
+    fn init(
+        &mut self,
+        all_data: AllData<SerializedItem, SerializedItem>,
+    ) -> Result<(), PersistentStoreError> {
+        let mut connection = self.connection.lock();
+        let tx = connection.transaction()?;
+
+        tx.execute(&format!("DELETE FROM {}", self.items_table), [])?;
+        {
+            let mut insert = tx.prepare(&format!(
+                "INSERT INTO {} (kind, key, version, deleted, item) VALUES (?1, ?2, ?3, ?4, ?5)",
+                self.items_table
+            ))?;
+
+            let flags = all_data
+                .flags
+                .iter()
+                .map(|(key, item)| (DataKind::Flag, key, item));
+            let segments = all_data
+                .segments
+                .iter()
+                .map(|(key, item)| (DataKind::Segment, key, item));
+
+            for (kind, key, item) in flags.chain(segments) {
+                insert.execute(params![
+                    kind_name(&kind),
+                    key,
+                    item.version as i64,
+                    item.deleted,
+                    item.serialized_item
+                ])?;
+            }
+        }
+        tx.execute(
+            &format!(
+                "INSERT OR REPLACE INTO {} (key, value) VALUES (?1, ?2)",
+                self.meta_table
+            ),
+            params![INITED_MARKER, ""],
+        )?;
+
+        tx.commit()?;
+
+        Ok(())
+    }
+
+    fn flag(&self, key: &str) -> Result<Option<SerializedItem>, PersistentStoreError> {
+        self.get(DataKind::Flag, key)
+    }
+
+    fn segment(&self, key: &str) -> Result<Option<SerializedItem>, PersistentStoreError> {
+        self.get(DataKind::Segment, key)
+    }
+
+    fn all_flags(&self) -> Result<HashMap<String, SerializedItem>, PersistentStoreError> {
+        let connection = self.connection.lock();
+        let mut statement = connection.prepare(&format!(
+            "SELECT key, version, deleted, item FROM {} WHERE kind = ?1",
+            self.items_table
+        ))?;
+
+        let rows = statement.query_map(params![kind_name(&DataKind::Flag)], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                SerializedItem {
+                    version: row.get::<_, i64>(1)? as u64,
+                    deleted: row.get(2)?,
+                    serialized_item: row.get(3)?,
+                },
+            ))
+        })?;
+
+        let flags = rows.collect::<Result<HashMap<_, _>, _>>()?;
+        Ok(flags)
+    }
+
+    fn upsert(
+        &mut self,
+        kind: DataKind,
+        key: &str,
+        serialized_item: SerializedItem,
+    ) -> Result<bool, PersistentStoreError> {
+        // The conditional update makes the version check and the write a single atomic statement,
+        // so concurrent writers from other processes cannot regress an item's version.
+        let updated = self.connection.lock().execute(
+            &format!(
+                "INSERT INTO {table} (kind, key, version, deleted, item) VALUES (?1, ?2, ?3, ?4, ?5)
+                ON CONFLICT (kind, key) DO UPDATE SET
+                    version = excluded.version,
+                    deleted = excluded.deleted,
+                    item = excluded.item
+                WHERE excluded.version > {table}.version",
+                table = self.items_table
+            ),
+            params![
+                kind_name(&kind),
+                key,
+                serialized_item.version as i64,
+                serialized_item.deleted,
+                serialized_item.serialized_item
+            ],
+        )?;
+
+        Ok(updated > 0)
+    }
+
+    fn is_initialized(&self) -> bool {
+        let connection = self.connection.lock();
+        let result = connection
+            .query_row(
+                &format!("SELECT 1 FROM {} WHERE key = ?1", self.meta_table),
+                params![INITED_MARKER],
+                |_| Ok(()),
+            )
+            .optional();
+
+        match result {
+            Ok(marker) => marker.is_some(),
+            Err(e) => {
+                warn!("sqlite store failed to check initialization state: {}", e);
+                false
+            }
+        }
+    }
+
+    fn remove_tombstone(
+        &mut self,
+        kind: DataKind,
+        key: &str,
+        version: u64,
+    ) -> Result<bool, PersistentStoreError> {
+        let removed = self.connection.lock().execute(
+            &format!(
+                "DELETE FROM {} WHERE kind = ?1 AND key = ?2 AND deleted = 1 AND version = ?3",
+                self.items_table
+            ),
+            params![kind_name(&kind), key, version as i64],
+        )?;
+
+        Ok(removed > 0)
+    }
+}
+
+/// Contains methods for configuring the SQLite persistent data store.
+///
+/// The builder is a [PersistentDataStoreFactory], so it is used together with a
+/// [crate::PersistentDataStoreBuilder], which adds the SDK's caching layer on top of the store.
+///
+/// # Examples
+///
+/// ```
+/// # use launchdarkly_server_sdk::{ConfigBuilder, PersistentDataStoreBuilder, SqlitePersistentDataStoreBuilder};
+/// # use std::sync::Arc;
+/// # fn main() {
+///     let sqlite = SqlitePersistentDataStoreBuilder::new("/var/lib/my-app/launchdarkly.db");
+///     ConfigBuilder::new("sdk-key")
+///         .data_store(PersistentDataStoreBuilder::new(Arc::new(sqlite)).cache_seconds(30));
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct SqlitePersistentDataStoreBuilder {
+    path: PathBuf,
+    prefix: String,
+    busy_timeout: Duration,
+}
+
+impl SqlitePersistentDataStoreBuilder {
+    /// Create a new [SqlitePersistentDataStoreBuilder] which will store data in the database file
+    /// at `path`. The file is created if it does not exist.
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            prefix: DEFAULT_PREFIX.to_string(),
+            busy_timeout: DEFAULT_BUSY_TIMEOUT,
+        }
+    }
+
+    /// Sets the prefix used for the names of the tables the store creates. Use a distinct prefix
+    /// for each LaunchDarkly environment stored in the same database file.
+    ///
+    /// The prefix may only contain ASCII letters, digits, and underscores. The default is
+    /// `launchdarkly`.
+    pub fn prefix(&mut self, prefix: impl Into<String>) -> &mut Self {
+        self.prefix = prefix.into();
+        self
+    }
+
+    /// Sets how long an operation waits for a lock held by another connection before failing.
+    ///
+    /// The default is 5 seconds.
+    pub fn busy_timeout(&mut self, busy_timeout: Duration) -> &mut Self {
+        self.busy_timeout = busy_timeout;
+        self
+    }
+}
+
+impl PersistentDataStoreFactory for SqlitePersistentDataStoreBuilder {
+    fn create_persistent_data_store(&self) -> Result<Box<dyn PersistentDataStore>, std::io::Error> {
+        let store = SqlitePersistentDataStore::open(&self.path, &self.prefix, self.busy_timeout)
+            .map_err(std::io::Error::other)?;
+        Ok(Box::new(store))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use maplit::hashmap;
+    use std::convert::TryFrom;
+
+    use super::*;
+    use crate::stores::store_types::StorageItem;
+    use crate::test_common::{basic_flag, basic_segment};
+    use launchdarkly_server_sdk_evaluation::Flag;
+
+    fn serialized_flag(key: &str, version: u64) -> SerializedItem {
+        let mut flag = basic_flag(key);
+        flag.version = version;
+        SerializedItem::try_from(StorageItem::Item(flag)).unwrap()
+    }
+
+    fn basic_data() -> AllData<SerializedItem, SerializedItem> {
+        AllData {
+            flags: hashmap! {"flag-key".into() => serialized_flag("flag-key", 42)},
+            segments: hashmap! {
+                "segment-key".into() =>
+                    SerializedItem::try_from(StorageItem::Item(basic_segment("segment-key"))).unwrap()
+            },
+        }
+    }
+
+    #[test]
+    fn store_is_not_initialized_until_init_is_called() {
+        let mut store = SqlitePersistentDataStore::in_memory("test").unwrap();
+        assert!(!store.is_initialized());
+
+        store.init(basic_data()).unwrap();
+        assert!(store.is_initialized());
+    }
+
+    #[test]
+    fn init_replaces_all_existing_data() {
+        let mut store = SqlitePersistentDataStore::in_memory("test").unwrap();
+        store.init(basic_data()).unwrap();
+
+        store
+            .init(AllData {
+                flags: hashmap! {"other-flag".into() => serialized_flag("other-flag", 1)},
+                segments: HashMap::new(),
+            })
+            .unwrap();
+
+        assert!(store.flag("flag-key").unwrap().is_none());
+        assert!(store.segment("segment-key").unwrap().is_none());
+        assert_eq!(1, store.flag("other-flag").unwrap().unwrap().version);
+        assert_eq!(1, store.all_flags().unwrap().len());
+    }
+
+    #[test]
+    fn upsert_only_applies_newer_versions() {
+        let mut store = SqlitePersistentDataStore::in_memory("test").unwrap();
+        store.init(basic_data()).unwrap();
+
+        assert!(!store
+            .upsert(DataKind::Flag, "flag-key", serialized_flag("flag-key", 41))
+            .unwrap());
+        assert!(!store
+            .upsert(DataKind::Flag, "flag-key", serialized_flag("flag-key", 42))
+            .unwrap());
+        assert_eq!(42, store.flag("flag-key").unwrap().unwrap().version);
+
+        assert!(store
+            .upsert(DataKind::Flag, "flag-key", serialized_flag("flag-key", 43))
+            .unwrap());
+        assert_eq!(43, store.flag("flag-key").unwrap().unwrap().version);
+
+        assert!(store
+            .upsert(DataKind::Flag, "new-flag", serialized_flag("new-flag", 1))
+            .unwrap());
+        assert_eq!(2, store.all_flags().unwrap().len());
+    }
+
+    #[test]
+    fn tombstones_are_stored_and_returned() {
+        let mut store = SqlitePersistentDataStore::in_memory("test").unwrap();
+        store.init(basic_data()).unwrap();
+
+        let tombstone =
+            SerializedItem::try_from(StorageItem::<crate::Flag>::Tombstone(43)).unwrap();
+        assert!(store.upsert(DataKind::Flag, "flag-key", tombstone).unwrap());
+
+        let item = store.flag("flag-key").unwrap().unwrap();
+        assert!(item.deleted);
+        assert_eq!(43, item.version);
+        assert!(store.all_flags().unwrap()["flag-key"].deleted);
+
+        assert!(!store
+            .upsert(DataKind::Flag, "flag-key", serialized_flag("flag-key", 42))
+            .unwrap());
+        assert!(store.flag("flag-key").unwrap().unwrap().deleted);
+    }
+
+    #[test]
+    fn flags_and_segments_do_not_collide() {
+        let mut store = SqlitePersistentDataStore::in_memory("test").unwrap();
+        store.init(basic_data()).unwrap();
+
+        assert!(store.segment("flag-key").unwrap().is_none());
+        assert!(store.flag("segment-key").unwrap().is_none());
+    }
+
+    #[test]
+    fn remove_tombstone_only_removes_matching_placeholders() {
+        let mut store = SqlitePersistentDataStore::in_memory("test").unwrap();
+        store.init(basic_data()).unwrap();
+
+        assert!(!store
+            .remove_tombstone(DataKind::Flag, "flag-key", 42)
+            .unwrap());
+        assert!(store.flag("flag-key").unwrap().is_some());
+
+        let tombstone = SerializedItem::try_from(StorageItem::<Flag>::Tombstone(43)).unwrap();
+        store.upsert(DataKind::Flag, "flag-key", tombstone).unwrap();
+        assert!(!store
+            .remove_tombstone(DataKind::Flag, "flag-key", 42)
+            .unwrap());
+        assert!(!store
+            .remove_tombstone(DataKind::Segment, "flag-key", 43)
+            .unwrap());
+        assert!(store
+            .remove_tombstone(DataKind::Flag, "flag-key", 43)
+            .unwrap());
+        assert!(store.flag("flag-key").unwrap().is_none());
+    }
+
+    #[test]
+    fn invalid_prefix_is_rejected() {
+        assert!(SqlitePersistentDataStore::in_memory("").is_err());
+        assert!(SqlitePersistentDataStore::in_memory("bad; DROP TABLE").is_err());
+    }
+
+    #[test]
+    fn initialization_is_visible_across_instances() {
+        let path = std::env::temp_dir().join(format!(
+            "launchdarkly-sqlite-test-{}.db",
+            uuid::Uuid::new_v4()
+        ));
+        let builder = SqlitePersistentDataStoreBuilder::new(&path);
+
+        let mut writer = builder.create_persistent_data_store().unwrap();
+        let reader = builder.create_persistent_data_store().unwrap();
+        assert!(!reader.is_initialized());
+
+        writer.init(basic_data()).unwrap();
+        assert!(reader.is_initialized());
+        assert_eq!(42, reader.flag("flag-key").unwrap().unwrap().version);
+
+        drop(writer);
+        drop(reader);
+        for suffix in ["", "-wal", "-shm"] {
+            let _ = std::fs::remove_file(format!("{}{}", path.display(), suffix));
+        }
+    }
+
+    #[cfg(feature = "store-testing")]
+    struct SqliteHarness {
+        path: PathBuf,
+    }
+
+    #[cfg(feature = "store-testing")]
+    impl crate::PersistentDataStoreTestHarness for SqliteHarness {
+        fn create_store(
+            &self,
+            prefix: &str,
+        ) -> Result<Box<dyn PersistentDataStore>, std::io::Error> {
+            let store = SqlitePersistentDataStore::open(&self.path, prefix, DEFAULT_BUSY_TIMEOUT)
+                .map_err(std::io::Error::other)?;
+            Ok(Box::new(store))
+        }
+
+        fn clear_data(&self, prefix: &str) -> Result<(), std::io::Error> {
+            let connection = Connection::open(&self.path).map_err(std::io::Error::other)?;
+            connection
+                .execute_batch(&format!(
+                    "DROP TABLE IF EXISTS {prefix}_items; DROP TABLE IF EXISTS {prefix}_meta;"
+                ))
+                .map_err(std::io::Error::other)
+        }
+    }
+
+    #[cfg(feature = "store-testing")]
+    impl Drop for SqliteHarness {
+        fn drop(&mut self) {
+            for suffix in ["", "-wal", "-shm"] {
+                let _ = std::fs::remove_file(format!("{}{}", self.path.display(), suffix));
+            }
+        }
+    }
+
+    #[cfg(feature = "store-testing")]
+    #[test]
+    fn store_passes_conformance_suite() {
+        let harness = SqliteHarness {
+            path: std::env::temp_dir().join(format!(
+                "launchdarkly-sqlite-conformance-{}.db",
+                uuid::Uuid::new_v4()
+            )),
+        };
+
+        crate::PersistentDataStoreTestSuite::new(harness).run();
+    }
+}
@@ -1,10 +1,15 @@
-use crate::stores::store_types::{AllData, PatchTarget, StorageItem};
-use std::collections::HashMap;
+use crate::stores::store_types::{AllData, DataKind, PatchTarget, StorageItem};
+use futures::future::{join_all, BoxFuture};
+use parking_lot::Mutex;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use std::time::Duration;
 use thiserror::Error;
 
-use launchdarkly_server_sdk_evaluation::{Flag, Segment, Store, Versioned};
+use launchdarkly_server_sdk_evaluation::{self as eval, Context, Flag, Segment, Store, Versioned};
 
 use super::persistent_store::PersistentStoreError;
+use super::tombstones::TombstoneTracker;
 
 #[non_exhaustive]
 #[derive(Debug, Error)]
@@ -29,36 +34,182 @@ pub trait DataStore: Store + Send + Sync {
     fn all_flags(&self) -> HashMap<String, Flag>;
     fn upsert(&mut self, key: &str, data: PatchTarget) -> Result<(), UpdateError>;
     fn to_store(&self) -> &dyn Store;
+
+    /// Returns a handle for reading from the store without blocking, if the store's reads may
+    /// involve I/O. Stores which are always in memory return `None`.
+    fn to_async_store(&self) -> Option<Arc<dyn AsyncStore>> {
+        None
+    }
+
+    /// Returns a copy of every flag and segment, including placeholders for deleted items, if the
+    /// store holds all of its data in memory. Other stores return `None`.
+    fn all_data(&self) -> Option<AllData<StorageItem<Flag>, StorageItem<Segment>>> {
+        None
+    }
+
+    /// Returns a number which changes whenever the store's contents change, so that data derived
+    /// from the store only needs rebuilding once it has changed. Stores which cannot tell when
+    /// their contents change return `None`.
+    fn version(&self) -> Option<u64> {
+        None
+    }
+}
+
+/// Asynchronous counterpart of [Store], used by the asynchronous evaluation methods.
+pub trait AsyncStore: Send + Sync {
+    fn flag<'a>(&'a self, key: &'a str) -> BoxFuture<'a, Option<Flag>>;
+    fn segment<'a>(&'a self, key: &'a str) -> BoxFuture<'a, Option<Segment>>;
+}
+
+/// A [Store] holding the flag being evaluated along with every prerequisite flag and segment its
+/// evaluation depends on.
+///
+/// The evaluation engine reads from a synchronous [Store], so dependencies are loaded from an
+/// [AsyncStore] ahead of time. Evaluation is pure, so the flag is repeatedly evaluated against the
+/// snapshot, recording any lookups the snapshot could not answer, until no lookups are missing.
+#[derive(Default)]
+pub(crate) struct PrefetchedStore {
+    flags: HashMap<String, Option<Flag>>,
+    segments: HashMap<String, Option<Segment>>,
+    missing_flags: Mutex<HashSet<String>>,
+    missing_segments: Mutex<HashSet<String>>,
+}
+
+impl PrefetchedStore {
+    pub(crate) async fn load(store: &dyn AsyncStore, flag_key: &str, context: &Context) -> Self {
+        let mut snapshot = PrefetchedStore::default();
+        let mut flag_keys = vec![flag_key.to_string()];
+        let mut segment_keys: Vec<String> = Vec::new();
+
+        loop {
+            let flags = join_all(flag_keys.iter().map(|key| store.flag(key))).await;
+            snapshot.flags.extend(flag_keys.into_iter().zip(flags));
+
+            let segments = join_all(segment_keys.iter().map(|key| store.segment(key))).await;
+            snapshot
+                .segments
+                .extend(segment_keys.into_iter().zip(segments));
+
+            let flag = match snapshot.flags.get(flag_key) {
+                Some(Some(flag)) => flag,
+                _ => break,
+            };
+            eval::evaluate(&snapshot, flag, context, None);
+
+            flag_keys = snapshot.missing_flags.lock().drain().collect();
+            segment_keys = snapshot.missing_segments.lock().drain().collect();
+            if flag_keys.is_empty() && segment_keys.is_empty() {
+                break;
+            }
+        }
+
+        snapshot
+    }
+}
+
+impl Store for PrefetchedStore {
+    fn flag(&self, flag_key: &str) -> Option<Flag> {
+        match self.flags.get(flag_key) {
+            Some(flag) => flag.clone(),
+            None => {
+                self.missing_flags.lock().insert(flag_key.to_string());
+                None
+            }
+        }
+    }
+
+    fn segment(&self, segment_key: &str) -> Option<Segment> {
+        match self.segments.get(segment_key) {
+            Some(segment) => segment.clone(),
+            None => {
+                self.missing_segments.lock().insert(segment_key.to_string());
+                None
+            }
+        }
+    }
 }
 
 /// Default implementation of [DataStore] which holds information in-memory.
 pub struct InMemoryDataStore {
     pub data: AllData<StorageItem<Flag>, StorageItem<Segment>>,
+    tombstones: TombstoneTracker,
+    version: u64,
 }
 
 impl InMemoryDataStore {
     pub fn new() -> Self {
+        Self::with_tombstone_retention(None)
+    }
+
+    /// Creates a store which discards deleted item placeholders once they are older than
+    /// `retention`. If `retention` is None, placeholders are kept forever.
+    pub fn with_tombstone_retention(retention: Option<Duration>) -> Self {
         Self {
             data: AllData {
                 flags: HashMap::new(),
                 segments: HashMap::new(),
             },
+            tombstones: TombstoneTracker::new(retention),
+            version: 0,
         }
     }
 
     fn upsert_flag(&mut self, key: &str, item: StorageItem<Flag>) {
         match self.data.flags.get(key) {
-            Some(existing) if existing.is_greater_than_or_equal(item.version()) => None,
-            _ => self.data.flags.insert(key.to_string(), item),
+            Some(existing) if existing.is_greater_than_or_equal(item.version()) => (),
+            _ => {
+                if let StorageItem::Tombstone(version) = item {
+                    self.tombstones.track(DataKind::Flag, key, version);
+                }
+                self.data.flags.insert(key.to_string(), item);
+                self.version += 1;
+            }
         };
     }
 
     fn upsert_segment(&mut self, key: &str, item: StorageItem<Segment>) {
         match self.data.segments.get(key) {
-            Some(existing) if existing.is_greater_than_or_equal(item.version()) => None,
-            _ => self.data.segments.insert(key.to_string(), item),
+            Some(existing) if existing.is_greater_than_or_equal(item.version()) => (),
+            _ => {
+                if let StorageItem::Tombstone(version) = item {
+                    self.tombstones.track(DataKind::Segment, key, version);
+                }
+                self.data.segments.insert(key.to_string(), item);
+                self.version += 1;
+            }
         };
     }
+
+    fn purge_expired_tombstones(&mut self) {
+        for expired in self.tombstones.take_expired() {
+            let purged = match expired.kind {
+                DataKind::Flag => {
+                    Self::remove_tombstone(&mut self.data.flags, &expired.key, expired.version)
+                }
+                DataKind::Segment => {
+                    Self::remove_tombstone(&mut self.data.segments, &expired.key, expired.version)
+                }
+            };
+            if purged {
+                self.version += 1;
+                debug!(
+                    "purged deleted {:?} placeholder {}",
+                    expired.kind, expired.key
+                );
+            }
+        }
+    }
+
+    fn remove_tombstone<T>(
+        items: &mut HashMap<String, StorageItem<T>>,
+        key: &str,
+        version: u64,
+    ) -> bool {
+        match items.get(key) {
+            Some(StorageItem::Tombstone(v)) if *v == version => items.remove(key).is_some(),
+            _ => false,
+        }
+    }
 }
 
 impl Store for InMemoryDataStore {
@@ -80,6 +231,8 @@ impl Store for InMemoryDataStore {
 impl DataStore for InMemoryDataStore {
     fn init(&mut self, new_data: AllData<Flag, Segment>) {
         self.data = new_data.into();
+        self.tombstones.clear();
+        self.version += 1;
         debug!("data store has been updated with new flag data");
     }
 
@@ -95,6 +248,8 @@ impl DataStore for InMemoryDataStore {
     }
 
     fn upsert(&mut self, key: &str, data: PatchTarget) -> Result<(), UpdateError> {
+        self.purge_expired_tombstones();
+
         match data {
             PatchTarget::Flag(item) => {
                 self.upsert_flag(key, item);
@@ -114,6 +269,14 @@ impl DataStore for InMemoryDataStore {
     fn to_store(&self) -> &dyn Store {
         self
     }
+
+    fn all_data(&self) -> Option<AllData<StorageItem<Flag>, StorageItem<Segment>>> {
+        Some(self.data.clone())
+    }
+
+    fn version(&self) -> Option<u64> {
+        Some(self.version)
+    }
 }
 
 impl Default for InMemoryDataStore {
@@ -212,6 +375,21 @@ mod tests {
         assert!(data_store.flag("flag-key").is_some());
     }
 
+    #[test]
+    fn in_memory_version_changes_only_with_the_data() {
+        let mut data_store = InMemoryDataStore::new();
+        data_store.init(basic_data());
+        let initialized = data_store.version();
+
+        let patch_target = PatchTarget::Flag(StorageItem::Item(basic_flag("flag-key")));
+        assert!(data_store.upsert("flag-key", patch_target).is_ok());
+        assert_eq!(initialized, data_store.version());
+
+        let patch_target = PatchTarget::Segment(StorageItem::Tombstone(43));
+        assert!(data_store.upsert("segment-key", patch_target).is_ok());
+        assert_ne!(initialized, data_store.version());
+    }
+
     #[test_case(41, 42)]
     #[test_case(43, 43)]
     fn in_memory_patch_does_not_update_flag_with_older_version(
@@ -366,4 +544,78 @@ mod tests {
             .is_ok());
         assert!(data_store.segment("segment-key").is_none());
     }
+
+    #[test]
+    fn in_memory_keeps_tombstones_without_retention() {
+        let mut data_store = InMemoryDataStore::new();
+        data_store.init(basic_data());
+        data_store
+            .upsert("flag-key", PatchTarget::Flag(StorageItem::Tombstone(43)))
+            .unwrap();
+        data_store
+            .upsert("other-key", PatchTarget::Flag(StorageItem::Tombstone(1)))
+            .unwrap();
+
+        assert!(matches!(
+            data_store.data.flags.get("flag-key"),
+            Some(StorageItem::Tombstone(43))
+        ));
+    }
+
+    #[test]
+    fn in_memory_purges_expired_tombstones_on_later_updates() {
+        let mut data_store =
+            InMemoryDataStore::with_tombstone_retention(Some(Duration::from_millis(20)));
+        data_store.init(basic_data());
+        data_store
+            .upsert("flag-key", PatchTarget::Flag(StorageItem::Tombstone(43)))
+            .unwrap();
+        data_store
+            .upsert(
+                "segment-key",
+                PatchTarget::Segment(StorageItem::Tombstone(2)),
+            )
+            .unwrap();
+
+        // Out of order updates are still rejected while the tombstone is retained.
+        data_store
+            .upsert(
+                "flag-key",
+                PatchTarget::Flag(StorageItem::Item(basic_flag("flag-key"))),
+            )
+            .unwrap();
+        assert!(data_store.flag("flag-key").is_none());
+
+        std::thread::sleep(Duration::from_millis(30));
+        data_store
+            .upsert("other-key", PatchTarget::Flag(StorageItem::Tombstone(1)))
+            .unwrap();
+
+        assert!(!data_store.data.flags.contains_key("flag-key"));
+        assert!(!data_store.data.segments.contains_key("segment-key"));
+        assert!(data_store.data.flags.contains_key("other-key"));
+    }
+
+    #[test]
+    fn in_memory_does_not_purge_recreated_items() {
+        let mut data_store =
+            InMemoryDataStore::with_tombstone_retention(Some(Duration::from_millis(20)));
+        data_store.init(basic_data());
+        data_store
+            .upsert("flag-key", PatchTarget::Flag(StorageItem::Tombstone(43)))
+            .unwrap();
+
+        let mut recreated = basic_flag("flag-key");
+        recreated.version = 44;
+        data_store
+            .upsert("flag-key", PatchTarget::Flag(StorageItem::Item(recreated)))
+            .unwrap();
+
+        std::thread::sleep(Duration::from_millis(30));
+        data_store
+            .upsert("other-key", PatchTarget::Flag(StorageItem::Tombstone(1)))
+            .unwrap();
+
+        assert_eq!(44, data_store.flag("flag-key").unwrap().version);
+    }
 }
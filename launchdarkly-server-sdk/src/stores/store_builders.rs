@@ -1,6 +1,7 @@
 use super::store::{DataStore, InMemoryDataStore};
 use parking_lot::RwLock;
 use std::sync::Arc;
+use std::time::Duration;
 use thiserror::Error;
 
 #[non_exhaustive]
@@ -19,18 +20,38 @@ pub trait DataStoreFactory {
 /// Contains methods for configuring the in memory data store.
 ///
 /// By default, the SDK uses an in memory store to manage flag and segment data.
-#[derive(Clone)]
-pub struct InMemoryDataStoreBuilder {}
+#[derive(Clone, Default)]
+pub struct InMemoryDataStoreBuilder {
+    tombstone_retention: Option<Duration>,
+}
 
 impl InMemoryDataStoreBuilder {
+    /// Create a new [InMemoryDataStoreBuilder] with the default configuration.
     pub fn new() -> Self {
-        Self {}
+        Self {
+            tombstone_retention: None,
+        }
+    }
+
+    /// Specifies how long to keep the placeholders which record that a flag or segment was
+    /// deleted. By default, placeholders are kept until the next full data set replaces the
+    /// store's contents.
+    ///
+    /// A placeholder stops an update which was delayed in transit from resurrecting an item that
+    /// has since been deleted. Once it has been purged, such an update would be applied, so the
+    /// retention should comfortably exceed the longest delay you expect between updates being
+    /// sent and received. Placeholders are purged as later updates arrive.
+    pub fn tombstone_retention(&mut self, retention: Duration) -> &mut Self {
+        self.tombstone_retention = Some(retention);
+        self
     }
 }
 
 impl DataStoreFactory for InMemoryDataStoreBuilder {
     fn build(&self) -> Result<Arc<RwLock<dyn DataStore>>, BuildError> {
-        Ok(Arc::new(RwLock::new(InMemoryDataStore::new())))
+        Ok(Arc::new(RwLock::new(
+            InMemoryDataStore::with_tombstone_retention(self.tombstone_retention),
+        )))
     }
 
     fn to_owned(&self) -> Box<dyn DataStoreFactory> {
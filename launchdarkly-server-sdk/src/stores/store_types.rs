@@ -55,7 +55,7 @@ impl<T: Versioned> Versioned for StorageItem<T> {
 }
 
 /// Used to hold store information and initial payloads from LaunchDarkly.
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct AllData<F, S> {
     /// All flag information indexed by flag key.
     pub flags: HashMap<String, F>,
@@ -118,6 +118,7 @@ impl TryFrom<AllData<Flag, Segment>> for AllData<SerializedItem, SerializedItem>
 }
 
 /// Enum which denotes the kind of data that may be persisted in our data stores.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum DataKind {
     /// A feature flag
     Flag,
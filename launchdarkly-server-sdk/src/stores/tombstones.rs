@@ -0,0 +1,127 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+use super::store_types::DataKind;
+
+/// A deleted item placeholder which may be purged once its retention window has passed.
+#[derive(Debug, PartialEq)]
+pub(super) struct ExpiredTombstone {
+    pub kind: DataKind,
+    pub key: String,
+    pub version: u64,
+}
+
+struct TrackedTombstone {
+    deleted_at: Instant,
+    tombstone: ExpiredTombstone,
+}
+
+/// Remembers when deleted item placeholders were written, so they can be purged once they are
+/// older than the configured retention window.
+///
+/// Placeholders exist so that a patch which was delayed in transit cannot resurrect an item that
+/// has since been deleted. Once a placeholder has been retained for longer than any patch could
+/// plausibly be delayed, it no longer serves that purpose.
+pub(super) struct TombstoneTracker {
+    retention: Option<Duration>,
+    // Ordered by deletion time, since entries are only ever appended.
+    pending: VecDeque<TrackedTombstone>,
+}
+
+impl TombstoneTracker {
+    /// Creates a tracker which reports placeholders once they are older than `retention`. If
+    /// `retention` is None, placeholders are retained forever and nothing is tracked.
+    pub fn new(retention: Option<Duration>) -> Self {
+        Self {
+            retention,
+            pending: VecDeque::new(),
+        }
+    }
+
+    pub fn track(&mut self, kind: DataKind, key: &str, version: u64) {
+        if self.retention.is_none() {
+            return;
+        }
+
+        self.pending.push_back(TrackedTombstone {
+            deleted_at: Instant::now(),
+            tombstone: ExpiredTombstone {
+                kind,
+                key: key.to_string(),
+                version,
+            },
+        });
+    }
+
+    /// Removes and returns every tracked placeholder which has outlived the retention window.
+    ///
+    /// Callers must only purge a placeholder if the store still holds it at the returned version;
+    /// the item may have been recreated, or deleted again, since.
+    pub fn take_expired(&mut self) -> Vec<ExpiredTombstone> {
+        let retention = match self.retention {
+            Some(retention) => retention,
+            None => return Vec::new(),
+        };
+
+        let mut expired = Vec::new();
+        while let Some(tracked) = self.pending.front() {
+            if tracked.deleted_at.elapsed() < retention {
+                break;
+            }
+            if let Some(tracked) = self.pending.pop_front() {
+                expired.push(tracked.tombstone);
+            }
+        }
+        expired
+    }
+
+    /// Forgets every tracked placeholder. Called when a full data set replaces the store's
+    /// contents, which discards any existing placeholders.
+    pub fn clear(&mut self) {
+        self.pending.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nothing_is_tracked_without_retention() {
+        let mut tracker = TombstoneTracker::new(None);
+        tracker.track(DataKind::Flag, "flag", 1);
+
+        assert!(tracker.pending.is_empty());
+        assert!(tracker.take_expired().is_empty());
+    }
+
+    #[test]
+    fn tombstones_expire_after_retention() {
+        let mut tracker = TombstoneTracker::new(Some(Duration::from_millis(20)));
+        tracker.track(DataKind::Flag, "flag", 1);
+        assert!(tracker.take_expired().is_empty());
+
+        std::thread::sleep(Duration::from_millis(30));
+        tracker.track(DataKind::Segment, "segment", 2);
+
+        assert_eq!(
+            vec![ExpiredTombstone {
+                kind: DataKind::Flag,
+                key: "flag".to_string(),
+                version: 1
+            }],
+            tracker.take_expired()
+        );
+        assert!(tracker.take_expired().is_empty());
+        assert_eq!(1, tracker.pending.len());
+    }
+
+    #[test]
+    fn clear_forgets_everything() {
+        let mut tracker = TombstoneTracker::new(Some(Duration::ZERO));
+        tracker.track(DataKind::Flag, "flag", 1);
+        tracker.clear();
+
+        assert!(tracker.take_expired().is_empty());
+    }
+}
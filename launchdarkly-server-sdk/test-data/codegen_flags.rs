@@ -0,0 +1,190 @@
+// This module was generated from a LaunchDarkly environment snapshot; do not edit it by
+// hand. Regenerate it when flags are added to or removed from the environment.
+
+/// Evaluates the "2x-ratio" flag.
+pub fn flag_2x_ratio(
+    client: &::launchdarkly_server_sdk::Client,
+    context: &::launchdarkly_server_sdk::Context,
+) -> f64 {
+    client.float_variation(context, "2x-ratio", 1.5_f64)
+}
+
+/// The variations of the "client" flag.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Client {
+    /// The "web" variation.
+    Web,
+    /// The "mobile" variation.
+    Mobile,
+}
+
+impl Client {
+    /// The value of the variation.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Client::Web => "web",
+            Client::Mobile => "mobile",
+        }
+    }
+
+    /// Returns the variant for the given value, if it is one of the flag's variations.
+    pub fn from_variation(value: &str) -> ::core::option::Option<Self> {
+        match value {
+            "web" => ::core::option::Option::Some(Client::Web),
+            "mobile" => ::core::option::Option::Some(Client::Mobile),
+            _ => ::core::option::Option::None,
+        }
+    }
+}
+
+/// Evaluates the "client" flag.
+pub fn client(
+    client: &::launchdarkly_server_sdk::Client,
+    context: &::launchdarkly_server_sdk::Context,
+) -> Client {
+    let default = Client::Mobile;
+    let value = client.str_variation(context, "client", default.as_str().to_string());
+    Client::from_variation(&value).unwrap_or(default)
+}
+
+/// Evaluates the "config" flag.
+pub fn config(
+    client: &::launchdarkly_server_sdk::Client,
+    context: &::launchdarkly_server_sdk::Context,
+) -> ::serde_json::Value {
+    client.json_variation(context, "config", ::serde_json::json!({"retries":3}))
+}
+
+/// The variations of the "context" flag.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Context {
+    /// The "user" variation.
+    User,
+    /// The "org" variation.
+    Org,
+}
+
+impl Context {
+    /// The value of the variation.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Context::User => "user",
+            Context::Org => "org",
+        }
+    }
+
+    /// Returns the variant for the given value, if it is one of the flag's variations.
+    pub fn from_variation(value: &str) -> ::core::option::Option<Self> {
+        match value {
+            "user" => ::core::option::Option::Some(Context::User),
+            "org" => ::core::option::Option::Some(Context::Org),
+            _ => ::core::option::Option::None,
+        }
+    }
+}
+
+/// Evaluates the "context" flag.
+pub fn context(
+    client: &::launchdarkly_server_sdk::Client,
+    context: &::launchdarkly_server_sdk::Context,
+) -> Context {
+    let default = Context::User;
+    let value = client.str_variation(context, "context", default.as_str().to_string());
+    Context::from_variation(&value).unwrap_or(default)
+}
+
+/// Evaluates the "new-checkout" flag.
+pub fn new_checkout(
+    client: &::launchdarkly_server_sdk::Client,
+    context: &::launchdarkly_server_sdk::Context,
+) -> bool {
+    client.bool_variation(context, "new-checkout", true)
+}
+
+/// The variations of the "option" flag.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Option {
+    /// The "some" variation.
+    Some,
+    /// The "none" variation.
+    None,
+}
+
+impl Option {
+    /// The value of the variation.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Option::Some => "some",
+            Option::None => "none",
+        }
+    }
+
+    /// Returns the variant for the given value, if it is one of the flag's variations.
+    pub fn from_variation(value: &str) -> ::core::option::Option<Self> {
+        match value {
+            "some" => ::core::option::Option::Some(Option::Some),
+            "none" => ::core::option::Option::Some(Option::None),
+            _ => ::core::option::Option::None,
+        }
+    }
+}
+
+/// Evaluates the "option" flag.
+pub fn option(
+    client: &::launchdarkly_server_sdk::Client,
+    context: &::launchdarkly_server_sdk::Context,
+) -> Option {
+    let default = Option::Some;
+    let value = client.str_variation(context, "option", default.as_str().to_string());
+    Option::from_variation(&value).unwrap_or(default)
+}
+
+/// The variations of the "theme" flag.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Theme {
+    /// The "Light" variation.
+    Light,
+    /// The "light" variation.
+    Light2,
+    /// The "Light1" variation.
+    Light1,
+}
+
+impl Theme {
+    /// The value of the variation.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Theme::Light => "Light",
+            Theme::Light2 => "light",
+            Theme::Light1 => "Light1",
+        }
+    }
+
+    /// Returns the variant for the given value, if it is one of the flag's variations.
+    pub fn from_variation(value: &str) -> ::core::option::Option<Self> {
+        match value {
+            "Light" => ::core::option::Option::Some(Theme::Light),
+            "light" => ::core::option::Option::Some(Theme::Light2),
+            "Light1" => ::core::option::Option::Some(Theme::Light1),
+            _ => ::core::option::Option::None,
+        }
+    }
+}
+
+/// Evaluates the "theme" flag.
+pub fn theme(
+    client: &::launchdarkly_server_sdk::Client,
+    context: &::launchdarkly_server_sdk::Context,
+) -> Theme {
+    let default = Theme::Light2;
+    let value = client.str_variation(context, "theme", default.as_str().to_string());
+    Theme::from_variation(&value).unwrap_or(default)
+}
+
+/// Evaluates the "type" flag.
+pub fn type_flag(
+    client: &::launchdarkly_server_sdk::Client,
+    context: &::launchdarkly_server_sdk::Context,
+) -> i64 {
+    client.int_variation(context, "type", 1)
+}
@@ -0,0 +1,39 @@
+use launchdarkly_server_sdk::{generate_flag_module, Client, ConfigBuilder, ContextBuilder};
+use serde_json::json;
+
+// Generated by `ld-flag-codegen test-data/codegen-snapshot.json test-data/codegen_flags.rs`. The
+// snapshot names flags after the imports and prelude types a generated module could clash with.
+#[allow(dead_code)]
+mod flags {
+    include!("../test-data/codegen_flags.rs");
+}
+
+#[test]
+fn fixture_matches_generated_module() {
+    let module = generate_flag_module(include_str!("../test-data/codegen-snapshot.json"))
+        .expect("snapshot should generate a module");
+    assert_eq!(include_str!("../test-data/codegen_flags.rs"), module);
+}
+
+#[tokio::test]
+async fn generated_accessors_return_off_variations_by_default() {
+    let config = ConfigBuilder::new("sdk-key")
+        .offline(true)
+        .build()
+        .expect("config should build");
+    let client = Client::build(config).expect("client should build");
+    client.start_with_default_executor();
+    let context = ContextBuilder::new("user-key")
+        .build()
+        .expect("context should build");
+
+    assert_eq!(flags::Client::Mobile, flags::client(&client, &context));
+    assert_eq!(flags::Context::User, flags::context(&client, &context));
+    assert_eq!(flags::Option::Some, flags::option(&client, &context));
+    assert_eq!(flags::Theme::Light2, flags::theme(&client, &context));
+    assert_eq!("Light1", flags::Theme::Light1.as_str());
+    assert!(flags::new_checkout(&client, &context));
+    assert_eq!(1, flags::type_flag(&client, &context));
+    assert_eq!(1.5, flags::flag_2x_ratio(&client, &context));
+    assert_eq!(json!({"retries": 3}), flags::config(&client, &context));
+}
@@ -0,0 +1,173 @@
+use launchdarkly_server_sdk::{
+    AttributeValue, Context, ContextBuilder, IntoContext, MultiContextBuilder, Reference,
+};
+
+#[derive(IntoContext)]
+#[context(kind = "org")]
+struct Organization {
+    #[context(key)]
+    id: i64,
+    #[context(name)]
+    name: String,
+}
+
+#[derive(IntoContext)]
+struct User {
+    #[context(key)]
+    id: String,
+    #[context(name, private)]
+    display_name: Option<String>,
+    #[context(anonymous)]
+    guest: bool,
+    #[context(private)]
+    email: String,
+    #[context(rename = "plan")]
+    tier: Option<String>,
+    age: i64,
+    #[context(skip)]
+    #[allow(dead_code)]
+    session_token: String,
+}
+
+#[derive(IntoContext)]
+struct Member {
+    #[context(key)]
+    id: String,
+    #[context(nested)]
+    organization: Organization,
+    #[context(nested)]
+    team: Option<Team>,
+}
+
+#[derive(IntoContext)]
+struct Team {
+    #[context(key)]
+    id: String,
+    #[context(kind)]
+    kind: String,
+}
+
+// Stands in for the SDK being depended on under another name.
+mod renamed {
+    pub use launchdarkly_server_sdk as sdk;
+}
+
+#[derive(IntoContext)]
+#[context(crate = "renamed::sdk", kind = "device")]
+struct Device {
+    #[context(key)]
+    id: String,
+}
+
+fn user() -> User {
+    User {
+        id: "user-key".to_string(),
+        display_name: Some("Bob".to_string()),
+        guest: false,
+        email: "bob@example.com".to_string(),
+        tier: None,
+        age: 42,
+        session_token: "secret".to_string(),
+    }
+}
+
+#[test]
+fn maps_fields_to_attributes() {
+    let context = Context::try_from(&user()).expect("context should build");
+
+    let expected = ContextBuilder::new("user-key")
+        .name("Bob")
+        .anonymous(false)
+        .set_string("email", "bob@example.com")
+        .set_value("age", AttributeValue::from(42_i64))
+        .add_private_attribute("name")
+        .add_private_attribute("email")
+        .build()
+        .expect("context should build");
+    assert_eq!(expected, context);
+    assert_eq!(None, context.get_value(&Reference::new("session_token")));
+    assert_eq!(None, context.get_value(&Reference::new("plan")));
+}
+
+#[test]
+fn renames_optional_attributes_with_values() {
+    let user = User {
+        tier: Some("gold".to_string()),
+        ..user()
+    };
+    let context = Context::try_from(&user).expect("context should build");
+
+    assert_eq!(
+        Some(AttributeValue::String("gold".to_string())),
+        context.get_value(&Reference::new("plan"))
+    );
+    assert_eq!(None, context.get_value(&Reference::new("tier")));
+}
+
+#[test]
+fn uses_kind_from_struct() {
+    let organization = Organization {
+        id: 7,
+        name: "LaunchDarkly".to_string(),
+    };
+    let context = Context::try_from(&organization).expect("context should build");
+
+    assert_eq!(context.kind(), "org");
+    assert_eq!("7", context.key());
+}
+
+#[test]
+fn uses_the_given_sdk_path() {
+    let device = Device {
+        id: "device-key".to_string(),
+    };
+    let context = Context::try_from(&device).expect("context should build");
+
+    assert_eq!(context.kind(), "device");
+    assert_eq!("device-key", context.key());
+}
+
+#[test]
+fn nested_fields_produce_a_multi_context() {
+    let member = Member {
+        id: "member-key".to_string(),
+        organization: Organization {
+            id: 7,
+            name: "LaunchDarkly".to_string(),
+        },
+        team: Some(Team {
+            id: "team-key".to_string(),
+            kind: "team".to_string(),
+        }),
+    };
+    let context = Context::try_from(&member).expect("context should build");
+
+    let expected = MultiContextBuilder::new()
+        .add_context(ContextBuilder::new("member-key").build().unwrap())
+        .add_context(
+            ContextBuilder::new("7")
+                .kind("org")
+                .name("LaunchDarkly")
+                .build()
+                .unwrap(),
+        )
+        .add_context(
+            ContextBuilder::new("team-key")
+                .kind("team")
+                .build()
+                .unwrap(),
+        )
+        .build()
+        .expect("context should build");
+    assert_eq!(expected, context);
+}
+
+#[test]
+fn invalid_contexts_are_reported() {
+    let team = Team {
+        id: "team-key".to_string(),
+        kind: "kind".to_string(),
+    };
+
+    assert!(Context::try_from(&team).is_err());
+}